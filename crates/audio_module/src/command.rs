@@ -1,7 +1,31 @@
+use std::fmt;
+
 pub enum Command {
     SetParameter(usize, f32),
+    /// Sets a parameter's value at a frame offset within the next processed block.
+    SetParameterAt {
+        id: usize,
+        value: f32,
+        frame_offset: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommandError {
+    /// The command referred to a parameter id that isn't provided by the module.
+    UnknownParameter(usize),
 }
 
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownParameter(id) => write!(f, "unknown parameter id: {id}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
 pub trait CommandHandler {
-    fn handle_command(&mut self, command: Command);
+    fn handle_command(&mut self, command: Command) -> Result<(), CommandError>;
 }
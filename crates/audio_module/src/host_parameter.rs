@@ -0,0 +1,198 @@
+use crate::{Parameter, ParameterProvider, StringConverter, ValueConverter, ValueType};
+
+/// Converts between a parameter's user values and the normalized range `0..=1`, as used by
+/// plugin hosts.
+///
+/// Float parameters are normalized via their value converter, so that automation follows e.g. a
+/// decibel taper. Bool, int, and choice parameters are divided into equal steps across their user
+/// range, with user values being rounded to the nearest step.
+///
+/// Converters are `Send` so that they can be used on the audio thread.
+pub struct NormalizedValueConverter {
+    value_type: ValueType,
+    value_converter: Box<dyn ValueConverter>,
+    min: f32,
+    max: f32,
+}
+
+impl NormalizedValueConverter {
+    pub fn new(parameter: &dyn Parameter) -> Self {
+        let (min, max) = parameter.user_range();
+        Self {
+            value_type: parameter.value_type(),
+            value_converter: parameter.make_value_converter(),
+            min,
+            max,
+        }
+    }
+
+    /// Makes the converters for all of a module's parameters, indexed by parameter index.
+    pub fn all<Provider: ParameterProvider>() -> Vec<Self> {
+        (0..Provider::parameter_count())
+            .filter_map(Provider::parameter)
+            .map(|parameter| Self::new(parameter.as_ref()))
+            .collect()
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Returns the lowest and highest user values, see [Parameter::user_range].
+    pub fn user_range(&self) -> (f32, f32) {
+        (self.min, self.max)
+    }
+
+    /// Returns the number of steps between the lowest and highest user values, or `None` if the
+    /// parameter is continuous.
+    pub fn step_count(&self) -> Option<u32> {
+        match self.value_type {
+            ValueType::Float => None,
+            ValueType::Bool | ValueType::Int { .. } | ValueType::Choice(_) => {
+                Some((self.max - self.min).round() as u32)
+            }
+        }
+    }
+
+    /// Clamps a user value to the parameter's range, rounding stepped values.
+    pub fn constrain(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        match self.value_type {
+            ValueType::Float => value,
+            ValueType::Bool | ValueType::Int { .. } | ValueType::Choice(_) => value.round(),
+        }
+    }
+
+    /// Converts a user value into a normalized value.
+    pub fn user_to_normalized(&self, value: f32) -> f32 {
+        match self.step_count() {
+            None => self.value_converter.user_to_linear(value).clamp(0.0, 1.0),
+            Some(0) => 0.0,
+            Some(steps) => (self.constrain(value) - self.min) / steps as f32,
+        }
+    }
+
+    /// Converts a normalized value into a user value.
+    pub fn normalized_to_user(&self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        match self.step_count() {
+            None => self.value_converter.linear_to_user(value),
+            Some(steps) => self.constrain(self.min + (value * steps as f32).round()),
+        }
+    }
+}
+
+/// A module parameter as it's presented to a plugin host.
+///
+/// The parameter's converters are made once, so that they can be used when the host queries the
+/// parameter, see [NormalizedValueConverter].
+pub struct HostParameter {
+    parameter: Box<dyn Parameter>,
+    converter: NormalizedValueConverter,
+    string_converter: Box<dyn StringConverter>,
+    name: String,
+    unit: String,
+}
+
+impl HostParameter {
+    pub fn new(parameter: Box<dyn Parameter>) -> Self {
+        Self {
+            converter: NormalizedValueConverter::new(parameter.as_ref()),
+            string_converter: parameter.make_string_converter(),
+            name: parameter.name(),
+            unit: parameter.unit(),
+            parameter,
+        }
+    }
+
+    /// Makes the host parameters for all of a module's parameters, indexed by parameter index.
+    pub fn all<Provider: ParameterProvider>() -> Vec<Self> {
+        (0..Provider::parameter_count())
+            .filter_map(Provider::parameter)
+            .map(Self::new)
+            .collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn unit(&self) -> &str {
+        &self.unit
+    }
+
+    pub fn default_user_value(&self) -> f32 {
+        self.parameter.default_user_value()
+    }
+
+    pub fn converter(&self) -> &NormalizedValueConverter {
+        &self.converter
+    }
+
+    /// Returns the display string for a user value.
+    pub fn user_to_string(&self, value: f32) -> String {
+        self.string_converter.to_string(value)
+    }
+
+    /// Parses a display string or plain user value, see [Parameter::parse_user_value].
+    pub fn parse_user_value(&self, text: &str) -> Option<f32> {
+        self.parameter.parse_user_value(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{
+            BoolParameter, ChoiceParameter, FloatParameter, IntParameter, percent_string_converter,
+            skew_value_converter,
+        },
+    };
+
+    #[test]
+    fn float_parameters_are_continuous() {
+        let parameter = FloatParameter::new("hold", "Hold")
+            .range(0.0, 1000.0)
+            .center_user_value(250.0)
+            .value_converter(skew_value_converter);
+        let converter = NormalizedValueConverter::new(&parameter);
+        assert_eq!(converter.step_count(), None);
+        assert!((converter.normalized_to_user(0.5) - 250.0).abs() < 1.0e-3);
+        assert_eq!(converter.normalized_to_user(2.0), 1000.0);
+        assert_eq!(converter.user_to_normalized(-10.0), 0.0);
+    }
+
+    #[test]
+    fn stepped_parameters() {
+        let freeze = NormalizedValueConverter::new(&BoolParameter::new("freeze", "Freeze"));
+        assert_eq!(freeze.step_count(), Some(1));
+        assert_eq!(freeze.normalized_to_user(0.7), 1.0);
+
+        let pitch = IntParameter::new("pitch", "Pitch").range(-12, 12);
+        let pitch = NormalizedValueConverter::new(&pitch);
+        assert_eq!(pitch.step_count(), Some(24));
+        assert_eq!(pitch.user_range(), (-12.0, 12.0));
+        assert_eq!(pitch.user_to_normalized(0.0), 0.5);
+        assert_eq!(pitch.user_to_normalized(6.4), 0.75);
+        assert_eq!(pitch.normalized_to_user(0.25), -6.0);
+        assert_eq!(pitch.constrain(20.0), 12.0);
+
+        let mode = ChoiceParameter::new("mode", "Mode", &["A", "B", "C"]);
+        let mode = NormalizedValueConverter::new(&mode);
+        assert_eq!(mode.step_count(), Some(2));
+        assert_eq!(mode.normalized_to_user(0.6), 1.0);
+        assert_eq!(mode.user_to_normalized(2.0), 1.0);
+    }
+
+    #[test]
+    fn strings() {
+        let parameter = HostParameter::new(Box::new(
+            FloatParameter::new("size", "Size").string_converter(percent_string_converter),
+        ));
+        assert_eq!(parameter.name(), "Size");
+        assert_eq!(parameter.user_to_string(0.75), "75 %");
+        assert_eq!(parameter.parse_user_value("25 %"), Some(0.25));
+        assert_eq!(parameter.parse_user_value("150 %"), None);
+    }
+}
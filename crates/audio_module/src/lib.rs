@@ -1,15 +1,27 @@
 mod command;
+mod host_parameter;
+mod midi;
 mod module;
+mod notification;
 mod parameter;
+mod parameter_store;
+mod preset;
 mod processor;
+mod state_history;
 mod string_converter;
 mod value_converter;
 
 pub use {
-    command::{Command, CommandHandler},
+    command::{Command, CommandError, CommandHandler},
+    host_parameter::{HostParameter, NormalizedValueConverter},
+    midi::{Controller, MidiMapping, MidiMappingError, MidiMessage, SUSTAIN_CONTROLLER},
     module::{AudioModule, ParameterProvider},
+    notification::{Notification, NotificationReceiver, NotificationSender, notification_channel},
     parameter::*,
+    parameter_store::ParameterStore,
+    preset::{AssignmentError, Preset, PresetError, PresetProvider, parse_assignment},
     processor::AudioProcessor,
+    state_history::{Slot, StateHistory},
     string_converter::*,
     value_converter::*,
 };
@@ -0,0 +1,499 @@
+use {
+    crate::{Command, ParameterProvider},
+    std::{collections::BTreeMap, fmt},
+};
+
+/// The controller number of the sustain pedal.
+pub const SUSTAIN_CONTROLLER: u8 = 64;
+
+/// The MIDI messages that are used for controlling parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MidiMessage {
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: u8,
+        note: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+}
+
+impl MidiMessage {
+    /// Parses a MIDI message from its bytes, returning `None` for other kinds of messages.
+    ///
+    /// Note-on messages with a velocity of 0 are treated as note-off messages.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let [status, data1, data2, ..] = *bytes else {
+            return None;
+        };
+        let channel = status & 0x0f;
+        let (data1, data2) = (data1 & 0x7f, data2 & 0x7f);
+
+        match status & 0xf0 {
+            0x80 => Some(Self::NoteOff {
+                channel,
+                note: data1,
+            }),
+            0x90 if data2 == 0 => Some(Self::NoteOff {
+                channel,
+                note: data1,
+            }),
+            0x90 => Some(Self::NoteOn {
+                channel,
+                note: data1,
+                velocity: data2,
+            }),
+            0xb0 => Some(Self::ControlChange {
+                channel,
+                controller: data1,
+                value: data2,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Identifies a controller on a MIDI channel, with channels numbered from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Controller {
+    pub channel: u8,
+    pub controller: u8,
+}
+
+/// Maps MIDI control changes to parameters, with parameters referred to by their string ids.
+///
+/// Control change values are converted to user values via the parameter's value converter. A
+/// 'hold' parameter can also be set, which is switched on while any notes or the sustain pedal
+/// are held down.
+///
+/// Mappings can be learned by calling [MidiMapping::learn], after which the next control change
+/// that's received is mapped to the parameter.
+#[derive(Clone, Debug, Default)]
+pub struct MidiMapping {
+    controllers: BTreeMap<Controller, String>,
+    hold_parameter: Option<String>,
+    learning: Option<usize>,
+    // A bit for each held note number
+    held_notes: u128,
+    sustain: bool,
+    hold_active: bool,
+}
+
+impl MidiMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps a controller to the parameter with the given string id, replacing any existing mapping
+    /// for the controller.
+    pub fn map(&mut self, controller: Controller, parameter_id: &str) {
+        self.controllers
+            .insert(controller, parameter_id.to_string());
+    }
+
+    pub fn unmap(&mut self, controller: Controller) {
+        self.controllers.remove(&controller);
+    }
+
+    /// Returns the controller mappings, ordered by channel and controller number.
+    pub fn mappings(&self) -> impl Iterator<Item = (Controller, &str)> {
+        self.controllers
+            .iter()
+            .map(|(controller, id)| (*controller, id.as_str()))
+    }
+
+    /// Sets the parameter that's switched on while notes or the sustain pedal are held.
+    pub fn set_hold_parameter(&mut self, parameter_id: Option<&str>) {
+        self.hold_parameter = parameter_id.map(str::to_string);
+    }
+
+    /// Maps the next control change that's received to the parameter with the given index.
+    pub fn learn(&mut self, parameter_index: usize) {
+        self.learning = Some(parameter_index);
+    }
+
+    pub fn cancel_learn(&mut self) {
+        self.learning = None;
+    }
+
+    /// Returns the index of the parameter that's waiting for a control change to be learned.
+    pub fn learning(&self) -> Option<usize> {
+        self.learning
+    }
+
+    /// Handles a MIDI message, returning the command that should be sent to the processor.
+    pub fn handle<Provider: ParameterProvider>(&mut self, message: MidiMessage) -> Option<Command> {
+        match message {
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => {
+                let controller_id = Controller {
+                    channel,
+                    controller,
+                };
+
+                if let Some(index) = self.learning.take() {
+                    let parameter = Provider::parameter(index)?;
+                    self.map(controller_id, parameter.id());
+                }
+
+                match self.controllers.get(&controller_id) {
+                    Some(id) => {
+                        let index = Provider::parameter_index(id)?;
+                        let converter = Provider::parameter(index)?.make_value_converter();
+                        let user_value = converter.linear_to_user(value as f32 / 127.0);
+                        Some(Command::SetParameter(index, user_value))
+                    }
+                    None if controller == SUSTAIN_CONTROLLER => {
+                        self.sustain = value >= 64;
+                        self.update_hold::<Provider>()
+                    }
+                    None => None,
+                }
+            }
+            // Note numbers outside of the MIDI range are ignored
+            MidiMessage::NoteOn { note, .. } => {
+                self.held_notes |= 1u128.checked_shl(note.into())?;
+                self.update_hold::<Provider>()
+            }
+            MidiMessage::NoteOff { note, .. } => {
+                self.held_notes &= !1u128.checked_shl(note.into())?;
+                self.update_hold::<Provider>()
+            }
+        }
+    }
+
+    fn update_hold<Provider: ParameterProvider>(&mut self) -> Option<Command> {
+        let active = self.held_notes != 0 || self.sustain;
+        if active == self.hold_active {
+            return None;
+        }
+        self.hold_active = active;
+
+        let index = Provider::parameter_index(self.hold_parameter.as_deref()?)?;
+        Some(Command::SetParameter(index, if active { 1.0 } else { 0.0 }))
+    }
+
+    /// Parses mappings from their text format.
+    ///
+    /// The format consists of `cc <channel> <controller> = <parameter id>` lines, with channels
+    /// numbered from 1, along with an optional `hold = <parameter id>` line. Empty lines and lines
+    /// starting with `#` are ignored.
+    pub fn from_text<Provider: ParameterProvider>(text: &str) -> Result<Self, MidiMappingError> {
+        let mut mapping = Self::default();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, id) = line
+                .split_once('=')
+                .ok_or(MidiMappingError::InvalidLine(line_number))?;
+            let (key, id) = (key.trim(), id.trim());
+
+            if Provider::parameter_index(id).is_none() {
+                return Err(MidiMappingError::UnknownParameter {
+                    line: line_number,
+                    id: id.to_string(),
+                });
+            }
+
+            let words: Vec<&str> = key.split_whitespace().collect();
+            match words.as_slice() {
+                ["hold"] => mapping.set_hold_parameter(Some(id)),
+                ["cc", channel, controller] => {
+                    let channel = channel
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|channel| (1..=16).contains(channel))
+                        .ok_or(MidiMappingError::InvalidLine(line_number))?;
+                    let controller = controller
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|controller| *controller < 128)
+                        .ok_or(MidiMappingError::InvalidLine(line_number))?;
+                    mapping.map(
+                        Controller {
+                            channel: channel - 1,
+                            controller,
+                        },
+                        id,
+                    );
+                }
+                _ => return Err(MidiMappingError::InvalidLine(line_number)),
+            }
+        }
+
+        Ok(mapping)
+    }
+
+    /// Writes the mappings in the text format that's read by [MidiMapping::from_text].
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if let Some(id) = &self.hold_parameter {
+            text.push_str(&format!("hold = {id}\n"));
+        }
+        for (controller, id) in self.mappings() {
+            text.push_str(&format!(
+                "cc {} {} = {id}\n",
+                controller.channel + 1,
+                controller.controller
+            ));
+        }
+        text
+    }
+}
+
+/// An error returned when MIDI mappings can't be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MidiMappingError {
+    /// The line isn't a valid mapping.
+    InvalidLine(usize),
+    /// The line refers to a parameter id that isn't provided by the module.
+    UnknownParameter { line: usize, id: String },
+}
+
+impl fmt::Display for MidiMappingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLine(line) => {
+                write!(
+                    f,
+                    "line {line}: expected 'cc <channel> <controller> = <id>'"
+                )
+            }
+            Self::UnknownParameter { line, id } => {
+                write!(f, "line {line}: unknown parameter '{id}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MidiMappingError {}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{BoolParameter, FloatParameter, Parameter},
+    };
+
+    struct TestProvider {}
+
+    impl ParameterProvider for TestProvider {
+        fn parameter_count() -> usize {
+            2
+        }
+
+        fn parameter(id: usize) -> Option<Box<dyn Parameter>> {
+            match id {
+                0 => Some(Box::new(
+                    FloatParameter::new("size", "Size").range(0.0, 100.0),
+                )),
+                1 => Some(Box::new(BoolParameter::new("freeze", "Freeze"))),
+                _ => None,
+            }
+        }
+    }
+
+    fn set_parameter(command: Option<Command>) -> Option<(usize, f32)> {
+        match command? {
+            Command::SetParameter(id, value) => Some((id, value)),
+            _ => None,
+        }
+    }
+
+    fn cc(controller: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange {
+            channel: 0,
+            controller,
+            value,
+        }
+    }
+
+    #[test]
+    fn parse_messages() {
+        assert_eq!(
+            MidiMessage::parse(&[0x91, 60, 100]),
+            Some(MidiMessage::NoteOn {
+                channel: 1,
+                note: 60,
+                velocity: 100
+            })
+        );
+        assert_eq!(
+            MidiMessage::parse(&[0x90, 60, 0]),
+            Some(MidiMessage::NoteOff {
+                channel: 0,
+                note: 60
+            })
+        );
+        assert_eq!(MidiMessage::parse(&[0xb0, 74, 127]), Some(cc(74, 127)));
+        assert_eq!(MidiMessage::parse(&[0xe0, 0, 64]), None);
+        assert_eq!(MidiMessage::parse(&[0xb0, 74]), None);
+    }
+
+    #[test]
+    fn control_changes_are_converted() {
+        let mut mapping = MidiMapping::new();
+        mapping.map(
+            Controller {
+                channel: 0,
+                controller: 74,
+            },
+            "size",
+        );
+
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(cc(74, 127))),
+            Some((0, 100.0))
+        );
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(cc(74, 0))),
+            Some((0, 0.0))
+        );
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(cc(75, 0))),
+            None
+        );
+    }
+
+    #[test]
+    fn learn() {
+        let mut mapping = MidiMapping::new();
+        mapping.learn(0);
+        assert_eq!(mapping.learning(), Some(0));
+
+        // The learned control change is applied immediately
+        assert!(set_parameter(mapping.handle::<TestProvider>(cc(20, 127))).is_some());
+        assert_eq!(mapping.learning(), None);
+        assert_eq!(
+            mapping.mappings().collect::<Vec<_>>(),
+            [(
+                Controller {
+                    channel: 0,
+                    controller: 20
+                },
+                "size"
+            )]
+        );
+    }
+
+    #[test]
+    fn notes_and_sustain_hold() {
+        let mut mapping = MidiMapping::new();
+        mapping.set_hold_parameter(Some("freeze"));
+
+        let note_on = |note| MidiMessage::NoteOn {
+            channel: 0,
+            note,
+            velocity: 100,
+        };
+        let note_off = |note| MidiMessage::NoteOff { channel: 0, note };
+
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(note_on(60))),
+            Some((1, 1.0))
+        );
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(note_on(64))),
+            None
+        );
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(note_off(60))),
+            None
+        );
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(note_off(64))),
+            Some((1, 0.0))
+        );
+
+        // The sustain pedal keeps the hold active after the note is released
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(cc(SUSTAIN_CONTROLLER, 127))),
+            Some((1, 1.0))
+        );
+        mapping.handle::<TestProvider>(note_on(60));
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(note_off(60))),
+            None
+        );
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(cc(SUSTAIN_CONTROLLER, 0))),
+            Some((1, 0.0))
+        );
+    }
+
+    #[test]
+    fn out_of_range_notes_are_ignored() {
+        let mut mapping = MidiMapping::new();
+        mapping.set_hold_parameter(Some("freeze"));
+
+        for note in [128, 200, 255] {
+            let note_on = MidiMessage::NoteOn {
+                channel: 0,
+                note,
+                velocity: 100,
+            };
+            assert!(mapping.handle::<TestProvider>(note_on).is_none());
+        }
+        assert!(
+            mapping
+                .handle::<TestProvider>(MidiMessage::NoteOff {
+                    channel: 0,
+                    note: 255
+                })
+                .is_none()
+        );
+
+        // The highest valid note still activates the hold
+        let note_on = MidiMessage::NoteOn {
+            channel: 0,
+            note: 127,
+            velocity: 100,
+        };
+        assert_eq!(
+            set_parameter(mapping.handle::<TestProvider>(note_on)),
+            Some((1, 1.0))
+        );
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let text = "# Mappings\nhold = freeze\ncc 1 74 = size\ncc 16 1 = size\n";
+        let mapping = MidiMapping::from_text::<TestProvider>(text).unwrap();
+        assert_eq!(mapping.mappings().count(), 2);
+        assert_eq!(mapping.to_text(), text.trim_start_matches("# Mappings\n"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            MidiMapping::from_text::<TestProvider>("cc 0 74 = size").err(),
+            Some(MidiMappingError::InvalidLine(1))
+        );
+        assert_eq!(
+            MidiMapping::from_text::<TestProvider>("cc 1 128 = size").err(),
+            Some(MidiMappingError::InvalidLine(1))
+        );
+        assert_eq!(
+            MidiMapping::from_text::<TestProvider>("\ncc 1 1 = width").err(),
+            Some(MidiMappingError::UnknownParameter {
+                line: 2,
+                id: "width".into()
+            })
+        );
+    }
+}
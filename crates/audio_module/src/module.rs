@@ -8,5 +8,12 @@ pub trait AudioModule: ParameterProvider {
 
 pub trait ParameterProvider {
     fn parameter_count() -> usize;
-    fn parameter(id: usize) -> Box<dyn Parameter>;
+    /// Returns the parameter with the given id, or `None` if the id is out of range.
+    fn parameter(id: usize) -> Option<Box<dyn Parameter>>;
+
+    /// Returns the index of the parameter with the given string id, see [Parameter::id].
+    fn parameter_index(id: &str) -> Option<usize> {
+        (0..Self::parameter_count())
+            .find(|&index| Self::parameter(index).is_some_and(|parameter| parameter.id() == id))
+    }
 }
@@ -0,0 +1,91 @@
+use ringbuf::{
+    HeapCons, HeapProd, HeapRb,
+    traits::{Consumer, Producer, Split},
+};
+
+/// A message sent from a processor to the GUI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Notification {
+    /// Levels measured since the previous meter notification, as linear gain values.
+    Meter {
+        input_peak: f32,
+        output_peak: f32,
+        wet_rms: f32,
+    },
+    /// A parameter's value was changed on the audio thread, e.g. by automation.
+    ParameterChanged { id: usize, value: f32 },
+    /// The processor's tail became active or decayed to silence.
+    TailActive(bool),
+}
+
+/// Makes a lock-free channel for sending notifications from the audio thread.
+///
+/// The channel holds up to `capacity` notifications, with further notifications being dropped
+/// until the receiver catches up.
+pub fn notification_channel(capacity: usize) -> (NotificationSender, NotificationReceiver) {
+    let (producer, consumer) = HeapRb::new(capacity).split();
+    (
+        NotificationSender { producer },
+        NotificationReceiver { consumer },
+    )
+}
+
+/// The sending side of a notification channel, see [notification_channel].
+///
+/// Sending doesn't lock or allocate, so it's safe to use on the audio thread.
+pub struct NotificationSender {
+    producer: HeapProd<Notification>,
+}
+
+// SAFETY: The producer is only accessible through `&mut self`, so a shared reference to the
+// sender can't be used to access it from multiple threads.
+unsafe impl Sync for NotificationSender {}
+
+impl NotificationSender {
+    /// Sends a notification, returning false if the channel is full and the notification was
+    /// dropped.
+    pub fn send(&mut self, notification: Notification) -> bool {
+        self.producer.try_push(notification).is_ok()
+    }
+}
+
+/// The receiving side of a notification channel, see [notification_channel].
+pub struct NotificationReceiver {
+    consumer: HeapCons<Notification>,
+}
+
+impl NotificationReceiver {
+    /// Returns the next notification, or `None` if the channel is empty.
+    pub fn try_recv(&mut self) -> Option<Notification> {
+        self.consumer.try_pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_are_received_in_order() {
+        let (mut sender, mut receiver) = notification_channel(4);
+        assert!(sender.send(Notification::TailActive(true)));
+        assert!(sender.send(Notification::ParameterChanged { id: 1, value: 0.5 }));
+
+        assert_eq!(receiver.try_recv(), Some(Notification::TailActive(true)));
+        assert_eq!(
+            receiver.try_recv(),
+            Some(Notification::ParameterChanged { id: 1, value: 0.5 })
+        );
+        assert_eq!(receiver.try_recv(), None);
+    }
+
+    #[test]
+    fn full_channel_drops_notifications() {
+        let (mut sender, mut receiver) = notification_channel(1);
+        assert!(sender.send(Notification::TailActive(true)));
+        assert!(!sender.send(Notification::TailActive(false)));
+
+        assert_eq!(receiver.try_recv(), Some(Notification::TailActive(true)));
+        assert_eq!(receiver.try_recv(), None);
+    }
+}
@@ -1,17 +1,45 @@
 use crate::{
-    string_converter::{DefaultStringConverter, StringConverter, float_string_converter},
-    value_converter::{DefaultValueConverter, ValueConverter, linear_value_converter},
+    string_converter::{
+        BoolStringConverter, ChoiceStringConverter, DefaultStringConverter, IntStringConverter,
+        StringConverter, float_string_converter,
+    },
+    value_converter::{
+        ChoiceValueConverter, DefaultValueConverter, SteppedValueConverter, ValueConverter,
+        linear_value_converter,
+    },
 };
 
+// The relative amount that parsed values may be outside of a parameter's user range
+const USER_RANGE_TOLERANCE: f32 = 1.0e-5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ValueType {
     Float,
     Bool,
+    /// Whole numbers in the range `min..=max`.
+    Int {
+        min: i32,
+        max: i32,
+    },
+    /// A choice between a number of options, with the option's index used as the user value.
+    Choice(usize),
 }
 
 pub trait Parameter {
+    /// A stable identifier for the parameter, e.g. `room_size`.
+    ///
+    /// Unlike the parameter's index, the id doesn't change when parameters are added or reordered,
+    /// so it should be used when referring to parameters in presets or control mappings.
+    fn id(&self) -> &'static str;
     fn name(&self) -> String;
     fn default_user_value(&self) -> f32;
 
+    /// The unit of the parameter's user values, e.g. `dB` or `ms`, or an empty string if the
+    /// values have no unit.
+    fn unit(&self) -> String {
+        String::default()
+    }
+
     fn value_type(&self) -> ValueType {
         ValueType::Float
     }
@@ -23,16 +51,52 @@ pub trait Parameter {
     fn make_string_converter(&self) -> Box<dyn StringConverter> {
         Box::new(DefaultStringConverter {})
     }
+
+    /// Returns the lowest and highest user values, as given by the parameter's value converter.
+    fn user_range(&self) -> (f32, f32) {
+        let converter = self.make_value_converter();
+        let (start, end) = (converter.linear_to_user(0.0), converter.linear_to_user(1.0));
+        (start.min(end), start.max(end))
+    }
+
+    /// Parses a user value from text, e.g. from a preset or the command line.
+    ///
+    /// Plain numbers are taken as user values, and anything else is parsed by the parameter's
+    /// string converter, e.g. `on`, `35 %`, or `0.5 s`. Values outside of the parameter's
+    /// [user range](Parameter::user_range) are rejected, and values of stepped parameters are
+    /// rounded to the nearest step.
+    fn parse_user_value(&self, text: &str) -> Option<f32> {
+        let value = match text.trim().parse::<f32>() {
+            Ok(value) if value.is_finite() => value,
+            _ => self.make_string_converter().from_string(text)?,
+        };
+
+        // A small tolerance allows for rounding errors in the converters, e.g. in the maximum of
+        // a log range
+        let (min, max) = self.user_range();
+        let tolerance = (max - min) * USER_RANGE_TOLERANCE;
+        if value < min - tolerance || value > max + tolerance {
+            return None;
+        }
+
+        let value = value.clamp(min, max);
+        match self.value_type() {
+            ValueType::Float => Some(value),
+            ValueType::Bool | ValueType::Int { .. } | ValueType::Choice(_) => Some(value.round()),
+        }
+    }
 }
 
 pub struct BoolParameter {
+    pub id: &'static str,
     pub name: String,
     pub default_user_value: bool,
 }
 
 impl BoolParameter {
-    pub fn new(name: &str) -> Self {
+    pub fn new(id: &'static str, name: &str) -> Self {
         Self {
+            id,
             name: name.to_string(),
             default_user_value: false,
         }
@@ -45,6 +109,10 @@ impl BoolParameter {
 }
 
 impl Parameter for BoolParameter {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
     fn name(&self) -> String {
         self.name.clone()
     }
@@ -56,26 +124,167 @@ impl Parameter for BoolParameter {
     fn value_type(&self) -> ValueType {
         ValueType::Bool
     }
+
+    fn make_string_converter(&self) -> Box<dyn StringConverter> {
+        Box::new(BoolStringConverter {})
+    }
+}
+
+pub struct ChoiceParameter {
+    pub id: &'static str,
+    pub name: String,
+    pub options: Vec<String>,
+    pub default_user_value: usize,
+}
+
+impl ChoiceParameter {
+    pub fn new(id: &'static str, name: &str, options: &[&str]) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            options: options.iter().map(|option| option.to_string()).collect(),
+            default_user_value: 0,
+        }
+    }
+
+    pub fn default_user_value(mut self, default: usize) -> Self {
+        self.default_user_value = default;
+        self
+    }
+}
+
+impl Parameter for ChoiceParameter {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn default_user_value(&self) -> f32 {
+        self.default_user_value as f32
+    }
+
+    fn value_type(&self) -> ValueType {
+        ValueType::Choice(self.options.len())
+    }
+
+    fn make_value_converter(&self) -> Box<dyn ValueConverter> {
+        Box::new(ChoiceValueConverter::new(self.options.len()))
+    }
+
+    fn make_string_converter(&self) -> Box<dyn StringConverter> {
+        Box::new(ChoiceStringConverter::new(self.options.clone()))
+    }
+}
+
+pub struct IntParameter {
+    pub id: &'static str,
+    pub name: String,
+    pub unit: String,
+    pub min_user_value: i32,
+    pub max_user_value: i32,
+    pub step: i32,
+    pub default_user_value: i32,
+}
+
+impl IntParameter {
+    pub fn new(id: &'static str, name: &str) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            unit: String::default(),
+            min_user_value: 0,
+            max_user_value: 1,
+            step: 1,
+            default_user_value: 0,
+        }
+    }
+
+    pub fn unit(mut self, unit: &str) -> Self {
+        self.unit = unit.to_string();
+        self
+    }
+
+    pub fn range(mut self, min: i32, max: i32) -> Self {
+        self.min_user_value = min;
+        self.max_user_value = max;
+        self
+    }
+
+    pub fn step(mut self, step: i32) -> Self {
+        self.step = step;
+        self
+    }
+
+    pub fn default_user_value(mut self, default: i32) -> Self {
+        self.default_user_value = default;
+        self
+    }
+}
+
+impl Parameter for IntParameter {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn default_user_value(&self) -> f32 {
+        self.default_user_value as f32
+    }
+
+    fn unit(&self) -> String {
+        self.unit.clone()
+    }
+
+    fn value_type(&self) -> ValueType {
+        ValueType::Int {
+            min: self.min_user_value,
+            max: self.max_user_value,
+        }
+    }
+
+    fn make_value_converter(&self) -> Box<dyn ValueConverter> {
+        Box::new(SteppedValueConverter::new(
+            self.min_user_value as f32,
+            self.max_user_value as f32,
+            self.step as f32,
+        ))
+    }
+
+    fn make_string_converter(&self) -> Box<dyn StringConverter> {
+        Box::new(IntStringConverter::new(self.unit.clone()))
+    }
 }
 
 pub struct FloatParameter {
+    pub id: &'static str,
     pub name: String,
     pub unit: String,
     pub min_user_value: f32,
     pub max_user_value: f32,
     pub default_user_value: f32,
+    pub precision: usize,
+    pub center_user_value: Option<f32>,
     pub value_converter_maker: fn(&FloatParameter) -> Box<dyn ValueConverter>,
     pub string_converter_maker: fn(&FloatParameter) -> Box<dyn StringConverter>,
 }
 
 impl FloatParameter {
-    pub fn new(name: &str) -> Self {
+    pub fn new(id: &'static str, name: &str) -> Self {
         Self {
+            id,
             name: name.to_string(),
             unit: String::default(),
             min_user_value: 0.0,
             max_user_value: 1.0,
             default_user_value: 0.0,
+            precision: 0,
+            center_user_value: None,
             value_converter_maker: linear_value_converter,
             string_converter_maker: float_string_converter,
         }
@@ -97,6 +306,18 @@ impl FloatParameter {
         self
     }
 
+    /// Sets the number of decimal places used by [float_string_converter].
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Sets the user value that's in the middle of the range when using [skew_value_converter](crate::skew_value_converter).
+    pub fn center_user_value(mut self, center: f32) -> Self {
+        self.center_user_value = Some(center);
+        self
+    }
+
     pub fn value_converter(
         mut self,
         converter: fn(&FloatParameter) -> Box<dyn ValueConverter>,
@@ -115,6 +336,10 @@ impl FloatParameter {
 }
 
 impl Parameter for FloatParameter {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
     fn name(&self) -> String {
         self.name.clone()
     }
@@ -123,6 +348,10 @@ impl Parameter for FloatParameter {
         self.default_user_value
     }
 
+    fn unit(&self) -> String {
+        self.unit.clone()
+    }
+
     fn make_value_converter(&self) -> Box<dyn ValueConverter> {
         (self.value_converter_maker)(self)
     }
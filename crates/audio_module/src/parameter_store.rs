@@ -0,0 +1,219 @@
+use {
+    crate::{CommandError, ParameterProvider},
+    std::sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+/// Parameter values that are shared between the GUI and the audio thread.
+///
+/// Values are stored as atomics indexed by parameter id, along with flags that record which values
+/// have changed since they were last pulled by the other side. Changes made by the GUI are pulled
+/// by the audio thread, and changes made on the audio thread (e.g. by automation) are pulled by the
+/// GUI.
+///
+/// Setting and pulling values is lock-free and doesn't allocate, so it's safe to use on the audio
+/// thread.
+pub struct ParameterStore {
+    values: Box<[AtomicU32]>,
+    audio_changed: Box<[AtomicBool]>,
+    gui_changed: Box<[AtomicBool]>,
+    // Allows a block without any changes to skip checking each parameter
+    any_audio_changed: AtomicBool,
+    any_gui_changed: AtomicBool,
+}
+
+impl ParameterStore {
+    /// Makes a store with the given initial values.
+    ///
+    /// All values start out as changed for the audio thread, so that a processor picks up the
+    /// initial values when it first pulls from the store.
+    pub fn new(values: &[f32]) -> Self {
+        Self {
+            values: values
+                .iter()
+                .map(|value| AtomicU32::new(value.to_bits()))
+                .collect(),
+            audio_changed: values.iter().map(|_| AtomicBool::new(true)).collect(),
+            gui_changed: values.iter().map(|_| AtomicBool::new(false)).collect(),
+            any_audio_changed: AtomicBool::new(true),
+            any_gui_changed: AtomicBool::new(false),
+        }
+    }
+
+    /// Makes a store containing the default values of a module's parameters.
+    pub fn with_defaults<Provider: ParameterProvider>() -> Self {
+        let values: Vec<f32> = (0..Provider::parameter_count())
+            .map(|id| {
+                Provider::parameter(id).map_or(0.0, |parameter| parameter.default_user_value())
+            })
+            .collect();
+        Self::new(&values)
+    }
+
+    /// Returns the number of parameters in the store.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the current user value of a parameter.
+    pub fn get(&self, id: usize) -> Option<f32> {
+        self.values
+            .get(id)
+            .map(|value| f32::from_bits(value.load(Ordering::Relaxed)))
+    }
+
+    /// Returns the current user values of all parameters, indexed by parameter id.
+    pub fn snapshot(&self) -> Vec<f32> {
+        self.values
+            .iter()
+            .map(|value| f32::from_bits(value.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Sets a parameter's user value from the GUI, to be pulled by the audio thread.
+    ///
+    /// Setting a parameter to its current value doesn't mark it as changed.
+    pub fn set(&self, id: usize, value: f32) -> Result<(), CommandError> {
+        self.store(id, value, &self.audio_changed, &self.any_audio_changed)
+    }
+
+    /// Sets a parameter's user value from the audio thread, to be pulled by the GUI.
+    pub fn set_from_audio(&self, id: usize, value: f32) -> Result<(), CommandError> {
+        self.store(id, value, &self.gui_changed, &self.any_gui_changed)
+    }
+
+    /// Calls `f` with the id and value of each parameter that was changed by the GUI since the
+    /// last call.
+    pub fn pull_audio_changes(&self, f: impl FnMut(usize, f32)) {
+        self.pull(&self.audio_changed, &self.any_audio_changed, f);
+    }
+
+    /// Calls `f` with the id and value of each parameter that was changed on the audio thread
+    /// since the last call.
+    pub fn pull_gui_changes(&self, f: impl FnMut(usize, f32)) {
+        self.pull(&self.gui_changed, &self.any_gui_changed, f);
+    }
+
+    fn store(
+        &self,
+        id: usize,
+        value: f32,
+        changed: &[AtomicBool],
+        any_changed: &AtomicBool,
+    ) -> Result<(), CommandError> {
+        let stored = self
+            .values
+            .get(id)
+            .ok_or(CommandError::UnknownParameter(id))?;
+
+        if stored.swap(value.to_bits(), Ordering::Relaxed) != value.to_bits() {
+            // The release stores make the new value visible to the side that pulls the change
+            changed[id].store(true, Ordering::Release);
+            any_changed.store(true, Ordering::Release);
+        }
+
+        Ok(())
+    }
+
+    fn pull(
+        &self,
+        changed: &[AtomicBool],
+        any_changed: &AtomicBool,
+        mut f: impl FnMut(usize, f32),
+    ) {
+        if !any_changed.swap(false, Ordering::Acquire) {
+            return;
+        }
+
+        for (id, changed) in changed.iter().enumerate() {
+            if changed.swap(false, Ordering::Acquire) {
+                f(id, f32::from_bits(self.values[id].load(Ordering::Relaxed)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::sync::Arc};
+
+    fn pull_audio(store: &ParameterStore) -> Vec<(usize, f32)> {
+        let mut changes = Vec::new();
+        store.pull_audio_changes(|id, value| changes.push((id, value)));
+        changes
+    }
+
+    fn pull_gui(store: &ParameterStore) -> Vec<(usize, f32)> {
+        let mut changes = Vec::new();
+        store.pull_gui_changes(|id, value| changes.push((id, value)));
+        changes
+    }
+
+    #[test]
+    fn initial_values_are_pulled_by_audio() {
+        let store = ParameterStore::new(&[0.5, 1.0]);
+        assert_eq!(pull_audio(&store), [(0, 0.5), (1, 1.0)]);
+        assert_eq!(pull_audio(&store), []);
+        assert_eq!(pull_gui(&store), []);
+    }
+
+    #[test]
+    fn changes_are_pulled_by_the_other_side() {
+        let store = ParameterStore::new(&[0.0, 0.0, 0.0]);
+        pull_audio(&store);
+
+        store.set(2, 0.25).unwrap();
+        store.set_from_audio(1, 0.75).unwrap();
+
+        assert_eq!(store.get(2), Some(0.25));
+        assert_eq!(store.get(1), Some(0.75));
+        assert_eq!(store.snapshot(), [0.0, 0.75, 0.25]);
+        assert_eq!(pull_audio(&store), [(2, 0.25)]);
+        assert_eq!(pull_gui(&store), [(1, 0.75)]);
+        assert_eq!(pull_gui(&store), []);
+    }
+
+    #[test]
+    fn unchanged_values_are_ignored() {
+        let store = ParameterStore::new(&[0.5]);
+        pull_audio(&store);
+
+        store.set(0, 0.5).unwrap();
+        assert_eq!(pull_audio(&store), []);
+    }
+
+    #[test]
+    fn unknown_parameter() {
+        let store = ParameterStore::new(&[0.0]);
+        assert_eq!(store.set(1, 0.0), Err(CommandError::UnknownParameter(1)));
+        assert_eq!(store.get(1), None);
+    }
+
+    #[test]
+    fn changes_from_another_thread() {
+        let store = Arc::new(ParameterStore::new(&[0.0]));
+        pull_audio(&store);
+
+        let gui = {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                for i in 1..=1000 {
+                    store.set(0, i as f32).unwrap();
+                }
+            })
+        };
+
+        let mut last = 0.0;
+        while last < 1000.0 {
+            store.pull_audio_changes(|_, value| {
+                assert!(value >= last);
+                last = value;
+            });
+        }
+
+        gui.join().unwrap();
+    }
+}
@@ -0,0 +1,331 @@
+use {
+    crate::ParameterProvider,
+    std::{collections::BTreeMap, fmt},
+};
+
+/// A named set of parameter values, with parameters referred to by their string ids.
+///
+/// A preset describes a module's complete state, so applying a preset sets every parameter,
+/// with parameters that aren't included in the preset being reset to their default values,
+/// see [Preset::user_values]. Presets shouldn't be applied by only overwriting the values that
+/// they contain, otherwise the result would depend on the module's previous state.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Preset {
+    pub name: String,
+    pub values: BTreeMap<String, f32>,
+}
+
+impl Preset {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Sets a parameter's user value in the preset.
+    pub fn value(mut self, id: &str, value: f32) -> Self {
+        self.values.insert(id.to_string(), value);
+        self
+    }
+
+    /// Makes a preset containing all of a module's parameter values, indexed by parameter index.
+    pub fn from_values<Provider: ParameterProvider>(name: &str, values: &[f32]) -> Self {
+        let values = (0..Provider::parameter_count())
+            .filter_map(Provider::parameter)
+            .zip(values)
+            .map(|(parameter, value)| (parameter.id().to_string(), *value))
+            .collect();
+
+        Self {
+            name: name.to_string(),
+            values,
+        }
+    }
+
+    /// Returns the user values of all of a module's parameters, indexed by parameter index.
+    ///
+    /// Parameters that aren't in the preset are set to their default values, and values for
+    /// unknown parameter ids are ignored.
+    pub fn user_values<Provider: ParameterProvider>(&self) -> Vec<f32> {
+        (0..Provider::parameter_count())
+            .map(|index| {
+                Provider::parameter(index).map_or(0.0, |parameter| {
+                    self.values
+                        .get(parameter.id())
+                        .copied()
+                        .unwrap_or_else(|| parameter.default_user_value())
+                })
+            })
+            .collect()
+    }
+
+    /// Parses a preset from its text format.
+    ///
+    /// The format consists of `key = value` lines, with an optional `name` key followed by
+    /// parameter ids. Values can be plain numbers or strings that the parameter can parse,
+    /// e.g. `on` or `35 %`. Empty lines and lines starting with `#` are ignored.
+    pub fn from_text<Provider: ParameterProvider>(text: &str) -> Result<Self, PresetError> {
+        let mut preset = Self::default();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line_number = line_index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(PresetError::InvalidLine(line_number))?;
+            let key = key.trim();
+
+            if key == "name" {
+                preset.name = value.trim().to_string();
+                continue;
+            }
+
+            let (_, value) = parse_assignment::<Provider>(line).map_err(|error| match error {
+                AssignmentError::InvalidAssignment(_) => PresetError::InvalidLine(line_number),
+                AssignmentError::UnknownParameter(id) => PresetError::UnknownParameter {
+                    line: line_number,
+                    id,
+                },
+                AssignmentError::InvalidValue { id, value } => PresetError::InvalidValue {
+                    line: line_number,
+                    id,
+                    value,
+                },
+            })?;
+
+            preset.values.insert(key.to_string(), value);
+        }
+
+        Ok(preset)
+    }
+
+    /// Writes the preset in the text format that's read by [Preset::from_text].
+    pub fn to_text(&self) -> String {
+        let mut text = format!("name = {}\n", self.name);
+        for (id, value) in self.values.iter() {
+            text.push_str(&format!("{id} = {value}\n"));
+        }
+        text
+    }
+}
+
+/// An error returned when a preset can't be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PresetError {
+    /// The line isn't a `key = value` pair.
+    InvalidLine(usize),
+    /// The line refers to a parameter id that isn't provided by the module.
+    UnknownParameter { line: usize, id: String },
+    /// The line's value couldn't be parsed by the parameter.
+    InvalidValue {
+        line: usize,
+        id: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLine(line) => write!(f, "line {line}: expected 'parameter = value'"),
+            Self::UnknownParameter { line, id } => {
+                write!(f, "line {line}: unknown parameter '{id}'")
+            }
+            Self::InvalidValue { line, id, value } => {
+                write!(f, "line {line}: invalid value for '{id}': '{value}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+/// Parses a `parameter = value` assignment, returning the parameter's index and user value.
+///
+/// The parameter is referred to by its string id, and the value can be a plain number or a
+/// string that the parameter can parse, e.g. `room_size=80 %`.
+pub fn parse_assignment<Provider: ParameterProvider>(
+    assignment: &str,
+) -> Result<(usize, f32), AssignmentError> {
+    let (key, value) = assignment
+        .split_once('=')
+        .ok_or_else(|| AssignmentError::InvalidAssignment(assignment.to_string()))?;
+    let (key, value) = (key.trim(), value.trim());
+
+    let index = Provider::parameter_index(key)
+        .ok_or_else(|| AssignmentError::UnknownParameter(key.to_string()))?;
+    let value = Provider::parameter(index)
+        .and_then(|parameter| parameter.parse_user_value(value))
+        .ok_or_else(|| AssignmentError::InvalidValue {
+            id: key.to_string(),
+            value: value.to_string(),
+        })?;
+
+    Ok((index, value))
+}
+
+/// An error returned when a `parameter = value` assignment can't be parsed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssignmentError {
+    /// The text isn't a `parameter = value` pair.
+    InvalidAssignment(String),
+    /// The assignment refers to a parameter id that isn't provided by the module.
+    UnknownParameter(String),
+    /// The assignment's value couldn't be parsed by the parameter.
+    InvalidValue { id: String, value: String },
+}
+
+impl fmt::Display for AssignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAssignment(text) => {
+                write!(f, "expected 'parameter=value', found '{text}'")
+            }
+            Self::UnknownParameter(id) => write!(f, "unknown parameter '{id}'"),
+            Self::InvalidValue { id, value } => write!(f, "invalid value for '{id}': '{value}'"),
+        }
+    }
+}
+
+impl std::error::Error for AssignmentError {}
+
+/// Provides a module's factory presets.
+pub trait PresetProvider: ParameterProvider {
+    fn factory_presets() -> Vec<Preset>;
+
+    /// Returns the factory preset with the given name, ignoring case.
+    fn factory_preset(name: &str) -> Option<Preset> {
+        Self::factory_presets()
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{BoolParameter, FloatParameter, Parameter, percent_string_converter},
+    };
+
+    struct TestProvider {}
+
+    impl ParameterProvider for TestProvider {
+        fn parameter_count() -> usize {
+            2
+        }
+
+        fn parameter(id: usize) -> Option<Box<dyn Parameter>> {
+            match id {
+                0 => Some(Box::new(
+                    FloatParameter::new("size", "Size")
+                        .string_converter(percent_string_converter)
+                        .default_user_value(0.5),
+                )),
+                1 => Some(Box::new(BoolParameter::new("freeze", "Freeze"))),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let text = "# A comment\nname = Big\n\nsize = 80 %\nfreeze = on\n";
+        let preset = Preset::from_text::<TestProvider>(text).unwrap();
+        assert_eq!(
+            preset,
+            Preset::new("Big").value("size", 0.8).value("freeze", 1.0)
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Preset::from_text::<TestProvider>("size 0.5"),
+            Err(PresetError::InvalidLine(1))
+        );
+        assert_eq!(
+            Preset::from_text::<TestProvider>("\nwidth = 0.5"),
+            Err(PresetError::UnknownParameter {
+                line: 2,
+                id: "width".into()
+            })
+        );
+        assert_eq!(
+            Preset::from_text::<TestProvider>("freeze = maybe"),
+            Err(PresetError::InvalidValue {
+                line: 1,
+                id: "freeze".into(),
+                value: "maybe".into()
+            })
+        );
+        assert_eq!(
+            Preset::from_text::<TestProvider>("name = Loud\nsize = 35"),
+            Err(PresetError::InvalidValue {
+                line: 2,
+                id: "size".into(),
+                value: "35".into()
+            })
+        );
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let preset = Preset::new("Odd Values")
+            .value("size", 0.123_456_79)
+            .value("freeze", 1.0);
+        let parsed = Preset::from_text::<TestProvider>(&preset.to_text()).unwrap();
+        assert_eq!(parsed, preset);
+    }
+
+    #[test]
+    fn assignments() {
+        assert_eq!(parse_assignment::<TestProvider>("size=80 %"), Ok((0, 0.8)));
+        assert_eq!(
+            parse_assignment::<TestProvider>(" freeze = on "),
+            Ok((1, 1.0))
+        );
+        assert_eq!(
+            parse_assignment::<TestProvider>("size"),
+            Err(AssignmentError::InvalidAssignment("size".into()))
+        );
+        assert_eq!(
+            parse_assignment::<TestProvider>("width=1"),
+            Err(AssignmentError::UnknownParameter("width".into()))
+        );
+        assert_eq!(
+            parse_assignment::<TestProvider>("size=big"),
+            Err(AssignmentError::InvalidValue {
+                id: "size".into(),
+                value: "big".into()
+            })
+        );
+
+        // Values outside of the parameter's range are rejected, and stepped values are rounded
+        for value in ["35", "-0.1", "150 %"] {
+            assert_eq!(
+                parse_assignment::<TestProvider>(&format!("size={value}")),
+                Err(AssignmentError::InvalidValue {
+                    id: "size".into(),
+                    value: value.into()
+                })
+            );
+        }
+        assert_eq!(parse_assignment::<TestProvider>("freeze=0.6"), Ok((1, 1.0)));
+    }
+
+    #[test]
+    fn user_values() {
+        let preset = Preset::new("Frozen").value("freeze", 1.0);
+        assert_eq!(preset.user_values::<TestProvider>(), [0.5, 1.0]);
+
+        let captured = Preset::from_values::<TestProvider>("Captured", &[0.25, 0.0]);
+        assert_eq!(captured.user_values::<TestProvider>(), [0.25, 0.0]);
+        assert_eq!(captured.values.len(), 2);
+    }
+}
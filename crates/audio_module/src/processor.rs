@@ -1,5 +1,60 @@
-use crate::CommandHandler;
+use {
+    crate::{CommandHandler, NotificationSender, ParameterStore},
+    std::sync::Arc,
+};
 
 pub trait AudioProcessor: CommandHandler + Send + Sync + 'static {
     fn process(&mut self, input: &[f32], output: &mut [f32], channels: u32);
+
+    /// Processes the input along with an auxiliary sidechain input.
+    ///
+    /// The sidechain buffer has the same length and channel layout as the input.
+    ///
+    /// Processors that don't make use of a sidechain can rely on the default implementation,
+    /// which ignores the sidechain and calls `process`.
+    fn process_with_sidechain(
+        &mut self,
+        input: &[f32],
+        sidechain: &[f32],
+        output: &mut [f32],
+        channels: u32,
+    ) {
+        let _ = sidechain;
+        self.process(input, output, channels);
+    }
+
+    /// Returns the number of frames of latency that the processor introduces.
+    fn latency_samples(&self) -> usize {
+        0
+    }
+
+    /// Returns the number of frames of output that follow the end of the input.
+    ///
+    /// `None` indicates that the tail is infinite.
+    fn tail_samples(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    /// Shares a parameter store with the processor.
+    ///
+    /// Processors that support a store pull changed values from it once per block, and publish
+    /// values that are set via commands so that they can be reflected in the GUI.
+    fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+        let _ = store;
+    }
+
+    /// Provides a channel for the processor to send notifications to the GUI, e.g. level meters.
+    fn set_notification_sender(&mut self, sender: NotificationSender) {
+        let _ = sender;
+    }
+
+    /// Clears the processor's internal state, e.g. when playback is restarted.
+    fn reset(&mut self) {}
+
+    /// Changes the sample rate that the processor should run at.
+    ///
+    /// This may allocate, so it shouldn't be called while processing is ongoing.
+    fn set_sample_rate(&mut self, sample_rate: usize) {
+        let _ = sample_rate;
+    }
 }
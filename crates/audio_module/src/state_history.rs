@@ -0,0 +1,243 @@
+use {crate::ParameterProvider, std::collections::VecDeque};
+
+/// One of the two parameter states that can be compared in a [StateHistory].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    fn index(self) -> usize {
+        match self {
+            Slot::A => 0,
+            Slot::B => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ParameterChange {
+    id: usize,
+    old_value: f32,
+    new_value: f32,
+}
+
+#[derive(Clone, Default)]
+struct SlotState {
+    values: Vec<f32>,
+    undo: VecDeque<ParameterChange>,
+    redo: Vec<ParameterChange>,
+}
+
+/// Keeps track of parameter values in two slots for A/B comparison, with undo and redo of the
+/// changes made in each slot.
+///
+/// The history only records values, it's up to the caller to apply the values that are returned
+/// when undoing a change or switching slots.
+pub struct StateHistory {
+    slots: [SlotState; 2],
+    active: Slot,
+    max_undo_steps: usize,
+}
+
+impl StateHistory {
+    /// Makes a history with both slots containing the given values.
+    ///
+    /// Each slot keeps up to `max_undo_steps` changes, with the oldest changes being discarded.
+    pub fn new(values: Vec<f32>, max_undo_steps: usize) -> Self {
+        let slot = SlotState {
+            values,
+            ..Default::default()
+        };
+
+        Self {
+            slots: [slot.clone(), slot],
+            active: Slot::A,
+            max_undo_steps,
+        }
+    }
+
+    /// Makes a history with both slots containing the default values of a module's parameters.
+    pub fn with_defaults<Provider: ParameterProvider>(max_undo_steps: usize) -> Self {
+        let values = (0..Provider::parameter_count())
+            .map(|id| {
+                Provider::parameter(id).map_or(0.0, |parameter| parameter.default_user_value())
+            })
+            .collect();
+        Self::new(values, max_undo_steps)
+    }
+
+    /// Returns the slot that's currently being edited.
+    pub fn active_slot(&self) -> Slot {
+        self.active
+    }
+
+    /// Returns the parameter values in the active slot, indexed by parameter id.
+    pub fn values(&self) -> &[f32] {
+        &self.slots[self.active.index()].values
+    }
+
+    /// Records a change to a parameter in the active slot.
+    ///
+    /// Consecutive changes to the same parameter are merged into a single undo step, so that e.g.
+    /// dragging a slider can be undone in one go. Changes to unknown ids or that don't change the
+    /// value are ignored.
+    pub fn set(&mut self, id: usize, value: f32) {
+        let max_undo_steps = self.max_undo_steps;
+        let slot = self.active_slot_mut();
+        let Some(old_value) = slot.values.get(id).copied() else {
+            return;
+        };
+        if old_value == value {
+            return;
+        }
+
+        slot.values[id] = value;
+        slot.redo.clear();
+
+        match slot.undo.back_mut() {
+            Some(last) if last.id == id => last.new_value = value,
+            _ => {
+                slot.undo.push_back(ParameterChange {
+                    id,
+                    old_value,
+                    new_value: value,
+                });
+                if slot.undo.len() > max_undo_steps {
+                    slot.undo.pop_front();
+                }
+            }
+        }
+    }
+
+    /// Reverts the most recent change in the active slot.
+    ///
+    /// The id of the changed parameter and its restored value are returned.
+    pub fn undo(&mut self) -> Option<(usize, f32)> {
+        let slot = self.active_slot_mut();
+        let change = slot.undo.pop_back()?;
+        slot.values[change.id] = change.old_value;
+        slot.redo.push(change);
+        Some((change.id, change.old_value))
+    }
+
+    /// Reapplies the most recently undone change in the active slot.
+    ///
+    /// The id of the changed parameter and its new value are returned.
+    pub fn redo(&mut self) -> Option<(usize, f32)> {
+        let slot = self.active_slot_mut();
+        let change = slot.redo.pop()?;
+        slot.values[change.id] = change.new_value;
+        slot.undo.push_back(change);
+        Some((change.id, change.new_value))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.slots[self.active.index()].undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.slots[self.active.index()].redo.is_empty()
+    }
+
+    /// Makes the given slot active, returning its values.
+    pub fn select(&mut self, slot: Slot) -> &[f32] {
+        self.active = slot;
+        self.values()
+    }
+
+    /// Copies the values from one slot to another, clearing the target slot's undo history.
+    pub fn copy(&mut self, from: Slot, to: Slot) {
+        if from != to {
+            self.slots[to.index()] = SlotState {
+                values: self.slots[from.index()].values.clone(),
+                ..Default::default()
+            };
+        }
+    }
+
+    fn active_slot_mut(&mut self) -> &mut SlotState {
+        &mut self.slots[self.active.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut history = StateHistory::new(vec![0.0, 0.0], 10);
+        history.set(0, 0.5);
+        history.set(1, 1.0);
+        assert_eq!(history.values(), [0.5, 1.0]);
+
+        assert_eq!(history.undo(), Some((1, 0.0)));
+        assert_eq!(history.undo(), Some((0, 0.0)));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.values(), [0.0, 0.0]);
+
+        assert_eq!(history.redo(), Some((0, 0.5)));
+        assert_eq!(history.values(), [0.5, 0.0]);
+
+        // A new change clears the redo stack
+        history.set(1, 0.25);
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(), None);
+    }
+
+    #[test]
+    fn consecutive_changes_are_merged() {
+        let mut history = StateHistory::new(vec![0.0, 0.0], 10);
+        history.set(0, 0.1);
+        history.set(0, 0.2);
+        history.set(0, 0.3);
+        history.set(1, 1.0);
+        history.set(0, 0.4);
+
+        assert_eq!(history.undo(), Some((0, 0.3)));
+        assert_eq!(history.undo(), Some((1, 0.0)));
+        assert_eq!(history.undo(), Some((0, 0.0)));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_is_bounded() {
+        let mut history = StateHistory::new(vec![0.0, 0.0], 2);
+        history.set(0, 1.0);
+        history.set(1, 1.0);
+        history.set(0, 2.0);
+
+        assert_eq!(history.undo(), Some((0, 1.0)));
+        assert_eq!(history.undo(), Some((1, 0.0)));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.values(), [1.0, 0.0]);
+    }
+
+    #[test]
+    fn unknown_and_unchanged_values_are_ignored() {
+        let mut history = StateHistory::new(vec![0.5], 10);
+        history.set(0, 0.5);
+        history.set(1, 1.0);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn a_b_slots() {
+        let mut history = StateHistory::new(vec![0.0, 0.0], 10);
+        history.set(0, 0.5);
+        assert_eq!(history.active_slot(), Slot::A);
+
+        assert_eq!(history.select(Slot::B), [0.0, 0.0]);
+        assert!(!history.can_undo());
+        history.set(1, 1.0);
+
+        assert_eq!(history.select(Slot::A), [0.5, 0.0]);
+        assert_eq!(history.undo(), Some((0, 0.0)));
+
+        history.copy(Slot::A, Slot::B);
+        assert_eq!(history.select(Slot::B), [0.0, 0.0]);
+        assert!(!history.can_undo());
+    }
+}
@@ -1,7 +1,13 @@
-use crate::FloatParameter;
+use crate::{FloatParameter, db_to_gain, gain_to_db};
 
 pub trait StringConverter {
     fn to_string(&self, value: f32) -> String;
+
+    /// Parses a user value from a string, e.g. a value that was typed into a text field.
+    ///
+    /// `None` is returned if the string can't be parsed.
+    #[allow(clippy::wrong_self_convention)]
+    fn from_string(&self, text: &str) -> Option<f32>;
 }
 
 #[derive(Clone)]
@@ -11,6 +17,10 @@ impl StringConverter for DefaultStringConverter {
     fn to_string(&self, value: f32) -> String {
         format!("{:.0}", value)
     }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        parse_with_unit(text, "")
+    }
 }
 
 #[derive(Clone)]
@@ -20,22 +30,110 @@ impl StringConverter for BoolStringConverter {
     fn to_string(&self, value: f32) -> String {
         if value == 0.0 { "off" } else { "on" }.to_string()
     }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        match text.trim().to_lowercase().as_str() {
+            "on" | "true" | "yes" => Some(1.0),
+            "off" | "false" | "no" => Some(0.0),
+            number => number
+                .parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(|value| if value == 0.0 { 0.0 } else { 1.0 }),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ChoiceStringConverter {
+    options: Vec<String>,
+}
+
+impl ChoiceStringConverter {
+    pub fn new(options: Vec<String>) -> Self {
+        Self { options }
+    }
+}
+
+impl StringConverter for ChoiceStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        self.options
+            .get(value.round().max(0.0) as usize)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        let text = text.trim();
+        match self
+            .options
+            .iter()
+            .position(|option| option.eq_ignore_ascii_case(text))
+        {
+            Some(index) => Some(index as f32),
+            // Fall back to accepting an option's index
+            None => text
+                .parse::<usize>()
+                .ok()
+                .filter(|&index| index < self.options.len())
+                .map(|index| index as f32),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct FloatStringConverter {
     unit: String,
+    precision: usize,
 }
 
 impl FloatStringConverter {
     pub fn new(unit: String) -> Self {
-        Self { unit }
+        Self { unit, precision: 0 }
+    }
+
+    /// Sets the number of decimal places that are displayed.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
     }
 }
 
 impl StringConverter for FloatStringConverter {
     fn to_string(&self, value: f32) -> String {
-        format!("{:.0} {1}", value, self.unit)
+        format!("{:.*} {}", self.precision, value, self.unit)
+    }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        parse_with_unit(text, &self.unit)
+    }
+}
+
+/// Displays values rounded to the nearest integer, with an optional unit.
+#[derive(Clone)]
+pub struct IntStringConverter {
+    unit: String,
+}
+
+impl IntStringConverter {
+    pub fn new(unit: String) -> Self {
+        Self { unit }
+    }
+}
+
+impl StringConverter for IntStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        // Converting to an integer avoids displaying '-0' for small negative values
+        let value = value.round() as i64;
+        if self.unit.is_empty() {
+            value.to_string()
+        } else {
+            format!("{value} {}", self.unit)
+        }
+    }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        parse_with_unit(text, &self.unit).map(f32::round)
     }
 }
 
@@ -46,12 +144,317 @@ impl StringConverter for PercentStringConverter {
     fn to_string(&self, value: f32) -> String {
         format!("{:.0} %", value * 100.0)
     }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        parse_with_unit(text, "%").map(|value| value / 100.0)
+    }
+}
+
+/// Displays gain values in decibels, with silence displayed as `-inf dB`.
+#[derive(Clone)]
+pub struct DecibelStringConverter {}
+
+impl StringConverter for DecibelStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        let db = gain_to_db(value);
+        if db.is_finite() {
+            // Adding 0 avoids displaying '-0.0' for values just below 0 dB
+            format!("{:.1} dB", (db * 10.0).round() / 10.0 + 0.0)
+        } else {
+            "-inf dB".to_string()
+        }
+    }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        // Silence is displayed as `-inf dB`, which is the only non-finite value that's accepted
+        if text.trim().trim_end_matches("dB").trim_end() == "-inf" {
+            return Some(0.0);
+        }
+
+        parse_with_unit(text, "dB")
+            .map(db_to_gain)
+            .filter(|gain| gain.is_finite())
+    }
+}
+
+/// Displays frequencies in Hz, switching to kHz for values of 1 kHz and above.
+#[derive(Clone)]
+pub struct FrequencyStringConverter {}
+
+impl StringConverter for FrequencyStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        // The thresholds are chosen so that rounding doesn't produce e.g. '1000 Hz'
+        match value.abs() {
+            hz if hz < 99.95 => format!("{value:.1} Hz"),
+            hz if hz < 999.5 => format!("{value:.0} Hz"),
+            hz if hz < 9995.0 => format!("{:.2} kHz", value / 1000.0),
+            _ => format!("{:.1} kHz", value / 1000.0),
+        }
+    }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        parse_with_unit(text, "Hz")
+    }
+}
+
+/// Displays times in ms, switching to s for values of 1 s and above.
+///
+/// The unit of the user value is configurable, e.g. `ms` or `s`.
+#[derive(Clone)]
+pub struct TimeStringConverter {
+    unit: String,
+    seconds_per_unit: f32,
+}
+
+impl TimeStringConverter {
+    pub fn new(unit: String) -> Self {
+        let seconds_per_unit = split_metric_prefix(&unit).0;
+        Self {
+            unit,
+            seconds_per_unit,
+        }
+    }
+}
+
+impl StringConverter for TimeStringConverter {
+    fn to_string(&self, value: f32) -> String {
+        let seconds = value * self.seconds_per_unit;
+        let ms = seconds * 1000.0;
+        match ms.abs() {
+            ms_abs if ms_abs < 9.995 => format!("{ms:.2} ms"),
+            ms_abs if ms_abs < 99.95 => format!("{ms:.1} ms"),
+            ms_abs if ms_abs < 999.5 => format!("{ms:.0} ms"),
+            ms_abs if ms_abs < 9995.0 => format!("{seconds:.2} s"),
+            _ => format!("{seconds:.1} s"),
+        }
+    }
+
+    fn from_string(&self, text: &str) -> Option<f32> {
+        parse_with_unit(text, &self.unit)
+    }
+}
+
+/// Parses a number that's optionally followed by a unit.
+///
+/// The unit may differ from the expected unit by a metric prefix, in which case the value is scaled,
+/// e.g. with an expected unit of `ms`, `2.5 s` is parsed as `2500`.
+fn parse_with_unit(text: &str, unit: &str) -> Option<f32> {
+    let text = text.trim();
+
+    // Find the longest prefix of the text that can be parsed as a number
+    let (value, suffix) = text
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(text.len()))
+        .rev()
+        .find_map(|index| {
+            let value = text[..index].trim_end().parse::<f32>().ok()?;
+            Some((value, text[index..].trim()))
+        })?;
+
+    // Infinite values aren't valid user values, e.g. `inf`, or a huge value with a large prefix
+    if !value.is_finite() {
+        return None;
+    }
+
+    if suffix.is_empty() || suffix == unit {
+        return Some(value);
+    }
+
+    let (unit_scale, unit_base) = split_metric_prefix(unit);
+    let (suffix_scale, suffix_base) = split_metric_prefix(suffix);
+    if !unit_base.is_empty() && suffix_base == unit_base {
+        Some(value * suffix_scale / unit_scale).filter(|value| value.is_finite())
+    } else {
+        None
+    }
+}
+
+fn split_metric_prefix(unit: &str) -> (f32, &str) {
+    const PREFIXES: [(&str, f32); 5] = [
+        ("G", 1.0e9),
+        ("M", 1.0e6),
+        ("k", 1.0e3),
+        ("m", 1.0e-3),
+        ("µ", 1.0e-6),
+    ];
+
+    PREFIXES
+        .iter()
+        .find_map(|(prefix, scale)| {
+            unit.strip_prefix(prefix)
+                .filter(|base| !base.is_empty())
+                .map(|base| (*scale, base))
+        })
+        .unwrap_or((1.0, unit))
 }
 
 pub fn float_string_converter(parameter: &FloatParameter) -> Box<dyn StringConverter> {
-    Box::new(FloatStringConverter::new(parameter.unit.clone()))
+    Box::new(FloatStringConverter::new(parameter.unit.clone()).precision(parameter.precision))
+}
+
+pub fn decibel_string_converter(_: &FloatParameter) -> Box<dyn StringConverter> {
+    Box::new(DecibelStringConverter {})
+}
+
+pub fn frequency_string_converter(_: &FloatParameter) -> Box<dyn StringConverter> {
+    Box::new(FrequencyStringConverter {})
+}
+
+pub fn time_string_converter(parameter: &FloatParameter) -> Box<dyn StringConverter> {
+    Box::new(TimeStringConverter::new(parameter.unit.clone()))
 }
 
 pub fn percent_string_converter(_: &FloatParameter) -> Box<dyn StringConverter> {
     Box::new(PercentStringConverter {})
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{FloatParameter, Parameter},
+    };
+
+    #[test]
+    fn choice() {
+        let converter = ChoiceStringConverter::new(vec!["a".into(), "b".into()]);
+        assert_eq!(converter.to_string(0.0), "a");
+        assert_eq!(converter.to_string(1.2), "b");
+        assert_eq!(converter.to_string(2.0), "");
+    }
+
+    #[test]
+    fn decibels() {
+        let converter = DecibelStringConverter {};
+        assert_eq!(converter.to_string(1.0), "0.0 dB");
+        assert_eq!(converter.to_string(0.5), "-6.0 dB");
+        assert_eq!(converter.to_string(0.99999), "0.0 dB");
+        assert_eq!(converter.to_string(0.0), "-inf dB");
+        assert_eq!(converter.from_string("-inf dB"), Some(0.0));
+        assert_eq!(converter.from_string("-inf"), Some(0.0));
+        assert!((converter.from_string("-6 dB").unwrap() - 0.501).abs() < 0.001);
+        assert_eq!(converter.from_string("inf"), None);
+    }
+
+    #[test]
+    fn float_precision() {
+        let converter = FloatStringConverter::new("dB".into());
+        assert_eq!(converter.to_string(-3.25), "-3 dB");
+        assert_eq!(converter.precision(2).to_string(-3.25), "-3.25 dB");
+    }
+
+    #[test]
+    fn frequency() {
+        let converter = FrequencyStringConverter {};
+        assert_eq!(converter.to_string(20.0), "20.0 Hz");
+        assert_eq!(converter.to_string(440.0), "440 Hz");
+        assert_eq!(converter.to_string(999.7), "1.00 kHz");
+        assert_eq!(converter.to_string(2500.0), "2.50 kHz");
+        assert_eq!(converter.to_string(12000.0), "12.0 kHz");
+        assert_eq!(converter.from_string("2.5 kHz"), Some(2500.0));
+    }
+
+    #[test]
+    fn time() {
+        let converter = TimeStringConverter::new("s".into());
+        assert_eq!(converter.to_string(0.35), "350 ms");
+        assert_eq!(converter.to_string(0.0025), "2.50 ms");
+        assert_eq!(converter.to_string(0.025), "25.0 ms");
+        assert_eq!(converter.to_string(1.5), "1.50 s");
+        assert_eq!(converter.to_string(20.0), "20.0 s");
+        assert!((converter.from_string("350 ms").unwrap() - 0.35).abs() < 1.0e-6);
+
+        let converter = TimeStringConverter::new("ms".into());
+        assert_eq!(converter.to_string(250.0), "250 ms");
+        assert_eq!(converter.to_string(1200.0), "1.20 s");
+        assert_eq!(converter.from_string("1.2 s"), Some(1200.0));
+    }
+
+    #[test]
+    fn int() {
+        let converter = IntStringConverter::new("st".into());
+        assert_eq!(converter.to_string(7.0), "7 st");
+        assert_eq!(converter.to_string(-0.2), "0 st");
+        assert_eq!(converter.to_string(-1.6), "-2 st");
+        assert_eq!(IntStringConverter::new(String::new()).to_string(3.0), "3");
+    }
+
+    #[test]
+    fn parse_bool() {
+        let converter = BoolStringConverter {};
+        assert_eq!(converter.from_string("on"), Some(1.0));
+        assert_eq!(converter.from_string(" Off "), Some(0.0));
+        assert_eq!(converter.from_string("1"), Some(1.0));
+        assert_eq!(converter.from_string("maybe"), None);
+    }
+
+    #[test]
+    fn parse_choice() {
+        let converter = ChoiceStringConverter::new(vec!["Natural".into(), "Gated".into()]);
+        assert_eq!(converter.from_string("gated"), Some(1.0));
+        assert_eq!(converter.from_string("0"), Some(0.0));
+        assert_eq!(converter.from_string("2"), None);
+        assert_eq!(converter.from_string("Reverse"), None);
+    }
+
+    #[test]
+    fn parse_default() {
+        let converter = DefaultStringConverter {};
+        assert_eq!(converter.from_string("42"), Some(42.0));
+        assert_eq!(converter.from_string("-1.5"), Some(-1.5));
+        assert_eq!(converter.from_string("nan"), None);
+        assert_eq!(converter.from_string("12 dB"), None);
+    }
+
+    #[test]
+    fn parse_float() {
+        let converter = FloatStringConverter::new("ms".into());
+        assert_eq!(converter.from_string("120"), Some(120.0));
+        assert_eq!(converter.from_string("120 ms"), Some(120.0));
+        assert_eq!(converter.from_string("120ms"), Some(120.0));
+        assert_eq!(converter.from_string("2.5 s"), Some(2500.0));
+        assert_eq!(converter.from_string("2.5 Hz"), None);
+        assert_eq!(converter.from_string("ms"), None);
+
+        let converter = FloatStringConverter::new("Hz".into());
+        assert_eq!(converter.from_string("1.5 kHz"), Some(1500.0));
+
+        let converter = FloatStringConverter::new("dB".into());
+        assert_eq!(converter.from_string("-6 dB"), Some(-6.0));
+    }
+
+    #[test]
+    fn parse_non_finite() {
+        let converter = FloatStringConverter::new("ms".into());
+        assert_eq!(converter.from_string("inf"), None);
+        assert_eq!(converter.from_string("-inf ms"), None);
+        assert_eq!(converter.from_string("infinity"), None);
+        assert_eq!(converter.from_string("3e38 Ms"), None);
+        assert_eq!(PercentStringConverter {}.from_string("inf %"), None);
+        assert_eq!(BoolStringConverter {}.from_string("inf"), None);
+
+        let parameter = FloatParameter::new("time", "Time")
+            .unit("ms")
+            .range(0.0, 1000.0);
+        assert_eq!(parameter.parse_user_value("inf"), None);
+        assert_eq!(parameter.parse_user_value("-inf"), None);
+        assert_eq!(parameter.parse_user_value("250 ms"), Some(250.0));
+    }
+
+    #[test]
+    fn parse_int() {
+        let converter = IntStringConverter::new("st".into());
+        assert_eq!(converter.from_string("7 st"), Some(7.0));
+        assert_eq!(converter.from_string("6.6"), Some(7.0));
+    }
+
+    #[test]
+    fn parse_percent() {
+        let converter = PercentStringConverter {};
+        assert_eq!(converter.from_string("35 %"), Some(0.35));
+        assert_eq!(converter.from_string("35%"), Some(0.35));
+        assert_eq!(converter.from_string("35"), Some(0.35));
+        assert_eq!(converter.from_string("35 ms"), None);
+    }
+}
@@ -1,10 +1,65 @@
-use crate::FloatParameter;
+use {crate::FloatParameter, std::fmt};
 
-pub trait ValueConverter {
+/// Converts between a parameter's user values and the linear range `0..=1`.
+///
+/// Converters are `Send` so that they can be used on the audio thread, e.g. by plugin wrappers
+/// that receive automation in the linear range.
+pub trait ValueConverter: Send {
     fn user_to_linear(&self, value: f32) -> f32;
     fn linear_to_user(&self, value: f32) -> f32;
 }
 
+/// An error returned when a value converter can't represent a range of user values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueConverterError {
+    /// The range is empty, reversed, or not finite.
+    InvalidRange { min: f32, max: f32 },
+    /// A log curve requires the range to be strictly positive.
+    NonPositiveLogRange { min: f32, max: f32 },
+    /// The center value of a skewed curve must lie strictly inside the range.
+    InvalidCenter { center: f32, min: f32, max: f32 },
+}
+
+impl fmt::Display for ValueConverterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidRange { min, max } => write!(f, "invalid range: {min} to {max}"),
+            Self::NonPositiveLogRange { min, max } => {
+                write!(
+                    f,
+                    "a log curve requires a positive range, found {min} to {max}"
+                )
+            }
+            Self::InvalidCenter { center, min, max } => {
+                write!(
+                    f,
+                    "center value {center} is outside of the range {min} to {max}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValueConverterError {}
+
+fn validate_range(min: f32, max: f32) -> Result<(), ValueConverterError> {
+    if min.is_finite() && max.is_finite() && min < max {
+        Ok(())
+    } else {
+        Err(ValueConverterError::InvalidRange { min, max })
+    }
+}
+
+/// Converts a gain value to decibels, with a gain of 0 resulting in `-inf`.
+pub fn gain_to_db(gain: f32) -> f32 {
+    20.0 * gain.log10()
+}
+
+/// Converts a value in decibels to a gain value, with `-inf` resulting in 0.
+pub fn db_to_gain(db: f32) -> f32 {
+    10.0f32.powf(db / 20.0)
+}
+
 pub struct DefaultValueConverter {}
 
 impl ValueConverter for DefaultValueConverter {
@@ -47,11 +102,26 @@ pub struct LogValueConverter {
 }
 
 impl LogValueConverter {
+    /// Makes a log converter for the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range can't be represented by a log curve, see [LogValueConverter::try_new].
     pub fn new(min: f32, max: f32) -> Self {
-        Self {
+        Self::try_new(min, max).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Makes a log converter for the given range, which must be strictly positive.
+    pub fn try_new(min: f32, max: f32) -> Result<Self, ValueConverterError> {
+        validate_range(min, max)?;
+        if min <= 0.0 {
+            return Err(ValueConverterError::NonPositiveLogRange { min, max });
+        }
+
+        Ok(Self {
             log_min_user_value: min.log2(),
             log_user_value_range: max.log2() - min.log2(),
-        }
+        })
     }
 }
 
@@ -65,6 +135,211 @@ impl ValueConverter for LogValueConverter {
     }
 }
 
+/// A power curve, skewed so that a chosen center value is in the middle of the linear range.
+///
+/// Unlike [LogValueConverter], the range may include 0 or negative values.
+pub struct SkewValueConverter {
+    pub min_user_value: f32,
+    pub user_value_range: f32,
+    pub exponent: f32,
+}
+
+impl SkewValueConverter {
+    /// Makes a skewed converter for the given range and center value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range or center value is invalid, see [SkewValueConverter::try_new].
+    pub fn new(min: f32, max: f32, center: f32) -> Self {
+        Self::try_new(min, max, center).unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Makes a skewed converter, with the center value required to be strictly inside the range.
+    pub fn try_new(min: f32, max: f32, center: f32) -> Result<Self, ValueConverterError> {
+        validate_range(min, max)?;
+        if !(center > min && center < max) {
+            return Err(ValueConverterError::InvalidCenter { center, min, max });
+        }
+
+        let user_value_range = max - min;
+        Ok(Self {
+            min_user_value: min,
+            user_value_range,
+            // The exponent that maps a linear value of 0.5 to the center value
+            exponent: ((center - min) / user_value_range).ln() / 0.5f32.ln(),
+        })
+    }
+}
+
+impl ValueConverter for SkewValueConverter {
+    fn user_to_linear(&self, value: f32) -> f32 {
+        let normalized = ((value - self.min_user_value) / self.user_value_range).clamp(0.0, 1.0);
+        normalized.powf(self.exponent.recip())
+    }
+
+    fn linear_to_user(&self, value: f32) -> f32 {
+        self.min_user_value + value.clamp(0.0, 1.0).powf(self.exponent) * self.user_value_range
+    }
+}
+
+/// Converts gain user values so that the linear value is proportional to the gain in decibels.
+///
+/// Gains at or below the minimum are mapped to 0, and a linear value of 0 results in silence.
+pub struct DecibelValueConverter {
+    pub min_db: f32,
+    pub db_range: f32,
+}
+
+impl DecibelValueConverter {
+    pub fn new(min_db: f32, max_db: f32) -> Self {
+        Self {
+            min_db,
+            db_range: max_db - min_db,
+        }
+    }
+}
+
+impl ValueConverter for DecibelValueConverter {
+    fn user_to_linear(&self, value: f32) -> f32 {
+        // NaN comparisons are false, so this also handles silence, with gain_to_db(0) being -inf
+        let linear = (gain_to_db(value) - self.min_db) / self.db_range;
+        if linear > 0.0 { linear } else { 0.0 }
+    }
+
+    fn linear_to_user(&self, value: f32) -> f32 {
+        if value > 0.0 {
+            db_to_gain(self.min_db + value * self.db_range)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A fader-style taper for gain controls.
+///
+/// Above the knee the linear value is proportional to the gain in decibels, and below it the gain
+/// falls linearly to silence, so that there's no jump in level at the bottom of the range.
+pub struct DecibelTaperValueConverter {
+    pub knee_db: f32,
+    pub max_db: f32,
+    pub knee_position: f32,
+}
+
+impl DecibelTaperValueConverter {
+    /// The default linear position of the knee.
+    pub const KNEE_POSITION: f32 = 0.25;
+
+    /// Makes a taper with the knee at [Self::KNEE_POSITION].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `knee_db` isn't below `max_db`.
+    pub fn new(knee_db: f32, max_db: f32) -> Self {
+        validate_range(knee_db, max_db).unwrap_or_else(|error| panic!("{error}"));
+        Self {
+            knee_db,
+            max_db,
+            knee_position: Self::KNEE_POSITION,
+        }
+    }
+
+    fn knee_gain(&self) -> f32 {
+        db_to_gain(self.knee_db)
+    }
+}
+
+impl ValueConverter for DecibelTaperValueConverter {
+    fn user_to_linear(&self, value: f32) -> f32 {
+        let knee_gain = self.knee_gain();
+        if value >= knee_gain {
+            let db_position = (gain_to_db(value) - self.knee_db) / (self.max_db - self.knee_db);
+            (self.knee_position + db_position * (1.0 - self.knee_position)).min(1.0)
+        } else {
+            (value.max(0.0) / knee_gain) * self.knee_position
+        }
+    }
+
+    fn linear_to_user(&self, value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        if value >= self.knee_position {
+            let db_position = (value - self.knee_position) / (1.0 - self.knee_position);
+            db_to_gain(self.knee_db + db_position * (self.max_db - self.knee_db))
+        } else {
+            value / self.knee_position * self.knee_gain()
+        }
+    }
+}
+
+/// A linear converter that quantizes user values to multiples of a step size, offset from the
+/// minimum value.
+pub struct SteppedValueConverter {
+    pub min_user_value: f32,
+    pub max_user_value: f32,
+    pub step: f32,
+}
+
+impl SteppedValueConverter {
+    pub fn new(min: f32, max: f32, step: f32) -> Self {
+        Self {
+            min_user_value: min,
+            max_user_value: max,
+            step,
+        }
+    }
+
+    fn quantize(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min_user_value, self.max_user_value);
+        if self.step > 0.0 {
+            let steps = ((value - self.min_user_value) / self.step).round();
+            (self.min_user_value + steps * self.step).min(self.max_user_value)
+        } else {
+            value
+        }
+    }
+}
+
+impl ValueConverter for SteppedValueConverter {
+    fn user_to_linear(&self, value: f32) -> f32 {
+        let range = self.max_user_value - self.min_user_value;
+        if range > 0.0 {
+            (self.quantize(value) - self.min_user_value) / range
+        } else {
+            0.0
+        }
+    }
+
+    fn linear_to_user(&self, value: f32) -> f32 {
+        let range = self.max_user_value - self.min_user_value;
+        self.quantize(self.min_user_value + value * range)
+    }
+}
+
+pub struct ChoiceValueConverter {
+    pub last_index: f32,
+}
+
+impl ChoiceValueConverter {
+    pub fn new(option_count: usize) -> Self {
+        Self {
+            last_index: option_count.saturating_sub(1) as f32,
+        }
+    }
+}
+
+impl ValueConverter for ChoiceValueConverter {
+    fn user_to_linear(&self, value: f32) -> f32 {
+        if self.last_index > 0.0 {
+            value / self.last_index
+        } else {
+            0.0
+        }
+    }
+
+    fn linear_to_user(&self, value: f32) -> f32 {
+        (value * self.last_index).round()
+    }
+}
+
 pub fn linear_value_converter(parameter: &FloatParameter) -> Box<dyn ValueConverter> {
     Box::new(LinearValueConverter::new(
         parameter.min_user_value,
@@ -72,9 +347,149 @@ pub fn linear_value_converter(parameter: &FloatParameter) -> Box<dyn ValueConver
     ))
 }
 
+/// Makes a [LogValueConverter] for the parameter's range.
+///
+/// # Panics
+///
+/// Panics if the parameter's range isn't strictly positive.
 pub fn log_value_converter(parameter: &FloatParameter) -> Box<dyn ValueConverter> {
     Box::new(LogValueConverter::new(
         parameter.min_user_value,
         parameter.max_user_value,
     ))
 }
+
+/// Makes a [SkewValueConverter] with the parameter's center value in the middle of the range.
+///
+/// If the parameter doesn't have a center value then the midpoint of the range is used, which
+/// results in a linear curve.
+pub fn skew_value_converter(parameter: &FloatParameter) -> Box<dyn ValueConverter> {
+    let (min, max) = (parameter.min_user_value, parameter.max_user_value);
+    let center = parameter
+        .center_user_value
+        .unwrap_or(min + (max - min) / 2.0);
+    Box::new(SkewValueConverter::new(min, max, center))
+}
+
+/// The gain in decibels at the knee of [decibel_taper_value_converter].
+pub const DECIBEL_TAPER_KNEE_DB: f32 = -40.0;
+
+/// Makes a [DecibelTaperValueConverter] for a gain parameter, with the parameter's maximum gain at
+/// the top of the range.
+pub fn decibel_taper_value_converter(parameter: &FloatParameter) -> Box<dyn ValueConverter> {
+    Box::new(DecibelTaperValueConverter::new(
+        DECIBEL_TAPER_KNEE_DB,
+        gain_to_db(parameter.max_user_value),
+    ))
+}
+
+/// The lowest gain in decibels that's represented by [decibel_value_converter] before silence.
+pub const DECIBEL_CONVERTER_MIN_DB: f32 = -60.0;
+
+/// Makes a [DecibelValueConverter] for a gain parameter, with the parameter's maximum gain at the
+/// top of the range.
+pub fn decibel_value_converter(parameter: &FloatParameter) -> Box<dyn ValueConverter> {
+    Box::new(DecibelValueConverter::new(
+        DECIBEL_CONVERTER_MIN_DB,
+        gain_to_db(parameter.max_user_value),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decibels() {
+        let converter = DecibelValueConverter::new(-60.0, 0.0);
+        assert_eq!(converter.user_to_linear(1.0), 1.0);
+        assert!((converter.user_to_linear(db_to_gain(-30.0)) - 0.5).abs() < 1.0e-5);
+        assert_eq!(converter.user_to_linear(0.0), 0.0);
+        assert_eq!(converter.user_to_linear(1.0e-6), 0.0);
+        assert_eq!(converter.linear_to_user(0.0), 0.0);
+        assert_eq!(converter.linear_to_user(1.0), 1.0);
+        assert!((converter.linear_to_user(0.5) - db_to_gain(-30.0)).abs() < 1.0e-5);
+        assert!((converter.linear_to_user(0.01) - db_to_gain(-59.4)).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn choice() {
+        let converter = ChoiceValueConverter::new(3);
+        assert_eq!(converter.user_to_linear(0.0), 0.0);
+        assert_eq!(converter.user_to_linear(1.0), 0.5);
+        assert_eq!(converter.user_to_linear(2.0), 1.0);
+        assert_eq!(converter.linear_to_user(0.2), 0.0);
+        assert_eq!(converter.linear_to_user(0.3), 1.0);
+        assert_eq!(converter.linear_to_user(1.0), 2.0);
+
+        let single = ChoiceValueConverter::new(1);
+        assert_eq!(single.user_to_linear(0.0), 0.0);
+        assert_eq!(single.linear_to_user(1.0), 0.0);
+    }
+
+    #[test]
+    fn log_range_validation() {
+        assert!(LogValueConverter::try_new(20.0, 20000.0).is_ok());
+        assert_eq!(
+            LogValueConverter::try_new(0.0, 1.0).err(),
+            Some(ValueConverterError::NonPositiveLogRange { min: 0.0, max: 1.0 })
+        );
+        assert!(LogValueConverter::try_new(-1.0, 1.0).is_err());
+        assert!(LogValueConverter::try_new(10.0, 1.0).is_err());
+        assert!(LogValueConverter::try_new(1.0, f32::INFINITY).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn log_with_zero_minimum_panics() {
+        LogValueConverter::new(0.0, 1.0);
+    }
+
+    #[test]
+    fn skew() {
+        let converter = SkewValueConverter::new(0.0, 1000.0, 100.0);
+        assert_eq!(converter.user_to_linear(0.0), 0.0);
+        assert!((converter.user_to_linear(100.0) - 0.5).abs() < 1.0e-5);
+        assert_eq!(converter.user_to_linear(1000.0), 1.0);
+        assert_eq!(converter.linear_to_user(0.0), 0.0);
+        assert!((converter.linear_to_user(0.5) - 100.0).abs() < 1.0e-3);
+        assert_eq!(converter.linear_to_user(1.0), 1000.0);
+        assert_eq!(converter.user_to_linear(-10.0), 0.0);
+
+        let linear = SkewValueConverter::new(-1.0, 1.0, 0.0);
+        assert!((linear.linear_to_user(0.75) - 0.5).abs() < 1.0e-5);
+
+        assert!(SkewValueConverter::try_new(0.0, 1.0, 1.0).is_err());
+        assert!(SkewValueConverter::try_new(0.0, 1.0, f32::NAN).is_err());
+    }
+
+    #[test]
+    fn decibel_taper() {
+        let converter = DecibelTaperValueConverter::new(-40.0, 0.0);
+        assert_eq!(converter.user_to_linear(0.0), 0.0);
+        assert_eq!(converter.user_to_linear(1.0), 1.0);
+        assert!((converter.user_to_linear(db_to_gain(-40.0)) - 0.25).abs() < 1.0e-5);
+        assert!((converter.user_to_linear(db_to_gain(-20.0)) - 0.625).abs() < 1.0e-5);
+        assert_eq!(converter.linear_to_user(0.0), 0.0);
+        assert!((converter.linear_to_user(0.125) - db_to_gain(-40.0) / 2.0).abs() < 1.0e-6);
+        assert!((converter.linear_to_user(1.0) - 1.0).abs() < 1.0e-6);
+
+        // The curve is continuous at the knee
+        let below = converter.linear_to_user(0.2499);
+        let above = converter.linear_to_user(0.2501);
+        assert!((above - below).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn stepped() {
+        let converter = SteppedValueConverter::new(-2.0, 10.0, 3.0);
+        assert_eq!(converter.user_to_linear(-2.0), 0.0);
+        assert_eq!(converter.user_to_linear(0.0), 0.25);
+        assert_eq!(converter.user_to_linear(7.0), 0.75);
+        assert_eq!(converter.user_to_linear(20.0), 1.0);
+        assert_eq!(converter.linear_to_user(0.0), -2.0);
+        assert_eq!(converter.linear_to_user(0.3), 1.0);
+        assert_eq!(converter.linear_to_user(0.9), 10.0);
+        assert_eq!(converter.linear_to_user(1.0), 10.0);
+    }
+}
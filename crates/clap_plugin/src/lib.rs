@@ -0,0 +1,290 @@
+//! A generic [CLAP](https://cleveraudio.org) plugin wrapper for any [AudioModule].
+//!
+//! A plugin is described by implementing [PluginDefinition], and then exported from a `cdylib`
+//! crate with [export_clap_plugin]:
+//!
+//! ```ignore
+//! struct Freeverb;
+//!
+//! impl clap_plugin::PluginDefinition for Freeverb {
+//!     type Module = freeverb_module::FreeverbModule;
+//!
+//!     const ID: &'static str = "rs.freeverb";
+//!     const NAME: &'static str = "Freeverb";
+//!     const VENDOR: &'static str = "freeverb-rs";
+//!     const VERSION: &'static str = "0.1.0";
+//!     const FEATURES: &'static [&'static std::ffi::CStr] =
+//!         &[clap_plugin::features::AUDIO_EFFECT, clap_plugin::features::REVERB];
+//! }
+//!
+//! clap_plugin::export_clap_plugin!(Freeverb);
+//! ```
+//!
+//! The module's parameters are exposed to the host via their value and string converters, and
+//! the plugin's state is saved in the [Preset](audio_module::Preset) text format, so that state
+//! saved by a host can also be read as a preset. The plugin has a single stereo input and output.
+
+mod parameters;
+mod plugin;
+
+pub use {
+    clack_plugin::plugin::features,
+    plugin::{ModuleAudioProcessor, ModuleMainThread, ModulePlugin, ModuleShared},
+};
+
+#[doc(hidden)]
+pub use clack_plugin;
+
+use {audio_module::AudioModule, std::ffi::CStr};
+
+/// Describes a plugin that wraps an [AudioModule].
+pub trait PluginDefinition: 'static {
+    type Module: AudioModule;
+
+    /// A unique identifier for the plugin in reverse domain name notation, e.g. `com.example.verb`.
+    const ID: &'static str;
+    const NAME: &'static str;
+    const VENDOR: &'static str;
+    const VERSION: &'static str;
+    const DESCRIPTION: &'static str = "";
+    /// The categories that hosts use to sort the plugin, see [features].
+    const FEATURES: &'static [&'static CStr];
+}
+
+/// Exports the `clap_entry` symbol for a [PluginDefinition]'s plugin.
+///
+/// This should be used once in a `cdylib` crate, the resulting library can then be renamed with a
+/// `.clap` extension and installed in a CLAP plugin folder.
+#[macro_export]
+macro_rules! export_clap_plugin {
+    ($definition:ty) => {
+        $crate::clack_plugin::clack_export_entry!(
+            $crate::clack_plugin::entry::SinglePluginEntry<$crate::ModulePlugin<$definition>>
+        );
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        audio_module::ParameterProvider,
+        clack_extensions::{params::PluginParams, state::PluginState},
+        clack_host::{events::event_types::ParamValueEvent, prelude::*},
+        freeverb_module::FreeverbModule,
+    };
+
+    struct TestDefinition;
+
+    impl PluginDefinition for TestDefinition {
+        type Module = FreeverbModule;
+
+        const ID: &'static str = "rs.freeverb.test";
+        const NAME: &'static str = "Freeverb Test";
+        const VENDOR: &'static str = "freeverb-rs";
+        const VERSION: &'static str = "0.1.0";
+        const FEATURES: &'static [&'static CStr] = &[features::AUDIO_EFFECT, features::REVERB];
+    }
+
+    type TestEntry = clack_plugin::entry::SinglePluginEntry<ModulePlugin<TestDefinition>>;
+
+    const BLOCK_FRAMES: usize = 64;
+
+    struct TestHostShared;
+
+    impl SharedHandler<'_> for TestHostShared {
+        fn request_restart(&self) {}
+        fn request_process(&self) {}
+        fn request_callback(&self) {}
+    }
+
+    struct TestHost;
+
+    impl HostHandlers for TestHost {
+        type Shared<'a> = TestHostShared;
+        type MainThread<'a> = ();
+        type AudioProcessor<'a> = ();
+    }
+
+    fn make_instance() -> PluginInstance<TestHost> {
+        let entry = PluginEntry::load_from_clack::<TestEntry>(c"/test/freeverb.clap").unwrap();
+        let host_info = HostInfo::new("Test Host", "freeverb-rs", "", "0.1.0").unwrap();
+        PluginInstance::<TestHost>::new(
+            |_| TestHostShared,
+            |_| (),
+            &entry,
+            c"rs.freeverb.test",
+            &host_info,
+        )
+        .unwrap()
+    }
+
+    fn parameter_id(id: &str) -> ClapId {
+        ClapId::new(FreeverbModule::parameter_index(id).unwrap() as u32)
+    }
+
+    // Processes a stereo block, returning the output channels
+    fn process_block(
+        processor: &mut StartedPluginAudioProcessor<TestHost>,
+        input: &mut [[f32; BLOCK_FRAMES]; 2],
+        events: &[ParamValueEvent],
+    ) -> [[f32; BLOCK_FRAMES]; 2] {
+        let mut output = [[0.0; BLOCK_FRAMES]; 2];
+        let mut input_ports = AudioPorts::with_capacity(2, 1);
+        let mut output_ports = AudioPorts::with_capacity(2, 1);
+
+        let input_audio = input_ports.with_input_buffers([AudioPortBuffer {
+            latency: 0,
+            channels: AudioPortBufferType::f32_input_only(
+                input.iter_mut().map(InputChannel::variable),
+            ),
+        }]);
+        let mut output_audio = output_ports.with_output_buffers([AudioPortBuffer {
+            latency: 0,
+            channels: AudioPortBufferType::f32_output_only(
+                output.iter_mut().map(|channel| channel.as_mut_slice()),
+            ),
+        }]);
+
+        processor
+            .process(
+                &input_audio,
+                &mut output_audio,
+                &InputEvents::from_buffer(&events),
+                &mut OutputEvents::void(),
+                None,
+                None,
+            )
+            .unwrap();
+
+        output
+    }
+
+    #[test]
+    fn descriptor() {
+        let entry = PluginEntry::load_from_clack::<TestEntry>(c"/test/freeverb.clap").unwrap();
+        let factory = entry.get_plugin_factory().unwrap();
+        let descriptor = factory.plugin_descriptor(0).unwrap();
+        assert_eq!(descriptor.id().unwrap(), c"rs.freeverb.test");
+        assert_eq!(descriptor.name().unwrap(), c"Freeverb Test");
+        assert_eq!(descriptor.vendor().unwrap(), c"freeverb-rs");
+    }
+
+    #[test]
+    fn parameters() {
+        let mut instance = make_instance();
+        let params: PluginParams = instance.plugin_handle().get_extension().unwrap();
+        let handle = instance.plugin_handle();
+
+        assert_eq!(
+            params.count(&handle) as usize,
+            FreeverbModule::parameter_count()
+        );
+
+        let room_size = parameter_id("room_size");
+        assert_eq!(params.get_value(&handle, room_size), Some(0.5));
+
+        let mut buffer = [0; 64];
+        let text = params
+            .value_to_text(&handle, room_size, 0.25, &mut buffer)
+            .unwrap();
+        assert_eq!(text, b"25 %");
+        let value = params.text_to_value(&handle, room_size, c"80 %").unwrap();
+        assert!((value - 0.8).abs() < 1.0e-6);
+
+        let freeze = parameter_id("freeze");
+        assert_eq!(params.text_to_value(&handle, freeze, c"on"), Some(1.0));
+        assert_eq!(
+            params.get_value(
+                &handle,
+                ClapId::new(FreeverbModule::parameter_count() as u32)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let room_size = parameter_id("room_size");
+        let freeze = parameter_id("freeze");
+
+        let mut instance = make_instance();
+        let params: PluginParams = instance.plugin_handle().get_extension().unwrap();
+        let state: PluginState = instance.plugin_handle().get_extension().unwrap();
+
+        let events = [
+            ParamValueEvent::new(0, room_size, Pckn::match_all(), 0.75),
+            ParamValueEvent::new(0, freeze, Pckn::match_all(), 1.0),
+        ];
+        params.flush(
+            &mut instance.inactive_plugin_handle().unwrap(),
+            &InputEvents::from_buffer(&events),
+            &mut OutputEvents::void(),
+        );
+
+        let mut saved = Vec::new();
+        state.save(&instance.plugin_handle(), &mut saved).unwrap();
+        let text = String::from_utf8(saved.clone()).unwrap();
+        assert!(
+            text.contains("room_size = 0.75"),
+            "Unexpected state: {text}"
+        );
+
+        let mut restored = make_instance();
+        let params: PluginParams = restored.plugin_handle().get_extension().unwrap();
+        let state: PluginState = restored.plugin_handle().get_extension().unwrap();
+        let handle = restored.plugin_handle();
+        state.load(&handle, &mut saved.as_slice()).unwrap();
+        assert_eq!(params.get_value(&handle, room_size), Some(0.75));
+        assert_eq!(params.get_value(&handle, freeze), Some(1.0));
+
+        assert!(
+            state
+                .load(&handle, &mut b"width = wide".as_slice())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn processing_with_automation() {
+        let mut instance = make_instance();
+        let params: PluginParams = instance.plugin_handle().get_extension().unwrap();
+        let config = PluginAudioConfiguration {
+            sample_rate: 44100.0,
+            min_frames_count: 1,
+            max_frames_count: BLOCK_FRAMES as u32,
+        };
+        let processor = instance.activate(|_, _| (), config).unwrap();
+        let mut processor = processor.start_processing().unwrap();
+
+        // The dry signal is enabled partway through the block, so only the second impulse is heard
+        // before the reverb's delay lines have filled
+        let mut input = [[0.0; BLOCK_FRAMES]; 2];
+        for channel in input.iter_mut() {
+            channel[0] = 1.0;
+            channel[32] = 1.0;
+        }
+        let dry = parameter_id("dry");
+        let wet = parameter_id("wet");
+        let events = [ParamValueEvent::new(32, dry, Pckn::match_all(), 1.0)];
+        let output = process_block(&mut processor, &mut input, &events);
+        for channel in output {
+            assert_eq!(channel[0], 0.0);
+            assert!(channel[32] > 0.0);
+        }
+
+        // Silencing the wet and dry signals via automation silences the output
+        let events = [
+            ParamValueEvent::new(0, dry, Pckn::match_all(), 0.0),
+            ParamValueEvent::new(0, wet, Pckn::match_all(), 0.0),
+        ];
+        let output = process_block(&mut processor, &mut input, &events);
+        assert!(output.iter().flatten().all(|sample| *sample == 0.0));
+
+        // Automated values are reported back to the host
+        let processor = processor.stop_processing();
+        instance.deactivate(processor);
+        let handle = instance.plugin_handle();
+        assert_eq!(params.get_value(&handle, wet), Some(0.0));
+    }
+}
@@ -0,0 +1,163 @@
+use {
+    audio_module::{HostParameter, NormalizedValueConverter, ValueType},
+    clack_extensions::params::{ParamInfo, ParamInfoFlags},
+    clack_plugin::utils::{ClapId, Cookie},
+};
+
+/// Converts between a parameter's user values and the values that are seen by the host.
+///
+/// Float parameters are exposed in the normalized range `0..=1`, so that automation follows the
+/// parameter's value converter, e.g. a decibel taper. Bool, int, and choice parameters are exposed
+/// as stepped values in their user range, so that ints show their real values and hosts can list
+/// the options of choices.
+pub(crate) trait HostValueConverter {
+    fn host_range(&self) -> (f64, f64);
+    fn user_to_host(&self, value: f32) -> f64;
+    fn host_to_user(&self, value: f64) -> f32;
+}
+
+impl HostValueConverter for NormalizedValueConverter {
+    fn host_range(&self) -> (f64, f64) {
+        match self.step_count() {
+            None => (0.0, 1.0),
+            Some(_) => {
+                let (min, max) = self.user_range();
+                (min as f64, max as f64)
+            }
+        }
+    }
+
+    fn user_to_host(&self, value: f32) -> f64 {
+        match self.step_count() {
+            None => self.user_to_normalized(value) as f64,
+            Some(_) => self.constrain(value) as f64,
+        }
+    }
+
+    fn host_to_user(&self, value: f64) -> f32 {
+        match self.step_count() {
+            None => self.normalized_to_user(value as f32),
+            Some(_) => self.constrain(value as f32),
+        }
+    }
+}
+
+/// A module parameter as it's presented to the host, see [HostValueConverter].
+pub(crate) trait ClapParameter {
+    fn info(&self, index: usize) -> ParamInfo<'_>;
+
+    /// Returns the display string for a value in the host's value range.
+    fn host_to_string(&self, value: f64) -> String;
+
+    /// Parses a display string or plain user value, returning a value in the host's value range.
+    fn string_to_host(&self, text: &str) -> Option<f64>;
+}
+
+impl ClapParameter for HostParameter {
+    fn info(&self, index: usize) -> ParamInfo<'_> {
+        let converter = self.converter();
+        let (min_value, max_value) = converter.host_range();
+        let flags = match converter.value_type() {
+            ValueType::Float => ParamInfoFlags::IS_AUTOMATABLE,
+            ValueType::Bool | ValueType::Int { .. } => {
+                ParamInfoFlags::IS_AUTOMATABLE | ParamInfoFlags::IS_STEPPED
+            }
+            ValueType::Choice(_) => {
+                ParamInfoFlags::IS_AUTOMATABLE
+                    | ParamInfoFlags::IS_STEPPED
+                    | ParamInfoFlags::IS_ENUM
+            }
+        };
+
+        ParamInfo {
+            id: ClapId::new(index as u32),
+            flags,
+            cookie: Cookie::empty(),
+            name: self.name().as_bytes(),
+            module: b"",
+            min_value,
+            max_value,
+            default_value: converter.user_to_host(self.default_user_value()),
+        }
+    }
+
+    fn host_to_string(&self, value: f64) -> String {
+        self.user_to_string(self.converter().host_to_user(value))
+    }
+
+    fn string_to_host(&self, text: &str) -> Option<f64> {
+        self.parse_user_value(text)
+            .map(|value| self.converter().user_to_host(value))
+    }
+}
+
+/// Returns the parameter index for a host parameter id, if it's in range.
+pub(crate) fn parameter_index<T>(parameters: &[T], id: ClapId) -> Option<usize> {
+    let index = id.get() as usize;
+    (index < parameters.len()).then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, audio_module::ParameterProvider, freeverb_module::FreeverbModule};
+
+    fn parameter(id: &str) -> (usize, HostParameter) {
+        let index = FreeverbModule::parameter_index(id).unwrap();
+        let parameter = HostParameter::all::<FreeverbModule>().swap_remove(index);
+        (index, parameter)
+    }
+
+    #[test]
+    fn float_parameters_are_normalized() {
+        let (index, gate_hold) = parameter("gate_hold");
+        let info = gate_hold.info(index);
+        assert_eq!(info.id, ClapId::new(index as u32));
+        assert_eq!(info.name, b"Gate Hold");
+        assert_eq!((info.min_value, info.max_value), (0.0, 1.0));
+        assert!(!info.flags.contains(ParamInfoFlags::IS_STEPPED));
+
+        // The skew converter puts the default value in the middle of the range
+        assert!((info.default_value - 0.5).abs() < 1.0e-6);
+        assert!((gate_hold.converter().host_to_user(0.5) - 250.0).abs() < 1.0e-3);
+        assert_eq!(gate_hold.converter().host_to_user(2.0), 1000.0);
+    }
+
+    #[test]
+    fn bool_and_choice_parameters_are_stepped() {
+        let (index, freeze) = parameter("freeze");
+        let info = freeze.info(index);
+        assert_eq!((info.min_value, info.max_value), (0.0, 1.0));
+        assert!(info.flags.contains(ParamInfoFlags::IS_STEPPED));
+        assert_eq!(freeze.converter().host_to_user(0.7), 1.0);
+
+        let (index, tail_mode) = parameter("tail_mode");
+        let info = tail_mode.info(index);
+        assert_eq!((info.min_value, info.max_value), (0.0, 2.0));
+        assert!(info.flags.contains(ParamInfoFlags::IS_ENUM));
+        assert_eq!(tail_mode.converter().host_to_user(1.2), 1.0);
+        assert_eq!(tail_mode.host_to_string(2.0), "Reverse");
+        assert_eq!(tail_mode.string_to_host("Gated"), Some(1.0));
+
+        // Int parameters are exposed with their user range
+        let (index, shimmer_pitch) = parameter("shimmer_pitch");
+        let info = shimmer_pitch.info(index);
+        assert_eq!((info.min_value, info.max_value), (0.0, 12.0));
+        assert_eq!(info.default_value, 12.0);
+        assert!(info.flags.contains(ParamInfoFlags::IS_STEPPED));
+        assert!(!info.flags.contains(ParamInfoFlags::IS_ENUM));
+        assert_eq!(shimmer_pitch.converter().host_to_user(6.6), 7.0);
+        assert_eq!(shimmer_pitch.converter().user_to_host(20.0), 12.0);
+        assert_eq!(shimmer_pitch.host_to_string(7.0), "7 st");
+    }
+
+    #[test]
+    fn strings() {
+        let (_, room_size) = parameter("room_size");
+        assert_eq!(room_size.host_to_string(0.75), "75 %");
+        assert_eq!(room_size.string_to_host("25 %"), Some(0.25));
+        assert_eq!(room_size.string_to_host("0.5"), Some(0.5));
+        // Values outside of the parameter's range are rejected
+        assert_eq!(room_size.string_to_host("150 %"), None);
+        assert_eq!(room_size.string_to_host("big"), None);
+    }
+}
@@ -0,0 +1,379 @@
+use {
+    crate::{
+        PluginDefinition,
+        parameters::{ClapParameter, HostValueConverter, parameter_index},
+    },
+    audio_module::{
+        AudioModule, AudioProcessor, Command, CommandHandler, HostParameter,
+        NormalizedValueConverter, ParameterStore, Preset,
+    },
+    clack_extensions::{
+        audio_ports::{
+            AudioPortFlags, AudioPortInfo, AudioPortInfoWriter, AudioPortType, PluginAudioPorts,
+            PluginAudioPortsImpl,
+        },
+        latency::{PluginLatency, PluginLatencyImpl},
+        params::{
+            ParamDisplayWriter, ParamInfoWriter, PluginAudioProcessorParams,
+            PluginMainThreadParams, PluginParams,
+        },
+        state::{PluginState, PluginStateImpl},
+        tail::{PluginTail, PluginTailImpl, TailLength},
+    },
+    clack_plugin::{
+        events::spaces::CoreEventSpace,
+        prelude::*,
+        stream::{InputStream, OutputStream},
+    },
+    std::{
+        ffi::CStr,
+        fmt::Write as _,
+        io::{Read, Write},
+        marker::PhantomData,
+        sync::atomic::{AtomicU32, Ordering},
+    },
+};
+
+// The number of channels in the plugin's audio ports, matching the processors' interleaved stereo
+const PROCESSOR_CHANNELS: usize = 2;
+
+/// A CLAP plugin that wraps the [AudioModule] of a [PluginDefinition].
+///
+/// Export it from a `cdylib` crate with [export_clap_plugin](crate::export_clap_plugin).
+pub struct ModulePlugin<Definition: PluginDefinition>(PhantomData<Definition>);
+
+impl<Definition: PluginDefinition> Plugin for ModulePlugin<Definition> {
+    type AudioProcessor<'a> = ModuleAudioProcessor<'a, Definition>;
+    type Shared<'a> = ModuleShared;
+    type MainThread<'a> = ModuleMainThread<'a, Definition>;
+
+    fn declare_extensions(builder: &mut PluginExtensions<Self>, _shared: Option<&ModuleShared>) {
+        builder
+            .register::<PluginAudioPorts>()
+            .register::<PluginLatency>()
+            .register::<PluginParams>()
+            .register::<PluginState>()
+            .register::<PluginTail>();
+    }
+}
+
+impl<Definition: PluginDefinition> DefaultPluginFactory for ModulePlugin<Definition> {
+    fn get_descriptor() -> PluginDescriptor {
+        PluginDescriptor::new(Definition::ID, Definition::NAME)
+            .with_vendor(Definition::VENDOR)
+            .with_version(Definition::VERSION)
+            .with_description(Definition::DESCRIPTION)
+            .with_features(Definition::FEATURES.iter().copied())
+    }
+
+    fn new_shared(_host: HostSharedHandle<'_>) -> Result<ModuleShared, PluginError> {
+        Ok(ModuleShared {
+            store: ParameterStore::with_defaults::<Definition::Module>(),
+            latency: AtomicU32::new(0),
+        })
+    }
+
+    fn new_main_thread<'a>(
+        _host: HostMainThreadHandle<'a>,
+        shared: &'a ModuleShared,
+    ) -> Result<Self::MainThread<'a>, PluginError> {
+        Ok(ModuleMainThread {
+            shared,
+            parameters: HostParameter::all::<Definition::Module>(),
+            _definition: PhantomData,
+        })
+    }
+}
+
+/// State that's shared between the plugin's main thread and audio processor.
+///
+/// Parameter values are held in a [ParameterStore], with changes made on the main thread (e.g.
+/// when state is loaded) being pulled by the audio processor, and changes from automation being
+/// stored by the audio processor so that the host can query them.
+pub struct ModuleShared {
+    store: ParameterStore,
+    latency: AtomicU32,
+}
+
+impl PluginShared<'_> for ModuleShared {}
+
+pub struct ModuleMainThread<'a, Definition: PluginDefinition> {
+    shared: &'a ModuleShared,
+    parameters: Vec<HostParameter>,
+    _definition: PhantomData<Definition>,
+}
+
+impl<'a, Definition: PluginDefinition> PluginMainThread<'a, ModuleShared>
+    for ModuleMainThread<'a, Definition>
+{
+}
+
+impl<Definition: PluginDefinition> PluginAudioPortsImpl for ModuleMainThread<'_, Definition> {
+    fn count(&self, _is_input: bool) -> u32 {
+        1
+    }
+
+    fn get(&self, index: u32, is_input: bool, writer: &mut AudioPortInfoWriter) {
+        if index == 0 {
+            writer.set(&AudioPortInfo {
+                id: ClapId::new(0),
+                name: if is_input { b"Main In" } else { b"Main Out" },
+                channel_count: PROCESSOR_CHANNELS as u32,
+                flags: AudioPortFlags::IS_MAIN,
+                port_type: Some(AudioPortType::STEREO),
+                in_place_pair: Some(ClapId::new(0)),
+            });
+        }
+    }
+}
+
+impl<Definition: PluginDefinition> PluginLatencyImpl for ModuleMainThread<'_, Definition> {
+    fn get(&self) -> u32 {
+        self.shared.latency.load(Ordering::Relaxed)
+    }
+}
+
+impl<Definition: PluginDefinition> PluginMainThreadParams for ModuleMainThread<'_, Definition> {
+    fn count(&self) -> u32 {
+        self.parameters.len() as u32
+    }
+
+    fn get_info(&self, param_index: u32, info: &mut ParamInfoWriter) {
+        let index = param_index as usize;
+        if let Some(parameter) = self.parameters.get(index) {
+            info.set(&parameter.info(index));
+        }
+    }
+
+    fn get_value(&self, param_id: ClapId) -> Option<f64> {
+        let index = parameter_index(&self.parameters, param_id)?;
+        let value = self.shared.store.get(index)?;
+        Some(self.parameters[index].converter().user_to_host(value))
+    }
+
+    fn value_to_text(
+        &self,
+        param_id: ClapId,
+        value: f64,
+        writer: &mut ParamDisplayWriter,
+    ) -> std::fmt::Result {
+        let index = parameter_index(&self.parameters, param_id).ok_or(std::fmt::Error)?;
+        write!(writer, "{}", self.parameters[index].host_to_string(value))
+    }
+
+    fn text_to_value(&self, param_id: ClapId, text: &CStr) -> Option<f64> {
+        let index = parameter_index(&self.parameters, param_id)?;
+        self.parameters[index].string_to_host(text.to_str().ok()?)
+    }
+
+    fn flush(&self, input_parameter_changes: &InputEvents, _output: &mut OutputEvents) {
+        // While the plugin is inactive, changes are stored to be pulled once processing starts
+        for event in input_parameter_changes {
+            if let Some(CoreEventSpace::ParamValue(event)) = event.as_core_event()
+                && let Some(index) = event
+                    .param_id()
+                    .and_then(|id| parameter_index(&self.parameters, id))
+            {
+                let value = self.parameters[index]
+                    .converter()
+                    .host_to_user(event.value());
+                let _ = self.shared.store.set(index, value);
+            }
+        }
+    }
+}
+
+impl<Definition: PluginDefinition> PluginStateImpl for ModuleMainThread<'_, Definition> {
+    fn save(&self, output: &mut OutputStream) -> Result<(), PluginError> {
+        let preset = Preset::from_values::<Definition::Module>(
+            Definition::NAME,
+            &self.shared.store.snapshot(),
+        );
+        output.write_all(preset.to_text().as_bytes())?;
+        Ok(())
+    }
+
+    fn load(&self, input: &mut InputStream) -> Result<(), PluginError> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let preset = Preset::from_text::<Definition::Module>(&text)?;
+
+        for (index, value) in preset
+            .user_values::<Definition::Module>()
+            .into_iter()
+            .enumerate()
+        {
+            let _ = self.shared.store.set(index, value);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ModuleAudioProcessor<'a, Definition: PluginDefinition> {
+    shared: &'a ModuleShared,
+    converters: Vec<NormalizedValueConverter>,
+    processor: <Definition::Module as AudioModule>::Processor,
+    // Interleaved buffers for the processor, allocated for the host's maximum block size
+    input: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl<'a, Definition: PluginDefinition>
+    PluginAudioProcessor<'a, ModuleShared, ModuleMainThread<'a, Definition>>
+    for ModuleAudioProcessor<'a, Definition>
+{
+    fn activate(
+        _host: HostAudioProcessorHandle<'a>,
+        _main_thread: &ModuleMainThread<'a, Definition>,
+        shared: &'a ModuleShared,
+        audio_config: PluginAudioConfiguration,
+    ) -> Result<Self, PluginError> {
+        let mut processor = Definition::Module::create_processor(audio_config.sample_rate as usize);
+        for (index, value) in shared.store.snapshot().into_iter().enumerate() {
+            let _ = processor.handle_command(Command::SetParameter(index, value));
+        }
+        shared
+            .latency
+            .store(processor.latency_samples() as u32, Ordering::Relaxed);
+
+        let buffer_size = audio_config.max_frames_count as usize * PROCESSOR_CHANNELS;
+        Ok(Self {
+            shared,
+            converters: NormalizedValueConverter::all::<Definition::Module>(),
+            processor,
+            input: vec![0.0; buffer_size],
+            output: vec![0.0; buffer_size],
+        })
+    }
+
+    fn process(
+        &mut self,
+        _process: Process,
+        mut audio: Audio,
+        events: Events,
+    ) -> Result<ProcessStatus, PluginError> {
+        self.pull_parameter_changes();
+        for event in events.input {
+            self.handle_event(event, true);
+        }
+
+        let mut port_pair = audio
+            .port_pair(0)
+            .ok_or(PluginError::Message("The main audio port is missing"))?;
+        let mut channels = port_pair
+            .channels()?
+            .into_f32()
+            .ok_or(PluginError::Message("Expected 32-bit audio"))?;
+
+        let frames = channels.frames_count() as usize;
+        let samples = frames * PROCESSOR_CHANNELS;
+        if samples > self.input.len() {
+            return Err(PluginError::Message(
+                "The block is larger than the maximum block size",
+            ));
+        }
+        let channel_count = channels.channel_pair_count().min(PROCESSOR_CHANNELS);
+
+        // Mono input is copied to both of the processor's channels
+        self.input[..samples].fill(0.0);
+        for channel in 0..PROCESSOR_CHANNELS {
+            let source = match channels.channel_pair(channel.min(channel_count.max(1) - 1)) {
+                Some(ChannelPair::InputOnly(input) | ChannelPair::InputOutput(input, _)) => input,
+                Some(ChannelPair::InPlace(buffer)) => buffer,
+                _ => continue,
+            };
+            for (frame, sample) in source.iter().take(frames).enumerate() {
+                self.input[frame * PROCESSOR_CHANNELS + channel] = *sample;
+            }
+        }
+
+        self.processor.process(
+            &self.input[..samples],
+            &mut self.output[..samples],
+            PROCESSOR_CHANNELS as u32,
+        );
+
+        for channel in 0..channels.channel_pair_count() {
+            let output = match channels.channel_pair(channel) {
+                Some(ChannelPair::OutputOnly(output) | ChannelPair::InputOutput(_, output)) => {
+                    output
+                }
+                Some(ChannelPair::InPlace(buffer)) => buffer,
+                _ => continue,
+            };
+            if channel < PROCESSOR_CHANNELS {
+                for (frame, sample) in output.iter_mut().take(frames).enumerate() {
+                    *sample = self.output[frame * PROCESSOR_CHANNELS + channel];
+                }
+            } else {
+                output.fill(0.0);
+            }
+        }
+
+        Ok(ProcessStatus::Continue)
+    }
+
+    fn reset(&mut self) {
+        self.processor.reset();
+    }
+}
+
+impl<Definition: PluginDefinition> ModuleAudioProcessor<'_, Definition> {
+    // Changes made on the main thread are passed on to the processor
+    fn pull_parameter_changes(&mut self) {
+        let processor = &mut self.processor;
+        self.shared.store.pull_audio_changes(|index, value| {
+            let _ = processor.handle_command(Command::SetParameter(index, value));
+        });
+    }
+
+    // Parameter events are applied at their frame offset when processing, or immediately when
+    // they arrive via a flush
+    fn handle_event(&mut self, event: &UnknownEvent, timestamped: bool) {
+        let Some(CoreEventSpace::ParamValue(event)) = event.as_core_event() else {
+            return;
+        };
+        let Some(index) = event
+            .param_id()
+            .and_then(|id| parameter_index(&self.converters, id))
+        else {
+            return;
+        };
+
+        let value = self.converters[index].host_to_user(event.value());
+        let command = if timestamped {
+            Command::SetParameterAt {
+                id: index,
+                value,
+                frame_offset: event.header().time() as usize,
+            }
+        } else {
+            Command::SetParameter(index, value)
+        };
+
+        // The index has been checked, so the command can't fail
+        let _ = self.processor.handle_command(command);
+        let _ = self.shared.store.set_from_audio(index, value);
+    }
+}
+
+impl<Definition: PluginDefinition> PluginAudioProcessorParams
+    for ModuleAudioProcessor<'_, Definition>
+{
+    fn flush(&mut self, input_parameter_changes: &InputEvents, _output: &mut OutputEvents) {
+        self.pull_parameter_changes();
+        for event in input_parameter_changes {
+            self.handle_event(event, false);
+        }
+    }
+}
+
+impl<Definition: PluginDefinition> PluginTailImpl for ModuleAudioProcessor<'_, Definition> {
+    fn get(&self) -> TailLength {
+        match self.processor.tail_samples() {
+            Some(samples) => TailLength::Finite(u32::try_from(samples).unwrap_or(u32::MAX)),
+            None => TailLength::Infinite,
+        }
+    }
+}
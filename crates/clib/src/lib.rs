@@ -77,3 +77,13 @@ pub extern "C" fn set_dry(freeverb: &mut Freeverb<f64>, value: f64) {
 pub extern "C" fn set_room_size(freeverb: &mut Freeverb<f64>, value: f64) {
     freeverb.set_room_size(value)
 }
+
+#[unsafe(no_mangle)]
+pub extern "C" fn set_shimmer(freeverb: &mut Freeverb<f64>, value: f64) {
+    freeverb.set_shimmer(value)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn set_shimmer_pitch(freeverb: &mut Freeverb<f64>, semitones: f64) {
+    freeverb.set_shimmer_pitch(semitones)
+}
@@ -0,0 +1,90 @@
+/// The number of channels that processors are run with, device channels are mapped to and from
+/// this layout.
+pub const PROCESSOR_CHANNELS: usize = 2;
+
+/// Converts interleaved device samples to interleaved stereo samples.
+///
+/// Mono input is copied to both channels, and channels beyond the first two are ignored.
+pub fn device_to_stereo(device: &[f32], device_channels: usize, stereo: &mut [f32]) {
+    debug_assert_eq!(
+        device.len() / device_channels,
+        stereo.len() / PROCESSOR_CHANNELS
+    );
+
+    for (device_frame, stereo_frame) in device
+        .chunks_exact(device_channels)
+        .zip(stereo.chunks_exact_mut(PROCESSOR_CHANNELS))
+    {
+        match *device_frame {
+            [mono] => stereo_frame.fill(mono),
+            [left, right, ..] => stereo_frame.copy_from_slice(&[left, right]),
+            [] => stereo_frame.fill(0.0),
+        }
+    }
+}
+
+/// Converts interleaved stereo samples to interleaved device samples.
+///
+/// Mono output receives the average of both channels, and channels beyond the first two are
+/// silent.
+pub fn stereo_to_device(stereo: &[f32], device: &mut [f32], device_channels: usize) {
+    debug_assert_eq!(
+        device.len() / device_channels,
+        stereo.len() / PROCESSOR_CHANNELS
+    );
+
+    for (stereo_frame, device_frame) in stereo
+        .chunks_exact(PROCESSOR_CHANNELS)
+        .zip(device.chunks_exact_mut(device_channels))
+    {
+        match device_frame {
+            [mono] => *mono = (stereo_frame[0] + stereo_frame[1]) * 0.5,
+            [left, right, rest @ ..] => {
+                *left = stereo_frame[0];
+                *right = stereo_frame[1];
+                rest.fill(0.0);
+            }
+            [] => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mono() {
+        let mut stereo = [0.0; 4];
+        device_to_stereo(&[0.25, 0.5], 1, &mut stereo);
+        assert_eq!(stereo, [0.25, 0.25, 0.5, 0.5]);
+
+        let mut device = [0.0; 2];
+        stereo_to_device(&[0.25, 0.75, 1.0, 0.0], &mut device, 1);
+        assert_eq!(device, [0.5, 0.5]);
+    }
+
+    #[test]
+    fn stereo() {
+        let samples = [0.1, 0.2, 0.3, 0.4];
+
+        let mut stereo = [0.0; 4];
+        device_to_stereo(&samples, 2, &mut stereo);
+        assert_eq!(stereo, samples);
+
+        let mut device = [0.0; 4];
+        stereo_to_device(&samples, &mut device, 2);
+        assert_eq!(device, samples);
+    }
+
+    #[test]
+    fn multichannel() {
+        let mut stereo = [0.0; 4];
+        device_to_stereo(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8], 4, &mut stereo);
+        assert_eq!(stereo, [0.1, 0.2, 0.5, 0.6]);
+
+        let mut device = [1.0; 8];
+        stereo_to_device(&[0.1, 0.2, 0.3, 0.4], &mut device, 4);
+        assert_eq!(device, [0.1, 0.2, 0.0, 0.0, 0.3, 0.4, 0.0, 0.0]);
+    }
+}
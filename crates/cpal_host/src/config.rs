@@ -0,0 +1,179 @@
+use {
+    crate::AudioHostError,
+    cpal::traits::{DeviceTrait, HostTrait},
+    std::fmt,
+};
+
+/// The direction of an audio device or stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Input,
+    Output,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Input => write!(f, "input"),
+            Self::Output => write!(f, "output"),
+        }
+    }
+}
+
+/// Describes the audio devices and stream settings to use.
+#[derive(Clone, Debug)]
+pub struct HostConfig {
+    /// The name of the audio host, e.g. `ALSA` or `JACK`, or `None` for the system's default.
+    pub host: Option<String>,
+    /// The name of the input device, or `None` for the host's default.
+    pub input_device: Option<String>,
+    /// The name of the output device, or `None` for the host's default.
+    pub output_device: Option<String>,
+    pub sample_rate: u32,
+    /// The number of frames per callback, or `None` to let the host decide.
+    pub buffer_size: Option<u32>,
+    /// The number of channels to open on each device.
+    pub channels: u16,
+    /// Whether or not input should be processed, otherwise the processor receives silence.
+    pub duplex: bool,
+    /// The number of input frames that are buffered ahead of the output, larger values add
+    /// latency but make underruns less likely when the streams' callbacks are irregular.
+    pub latency_frames: u32,
+}
+
+impl Default for HostConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            input_device: None,
+            output_device: None,
+            sample_rate: 44100,
+            buffer_size: None,
+            channels: 2,
+            duplex: true,
+            latency_frames: 256,
+        }
+    }
+}
+
+impl HostConfig {
+    pub(crate) fn stream_config(&self) -> cpal::StreamConfig {
+        cpal::StreamConfig {
+            channels: self.channels,
+            sample_rate: cpal::SampleRate(self.sample_rate),
+            buffer_size: match self.buffer_size {
+                Some(frames) => cpal::BufferSize::Fixed(frames),
+                None => cpal::BufferSize::Default,
+            },
+        }
+    }
+}
+
+/// Information about an audio device, see [list_devices].
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub name: String,
+    pub is_input: bool,
+    pub is_output: bool,
+    pub is_default: bool,
+}
+
+/// Returns the names of the audio hosts that are available on this system.
+pub fn host_names() -> Vec<&'static str> {
+    cpal::available_hosts()
+        .into_iter()
+        .map(|id| id.name())
+        .collect()
+}
+
+/// Lists the devices that are provided by a host, or by the default host if `host` is `None`.
+pub fn list_devices(host: Option<&str>) -> Result<Vec<DeviceInfo>, AudioHostError> {
+    let host = find_host(host)?;
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    let default_output = host.default_output_device().and_then(|d| d.name().ok());
+
+    let mut devices = Vec::new();
+    for device in host.devices()? {
+        let name = device.name()?;
+        devices.push(DeviceInfo {
+            is_input: device.supports_input(),
+            is_output: device.supports_output(),
+            is_default: default_input.as_ref() == Some(&name)
+                || default_output.as_ref() == Some(&name),
+            name,
+        });
+    }
+
+    Ok(devices)
+}
+
+pub(crate) fn find_host(name: Option<&str>) -> Result<cpal::Host, AudioHostError> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+        .ok_or_else(|| AudioHostError::UnknownHost {
+            name: name.to_string(),
+            available: host_names(),
+        })?;
+
+    cpal::host_from_id(id).map_err(|_| AudioHostError::HostUnavailable(id.name().to_string()))
+}
+
+pub(crate) fn find_device(
+    host: &cpal::Host,
+    name: Option<&str>,
+    direction: Direction,
+) -> Result<cpal::Device, AudioHostError> {
+    let Some(name) = name else {
+        let device = match direction {
+            Direction::Input => host.default_input_device(),
+            Direction::Output => host.default_output_device(),
+        };
+        return device.ok_or(AudioHostError::NoDefaultDevice(direction));
+    };
+
+    let mut devices: Box<dyn Iterator<Item = cpal::Device>> = match direction {
+        Direction::Input => Box::new(host.input_devices()?),
+        Direction::Output => Box::new(host.output_devices()?),
+    };
+
+    devices
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or_else(|| AudioHostError::DeviceNotFound {
+            direction,
+            name: name.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_host() {
+        match find_host(Some("Not A Host")) {
+            Err(AudioHostError::UnknownHost { name, available }) => {
+                assert_eq!(name, "Not A Host");
+                assert_eq!(available, host_names());
+            }
+            _ => panic!("Expected an unknown host error"),
+        }
+    }
+
+    #[test]
+    fn stream_config() {
+        let config = HostConfig {
+            buffer_size: Some(256),
+            channels: 1,
+            ..Default::default()
+        };
+        let stream_config = config.stream_config();
+        assert_eq!(stream_config.channels, 1);
+        assert_eq!(stream_config.sample_rate, cpal::SampleRate(44100));
+        assert_eq!(stream_config.buffer_size, cpal::BufferSize::Fixed(256));
+    }
+}
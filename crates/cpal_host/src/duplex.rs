@@ -0,0 +1,357 @@
+use {
+    crate::PROCESSOR_CHANNELS,
+    ringbuf::{
+        HeapCons, HeapProd,
+        traits::{Consumer, Observer, Producer},
+    },
+    std::sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+// How far the queue's fill level can stray from the target latency before frames are dropped or
+// inserted
+const DRIFT_TOLERANCE_FRAMES: usize = 16;
+
+// The number of callbacks that the fill level is measured over before deciding on a correction
+const DRIFT_WINDOW_CALLBACKS: usize = 64;
+
+/// Counters for the problems that occurred while passing audio between the input and output
+/// streams, shared between the audio callbacks and e.g. a GUI.
+#[derive(Debug, Default)]
+pub struct EngineStats {
+    input_overflows: AtomicU64,
+    output_underruns: AtomicU64,
+    dropped_frames: AtomicU64,
+    inserted_frames: AtomicU64,
+    failed_commands: AtomicU64,
+}
+
+impl EngineStats {
+    /// The number of input callbacks that couldn't be queued in full because the output stream
+    /// wasn't keeping up.
+    pub fn input_overflows(&self) -> u64 {
+        self.input_overflows.load(Ordering::Relaxed)
+    }
+
+    /// The number of output callbacks that ran out of input.
+    pub fn output_underruns(&self) -> u64 {
+        self.output_underruns.load(Ordering::Relaxed)
+    }
+
+    /// The total number of overflows and underruns.
+    pub fn xruns(&self) -> u64 {
+        self.input_overflows() + self.output_underruns()
+    }
+
+    /// The number of input frames that were skipped to compensate for the input clock running
+    /// faster than the output clock.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// The number of frames that were repeated to compensate for the input clock running slower
+    /// than the output clock.
+    pub fn inserted_frames(&self) -> u64 {
+        self.inserted_frames.load(Ordering::Relaxed)
+    }
+
+    /// The number of commands that the processor failed to handle.
+    ///
+    /// Failures are counted rather than reported on the audio thread, so that they can be
+    /// reported elsewhere without blocking audio.
+    pub fn failed_commands(&self) -> u64 {
+        self.failed_commands.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_failed_command(&self) {
+        Self::increment(&self.failed_commands, 1);
+    }
+
+    fn increment(counter: &AtomicU64, amount: u64) {
+        counter.fetch_add(amount, Ordering::Relaxed);
+    }
+}
+
+/// Queues stereo input frames from the input stream's callback.
+pub(crate) struct InputWriter {
+    producer: HeapProd<f32>,
+    stats: Arc<EngineStats>,
+}
+
+impl InputWriter {
+    pub fn new(producer: HeapProd<f32>, stats: Arc<EngineStats>) -> Self {
+        Self { producer, stats }
+    }
+
+    /// Queues interleaved stereo samples, frames that don't fit in the queue are dropped.
+    pub fn write(&mut self, stereo: &[f32]) {
+        let written = self.producer.push_slice(stereo);
+        if written < stereo.len() {
+            EngineStats::increment(&self.stats.input_overflows, 1);
+        }
+    }
+}
+
+/// Reads queued input for the output stream's callback, keeping a target number of frames in the
+/// queue.
+///
+/// Reading starts once the queue has filled up to the target latency. After that, the lowest fill
+/// level is measured over a window of callbacks, which smooths out the jitter caused by the
+/// streams' callbacks arriving in turn. Drift between the input and output clocks shows up as the
+/// lowest fill level moving away from the target, which is corrected by dropping or repeating
+/// single frames over the following callbacks. If the queue runs dry then the missing input is
+/// replaced with silence, and reading pauses until the queue has filled up again.
+pub(crate) struct InputReader {
+    consumer: HeapCons<f32>,
+    latency_frames: usize,
+    primed: bool,
+    lowest_fill: usize,
+    measured_callbacks: usize,
+    // Positive values are frames waiting to be dropped, negative values are frames to be inserted
+    pending_correction: isize,
+    stats: Arc<EngineStats>,
+}
+
+impl InputReader {
+    pub fn new(consumer: HeapCons<f32>, latency_frames: usize, stats: Arc<EngineStats>) -> Self {
+        Self {
+            consumer,
+            latency_frames,
+            primed: false,
+            lowest_fill: usize::MAX,
+            measured_callbacks: 0,
+            pending_correction: 0,
+            stats,
+        }
+    }
+
+    /// Fills the interleaved stereo buffer with queued input.
+    pub fn read(&mut self, stereo: &mut [f32]) {
+        let frames = stereo.len() / PROCESSOR_CHANNELS;
+        let available = self.consumer.occupied_len() / PROCESSOR_CHANNELS;
+
+        if !self.primed {
+            if available < self.latency_frames + frames {
+                stereo.fill(0.0);
+                return;
+            }
+            self.primed = true;
+            self.reset_measurement();
+            self.pending_correction = 0;
+        }
+
+        if available < frames {
+            let read = self.consumer.pop_slice(stereo);
+            stereo[read..].fill(0.0);
+            EngineStats::increment(&self.stats.output_underruns, 1);
+            self.primed = false;
+            return;
+        }
+
+        if self.pending_correction > 0 && available > frames {
+            // The input is running fast, so skip a frame
+            self.consumer.skip(PROCESSOR_CHANNELS);
+            self.consumer.pop_slice(stereo);
+            self.pending_correction -= 1;
+            EngineStats::increment(&self.stats.dropped_frames, 1);
+        } else if self.pending_correction < 0 && frames > 1 {
+            // The input is running slow, so repeat the last frame
+            let (read, last) = stereo.split_at_mut(stereo.len() - PROCESSOR_CHANNELS);
+            self.consumer.pop_slice(read);
+            last.copy_from_slice(&read[read.len() - PROCESSOR_CHANNELS..]);
+            self.pending_correction += 1;
+            EngineStats::increment(&self.stats.inserted_frames, 1);
+        } else {
+            self.consumer.pop_slice(stereo);
+            // The fill level is only measured while no corrections are being made
+            self.measure(available - frames);
+        }
+    }
+
+    fn measure(&mut self, fill: usize) {
+        self.lowest_fill = self.lowest_fill.min(fill);
+        self.measured_callbacks += 1;
+
+        if self.measured_callbacks == DRIFT_WINDOW_CALLBACKS {
+            if self.lowest_fill.abs_diff(self.latency_frames) > DRIFT_TOLERANCE_FRAMES {
+                self.pending_correction = self.lowest_fill as isize - self.latency_frames as isize;
+            }
+            self.reset_measurement();
+        }
+    }
+
+    fn reset_measurement(&mut self) {
+        self.lowest_fill = usize::MAX;
+        self.measured_callbacks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        ringbuf::{HeapRb, traits::Split},
+    };
+
+    const BLOCK_FRAMES: usize = 8;
+    const LATENCY_FRAMES: usize = 64;
+
+    fn make_queue() -> (InputWriter, InputReader, Arc<EngineStats>) {
+        let stats = Arc::new(EngineStats::default());
+        let (producer, consumer) = HeapRb::new(1024 * PROCESSOR_CHANNELS).split();
+        (
+            InputWriter::new(producer, stats.clone()),
+            InputReader::new(consumer, LATENCY_FRAMES, stats.clone()),
+            stats,
+        )
+    }
+
+    // Returns a block of stereo frames with increasing values, starting at `start`
+    fn block(start: usize, frames: usize) -> Vec<f32> {
+        (start..start + frames)
+            .flat_map(|frame| [frame as f32, frame as f32])
+            .collect()
+    }
+
+    #[test]
+    fn reading_starts_after_the_latency_has_been_buffered() {
+        let (mut writer, mut reader, stats) = make_queue();
+        let mut output = [1.0; BLOCK_FRAMES * PROCESSOR_CHANNELS];
+
+        let mut written = 0;
+        while written < LATENCY_FRAMES {
+            writer.write(&block(written, BLOCK_FRAMES));
+            written += BLOCK_FRAMES;
+            reader.read(&mut output);
+            assert!(output.iter().all(|sample| *sample == 0.0));
+        }
+
+        writer.write(&block(written, BLOCK_FRAMES));
+        reader.read(&mut output);
+        assert_eq!(output.as_slice(), block(0, BLOCK_FRAMES));
+        assert_eq!(stats.xruns(), 0);
+    }
+
+    #[test]
+    fn underruns_are_counted_and_rebuffered() {
+        let (mut writer, mut reader, stats) = make_queue();
+        let mut output = [0.0; BLOCK_FRAMES * PROCESSOR_CHANNELS];
+
+        writer.write(&block(0, LATENCY_FRAMES + BLOCK_FRAMES));
+        // Read until the queue runs dry
+        for _ in 0..LATENCY_FRAMES / BLOCK_FRAMES + 2 {
+            reader.read(&mut output);
+        }
+        assert_eq!(stats.output_underruns(), 1);
+
+        // Reading pauses until the latency has been buffered again
+        writer.write(&block(0, BLOCK_FRAMES));
+        reader.read(&mut output);
+        assert!(output.iter().all(|sample| *sample == 0.0));
+        assert_eq!(stats.output_underruns(), 1);
+    }
+
+    #[test]
+    fn overflows_are_counted() {
+        let (mut writer, _reader, stats) = make_queue();
+        writer.write(&block(0, 1000));
+        assert_eq!(stats.input_overflows(), 0);
+        writer.write(&block(0, 100));
+        assert_eq!(stats.input_overflows(), 1);
+    }
+
+    // Runs the queue with the input delivering `input_frames` for every `BLOCK_FRAMES` of output
+    fn run_with_drift(input_frames: usize, blocks: usize) -> (Arc<EngineStats>, usize) {
+        let (mut writer, mut reader, stats) = make_queue();
+        let mut output = [0.0; BLOCK_FRAMES * PROCESSOR_CHANNELS];
+
+        writer.write(&block(0, LATENCY_FRAMES + BLOCK_FRAMES));
+        let mut written = LATENCY_FRAMES + BLOCK_FRAMES;
+        let mut read = 0;
+        let mut previous = None;
+
+        for i in 0..blocks {
+            // The input delivers an extra or missing frame every few blocks
+            let frames = if i % 4 == 0 {
+                input_frames
+            } else {
+                BLOCK_FRAMES
+            };
+            writer.write(&block(written, frames));
+            written += frames;
+
+            reader.read(&mut output);
+            read += BLOCK_FRAMES;
+
+            // The output stays continuous, with at most single frames being skipped or repeated
+            for frame in output.chunks_exact(PROCESSOR_CHANNELS) {
+                if let Some(previous) = previous {
+                    let step = frame[0] - previous;
+                    assert!((0.0..=2.0).contains(&step), "Unexpected step: {step}");
+                }
+                previous = Some(frame[0]);
+            }
+        }
+
+        // Every written frame has either been read, dropped, or is still queued, with inserted
+        // frames being read without having been written
+        let fill = reader.consumer.occupied_len() / PROCESSOR_CHANNELS;
+        assert_eq!(
+            written,
+            read + stats.dropped_frames() as usize - stats.inserted_frames() as usize + fill
+        );
+        (stats, fill)
+    }
+
+    #[test]
+    fn fast_input_drops_frames() {
+        let (stats, fill) = run_with_drift(BLOCK_FRAMES + 1, 2000);
+        assert!(stats.dropped_frames() > 0);
+        assert_eq!(stats.inserted_frames(), 0);
+        assert_eq!(stats.xruns(), 0);
+        assert!(
+            fill.abs_diff(LATENCY_FRAMES) < 2 * DRIFT_TOLERANCE_FRAMES + BLOCK_FRAMES,
+            "Unexpected fill: {fill}"
+        );
+    }
+
+    #[test]
+    fn slow_input_inserts_frames() {
+        let (stats, fill) = run_with_drift(BLOCK_FRAMES - 1, 2000);
+        assert!(stats.inserted_frames() > 0);
+        assert_eq!(stats.dropped_frames(), 0);
+        assert_eq!(stats.xruns(), 0);
+        assert!(
+            fill.abs_diff(LATENCY_FRAMES) < 2 * DRIFT_TOLERANCE_FRAMES + BLOCK_FRAMES,
+            "Unexpected fill: {fill}"
+        );
+    }
+
+    #[test]
+    fn irregular_callback_sizes() {
+        let (mut writer, mut reader, stats) = make_queue();
+        let mut written = 0;
+        let mut expected = 0.0;
+
+        for i in 0..3000 {
+            // The input callbacks vary in size, but deliver the same number of frames on average
+            let input_frames = [3, 13, 8][i % 3];
+            writer.write(&block(written, input_frames));
+            written += input_frames;
+
+            let mut output = vec![0.0; [5, 11][i % 2] * PROCESSOR_CHANNELS];
+            reader.read(&mut output);
+            if output.iter().any(|sample| *sample != 0.0) {
+                for frame in output.chunks_exact(PROCESSOR_CHANNELS) {
+                    assert_eq!(frame[0], expected);
+                    expected += 1.0;
+                }
+            }
+        }
+
+        assert_eq!(stats.xruns(), 0);
+        assert_eq!(stats.dropped_frames() + stats.inserted_frames(), 0);
+    }
+}
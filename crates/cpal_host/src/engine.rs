@@ -0,0 +1,198 @@
+use {
+    crate::{
+        AudioHostError, Direction, HostConfig, PROCESSOR_CHANNELS,
+        channel_map::{device_to_stereo, stereo_to_device},
+        config::{find_device, find_host},
+        duplex::{EngineStats, InputReader, InputWriter},
+    },
+    audio_module::{AudioProcessor, Command},
+    cpal::traits::{DeviceTrait, StreamTrait},
+    ringbuf::{HeapRb, traits::Split},
+    std::{sync::Arc, time::Duration},
+};
+
+// The largest number of frames that's processed at once, larger callbacks are split into blocks
+const MAX_BLOCK_FRAMES: usize = 1024;
+
+// The number of frames of input that can be queued for the output stream, beyond the configured
+// latency
+const INPUT_QUEUE_HEADROOM_FRAMES: usize = MAX_BLOCK_FRAMES * 4;
+
+/// A running processor along with the device streams that drive it.
+///
+/// Audio stops when the engine is dropped.
+pub struct AudioEngine {
+    _input: Option<cpal::Stream>,
+    _output: cpal::Stream,
+    input_device_name: Option<String>,
+    output_device_name: String,
+    stats: Arc<EngineStats>,
+    errors: crossbeam_channel::Receiver<cpal::StreamError>,
+}
+
+impl AudioEngine {
+    /// Opens the configured devices and starts running the processor.
+    ///
+    /// The processor is run in the output stream's callback, with input being passed from the
+    /// input stream via a queue that buffers the configured latency and compensates for drift
+    /// between the devices' clocks. Callbacks of any size are supported. Commands are taken from
+    /// `command_receiver` before each callback is processed, with commands that the processor
+    /// fails to handle being counted in the engine's [stats](Self::stats).
+    ///
+    /// The processor should have been created with the configuration's sample rate.
+    pub fn start<Processor: AudioProcessor>(
+        config: &HostConfig,
+        mut processor: Processor,
+        command_receiver: crossbeam_channel::Receiver<Command>,
+    ) -> Result<Self, AudioHostError> {
+        if config.channels == 0 {
+            return Err(AudioHostError::InvalidChannelCount(config.channels));
+        }
+
+        let host = find_host(config.host.as_deref())?;
+        let output_device = find_device(&host, config.output_device.as_deref(), Direction::Output)?;
+        let input_device = if config.duplex {
+            Some(find_device(
+                &host,
+                config.input_device.as_deref(),
+                Direction::Input,
+            )?)
+        } else {
+            None
+        };
+
+        let stream_config = config.stream_config();
+        let channels = config.channels as usize;
+        let (error_sender, errors) = crossbeam_channel::unbounded();
+
+        let stats = Arc::new(EngineStats::default());
+        let latency_frames = config.latency_frames as usize;
+        let ring_buffer =
+            HeapRb::new((latency_frames + INPUT_QUEUE_HEADROOM_FRAMES) * PROCESSOR_CHANNELS);
+        let (to_output, from_input) = ring_buffer.split();
+        let mut input_writer = InputWriter::new(to_output, stats.clone());
+        let mut input_reader = InputReader::new(from_input, latency_frames, stats.clone());
+
+        let input = match &input_device {
+            Some(device) => {
+                let mut stereo = vec![0.0; MAX_BLOCK_FRAMES * PROCESSOR_CHANNELS];
+                let error_sender = error_sender.clone();
+                let stream = device
+                    .build_input_stream(
+                        &stream_config,
+                        move |data: &[f32], _info: &cpal::InputCallbackInfo| {
+                            for block in data.chunks(MAX_BLOCK_FRAMES * channels) {
+                                let stereo = &mut stereo[..block.len() / channels * 2];
+                                device_to_stereo(block, channels, stereo);
+                                input_writer.write(stereo);
+                            }
+                        },
+                        move |error| {
+                            let _ = error_sender.send(error);
+                        },
+                        None,
+                    )
+                    .map_err(|error| AudioHostError::BuildStream {
+                        direction: Direction::Input,
+                        error,
+                    })?;
+                Some(stream)
+            }
+            None => None,
+        };
+
+        let duplex = input.is_some();
+        let output_stats = stats.clone();
+        let mut input_buffer = vec![0.0; MAX_BLOCK_FRAMES * PROCESSOR_CHANNELS];
+        let mut output_buffer = vec![0.0; MAX_BLOCK_FRAMES * PROCESSOR_CHANNELS];
+
+        let output = output_device
+            .build_output_stream(
+                &stream_config,
+                move |data: &mut [f32], _info: &cpal::OutputCallbackInfo| {
+                    while let Ok(command) = command_receiver.try_recv() {
+                        if processor.handle_command(command).is_err() {
+                            output_stats.record_failed_command();
+                        }
+                    }
+
+                    for block in data.chunks_mut(MAX_BLOCK_FRAMES * channels) {
+                        let samples = block.len() / channels * PROCESSOR_CHANNELS;
+                        let input = &mut input_buffer[..samples];
+                        let output = &mut output_buffer[..samples];
+
+                        if duplex {
+                            input_reader.read(input);
+                        } else {
+                            input.fill(0.0);
+                        }
+
+                        processor.process(input, output, PROCESSOR_CHANNELS as u32);
+                        stereo_to_device(output, block, channels);
+                    }
+                },
+                move |error| {
+                    let _ = error_sender.send(error);
+                },
+                None,
+            )
+            .map_err(|error| AudioHostError::BuildStream {
+                direction: Direction::Output,
+                error,
+            })?;
+
+        if let Some(input) = &input {
+            input.play().map_err(|error| AudioHostError::PlayStream {
+                direction: Direction::Input,
+                error,
+            })?;
+        }
+        output.play().map_err(|error| AudioHostError::PlayStream {
+            direction: Direction::Output,
+            error,
+        })?;
+
+        Ok(Self {
+            _input: input,
+            _output: output,
+            input_device_name: input_device.map(|device| device.name()).transpose()?,
+            output_device_name: output_device.name()?,
+            stats,
+            errors,
+        })
+    }
+
+    /// Returns the name of the input device, or `None` if input isn't being processed.
+    pub fn input_device_name(&self) -> Option<&str> {
+        self.input_device_name.as_deref()
+    }
+
+    pub fn output_device_name(&self) -> &str {
+        &self.output_device_name
+    }
+
+    /// Returns the counters for xruns, drift compensation, and failed commands, which are updated
+    /// while audio runs.
+    pub fn stats(&self) -> Arc<EngineStats> {
+        self.stats.clone()
+    }
+
+    /// Returns the next error reported by the device streams, if any.
+    pub fn try_recv_error(&self) -> Option<cpal::StreamError> {
+        self.errors.try_recv().ok()
+    }
+
+    /// Waits for an error to be reported by the device streams, returning `None` if no error was
+    /// reported before the timeout.
+    pub fn wait_for_error_timeout(&self, timeout: Duration) -> Option<cpal::StreamError> {
+        self.errors.recv_timeout(timeout).ok()
+    }
+
+    /// Blocks until an error is reported by the device streams.
+    pub fn wait_for_error(&self) -> cpal::StreamError {
+        // The engine holds the streams, so the senders stay alive while `self` is borrowed
+        self.errors
+            .recv()
+            .expect("the error channel should remain connected")
+    }
+}
@@ -0,0 +1,74 @@
+use {crate::Direction, std::fmt};
+
+/// An error that prevented audio from being started.
+#[derive(Debug)]
+pub enum AudioHostError {
+    /// The requested host isn't available on this system.
+    UnknownHost {
+        name: String,
+        available: Vec<&'static str>,
+    },
+    /// The host is known but couldn't be initialized, e.g. the JACK server isn't running.
+    HostUnavailable(String),
+    /// The host doesn't have a default device for the given direction.
+    NoDefaultDevice(Direction),
+    /// No device with the given name was found.
+    DeviceNotFound {
+        direction: Direction,
+        name: String,
+    },
+    /// The configuration requested a channel count that can't be used.
+    InvalidChannelCount(u16),
+    Devices(cpal::DevicesError),
+    DeviceName(cpal::DeviceNameError),
+    BuildStream {
+        direction: Direction,
+        error: cpal::BuildStreamError,
+    },
+    PlayStream {
+        direction: Direction,
+        error: cpal::PlayStreamError,
+    },
+}
+
+impl fmt::Display for AudioHostError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownHost { name, available } => write!(
+                f,
+                "unknown audio host '{name}', available hosts: {}",
+                available.join(", ")
+            ),
+            Self::HostUnavailable(name) => write!(f, "the audio host '{name}' is unavailable"),
+            Self::NoDefaultDevice(direction) => write!(f, "no default {direction} device"),
+            Self::DeviceNotFound { direction, name } => {
+                write!(f, "no {direction} device named '{name}'")
+            }
+            Self::InvalidChannelCount(channels) => {
+                write!(f, "invalid channel count ({channels})")
+            }
+            Self::Devices(error) => write!(f, "failed to list devices: {error}"),
+            Self::DeviceName(error) => write!(f, "failed to get a device's name: {error}"),
+            Self::BuildStream { direction, error } => {
+                write!(f, "failed to create the {direction} stream: {error}")
+            }
+            Self::PlayStream { direction, error } => {
+                write!(f, "failed to start the {direction} stream: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AudioHostError {}
+
+impl From<cpal::DevicesError> for AudioHostError {
+    fn from(error: cpal::DevicesError) -> Self {
+        Self::Devices(error)
+    }
+}
+
+impl From<cpal::DeviceNameError> for AudioHostError {
+    fn from(error: cpal::DeviceNameError) -> Self {
+        Self::DeviceName(error)
+    }
+}
@@ -0,0 +1,19 @@
+//! Runs an [AudioProcessor](audio_module::AudioProcessor) on audio devices via `cpal`, without
+//! depending on a GUI.
+
+mod channel_map;
+mod config;
+mod duplex;
+mod engine;
+mod error;
+
+pub use {
+    channel_map::{PROCESSOR_CHANNELS, device_to_stereo, stereo_to_device},
+    config::{DeviceInfo, Direction, HostConfig, host_names, list_devices},
+    duplex::EngineStats,
+    engine::AudioEngine,
+    error::AudioHostError,
+};
+
+// Re-exported so that users of the crate can handle stream errors without depending on cpal
+pub use cpal::StreamError;
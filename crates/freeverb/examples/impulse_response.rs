@@ -0,0 +1,100 @@
+//! Renders Freeverb's impulse response and writes it to a 32-bit float stereo WAV file.
+//!
+//! Usage: `impulse_response <output.wav> [room size] [dampening] [width] [sample rate]`
+
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter, Write},
+    process,
+};
+
+const MAX_SECONDS: usize = 60;
+const THRESHOLD: f32 = 1.0e-5; // -100 dB
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let Some(path) = args.first() else {
+        eprintln!(
+            "Usage: impulse_response <output.wav> [room size] [dampening] [width] [sample rate]"
+        );
+        process::exit(1);
+    };
+
+    let parse_arg = |index: usize, default: f64| match args.get(index) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid argument: '{arg}'");
+            process::exit(1);
+        }),
+        None => default,
+    };
+
+    let room_size = parse_arg(1, 0.5);
+    let dampening = parse_arg(2, 0.5);
+    let width = parse_arg(3, 1.0);
+    let sample_rate = parse_arg(4, 44100.0) as usize;
+
+    let ir = freeverb::render_impulse_response::<f64>(
+        sample_rate,
+        sample_rate * MAX_SECONDS,
+        THRESHOLD,
+        |freeverb| {
+            freeverb.set_room_size(room_size);
+            freeverb.set_dampening(dampening);
+            freeverb.set_width(width);
+        },
+    );
+
+    if let Err(error) = write_wav(path, &ir, sample_rate as u32) {
+        eprintln!("Failed to write '{path}': {error}");
+        process::exit(1);
+    }
+
+    println!(
+        "Wrote {} frames ({:.2}s) to '{path}'",
+        ir.len(),
+        ir.len() as f64 / sample_rate as f64
+    );
+}
+
+fn write_wav(path: &str, frames: &[(f64, f64)], sample_rate: u32) -> io::Result<()> {
+    const CHANNELS: u16 = 2;
+    const BYTES_PER_SAMPLE: u16 = 4;
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+
+    let block_align = CHANNELS * BYTES_PER_SAMPLE;
+    let data_size = frames.len() as u32 * block_align as u32;
+    // 'WAVE' + fmt chunk (8 + 18) + fact chunk (8 + 4) + data chunk header (8)
+    let riff_size = 4 + 26 + 12 + 8 + data_size;
+
+    let mut file = BufWriter::new(File::create(path)?);
+
+    file.write_all(b"RIFF")?;
+    file.write_all(&riff_size.to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+
+    // Non-PCM formats have an extended fmt chunk, and require a fact chunk
+    file.write_all(b"fmt ")?;
+    file.write_all(&18u32.to_le_bytes())?;
+    file.write_all(&WAVE_FORMAT_IEEE_FLOAT.to_le_bytes())?;
+    file.write_all(&CHANNELS.to_le_bytes())?;
+    file.write_all(&sample_rate.to_le_bytes())?;
+    file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    file.write_all(&block_align.to_le_bytes())?;
+    file.write_all(&(BYTES_PER_SAMPLE * 8).to_le_bytes())?;
+    file.write_all(&0u16.to_le_bytes())?;
+
+    file.write_all(b"fact")?;
+    file.write_all(&4u32.to_le_bytes())?;
+    file.write_all(&(frames.len() as u32).to_le_bytes())?;
+
+    file.write_all(b"data")?;
+    file.write_all(&data_size.to_le_bytes())?;
+    for (left, right) in frames {
+        file.write_all(&(*left as f32).to_le_bytes())?;
+        file.write_all(&(*right as f32).to_le_bytes())?;
+    }
+
+    file.flush()
+}
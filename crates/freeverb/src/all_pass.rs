@@ -11,6 +11,10 @@ impl<T: Float> AllPass<T> {
         }
     }
 
+    pub fn reset(&mut self) {
+        self.delay_line.reset();
+    }
+
     pub fn tick(&mut self, input: T) -> T {
         let delayed = self.delay_line.read();
         let output = -input + delayed;
@@ -19,6 +19,11 @@ impl<T: Float> Comb<T> {
         }
     }
 
+    pub fn reset(&mut self) {
+        self.delay_line.reset();
+        self.filter_state = T::from(0.0);
+    }
+
     pub fn set_dampening(&mut self, value: T) {
         self.dampening = value;
         self.dampening_inverse = T::from(1.0) - value;
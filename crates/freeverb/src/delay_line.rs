@@ -13,10 +13,27 @@ impl<T: Float> DelayLine<T> {
         }
     }
 
+    /// Clears the line's contents.
+    pub fn reset(&mut self) {
+        self.buffer.fill(T::from(0.0));
+        self.index = 0;
+    }
+
     pub fn read(&self) -> T {
         self.buffer[self.index]
     }
 
+    /// Reads the value that was written `delay` ticks ago.
+    ///
+    /// A delay of 1 returns the most recently written value, and the delay can't exceed the
+    /// line's length.
+    pub fn read_at(&self, delay: usize) -> T {
+        debug_assert!(delay > 0 && delay <= self.buffer.len());
+
+        let length = self.buffer.len();
+        self.buffer[(self.index + length - delay) % length]
+    }
+
     pub fn write_and_advance(&mut self, value: T) {
         self.buffer[self.index] = value;
 
@@ -50,4 +67,15 @@ mod tests {
     delay_line_test!(length_1, 1);
     delay_line_test!(length_3, 3);
     delay_line_test!(length_10, 10);
+
+    #[test]
+    fn read_at() {
+        let mut line = super::DelayLine::new(4);
+        for i in 1..=6 {
+            line.write_and_advance(i as f32);
+        }
+        assert_eq!(line.read_at(1), 6.0);
+        assert_eq!(line.read_at(2), 5.0);
+        assert_eq!(line.read_at(4), 3.0);
+    }
 }
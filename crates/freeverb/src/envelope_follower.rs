@@ -0,0 +1,99 @@
+/// A peak envelope follower with separate attack and release times.
+pub struct EnvelopeFollower {
+    sample_rate: f32,
+    attack_seconds: f32,
+    release_seconds: f32,
+    attack: f32,
+    release: f32,
+    level: f32,
+}
+
+impl EnvelopeFollower {
+    pub fn new(sample_rate: usize) -> Self {
+        Self {
+            sample_rate: sample_rate as f32,
+            attack_seconds: 0.0,
+            release_seconds: 0.0,
+            attack: 0.0,
+            release: 0.0,
+            level: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.level = 0.0;
+    }
+
+    /// Sets the time taken to rise towards a higher input level, in seconds.
+    pub fn set_attack(&mut self, seconds: f32) {
+        self.attack_seconds = seconds;
+        self.attack = self.coefficient(seconds);
+    }
+
+    /// Sets the time taken to fall towards a lower input level, in seconds.
+    pub fn set_release(&mut self, seconds: f32) {
+        self.release_seconds = seconds;
+        self.release = self.coefficient(seconds);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.sample_rate = sample_rate as f32;
+        self.set_attack(self.attack_seconds);
+        self.set_release(self.release_seconds);
+    }
+
+    pub fn tick(&mut self, input: f32) -> f32 {
+        let input = input.abs();
+        let coefficient = if input > self.level {
+            self.attack
+        } else {
+            self.release
+        };
+
+        self.level = input + (self.level - input) * coefficient;
+        self.level
+    }
+
+    fn coefficient(&self, seconds: f32) -> f32 {
+        let samples = seconds * self.sample_rate;
+        if samples > 0.0 {
+            (-1.0 / samples).exp()
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instant_attack_and_release() {
+        let mut follower = EnvelopeFollower::new(44100);
+        assert_eq!(follower.tick(-0.5), 0.5);
+        assert_eq!(follower.tick(0.25), 0.25);
+        assert_eq!(follower.tick(0.0), 0.0);
+    }
+
+    #[test]
+    fn attack_time() {
+        let mut follower = EnvelopeFollower::new(1000);
+        follower.set_attack(0.01);
+
+        // After the attack time the level should have risen to within a factor of e of the input
+        let level = (0..10).fold(0.0, |_, _| follower.tick(1.0));
+        assert!((level - (1.0 - (-1.0f32).exp())).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn release_time() {
+        let mut follower = EnvelopeFollower::new(1000);
+        follower.set_release(0.01);
+        follower.tick(1.0);
+
+        // After the release time the level should have fallen by a factor of e
+        let level = (0..10).fold(0.0, |_, _| follower.tick(0.0));
+        assert!((level - (-1.0f32).exp()).abs() < 1.0e-6);
+    }
+}
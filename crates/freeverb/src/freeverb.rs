@@ -1,4 +1,12 @@
-use crate::{all_pass::AllPass, comb::Comb, float::Float, tuning::*};
+use crate::{
+    all_pass::AllPass,
+    comb::Comb,
+    envelope_follower::EnvelopeFollower,
+    float::Float,
+    pitch_shifter::PitchShifter,
+    tail_envelope::{TailEnvelope, TailMode},
+    tuning::*,
+};
 
 /// A processor for the Freeverb reverb algorithm.
 ///
@@ -7,6 +15,10 @@ use crate::{all_pass::AllPass, comb::Comb, float::Float, tuning::*};
 pub struct Freeverb<T: Float = f64> {
     combs: [(Comb<T>, Comb<T>); 8],
     allpasses: [(AllPass<T>, AllPass<T>); 4],
+    shimmers: (PitchShifter<T>, PitchShifter<T>),
+    shimmer_input: (T, T),
+    tail_envelope: TailEnvelope,
+    ducking_follower: EnvelopeFollower,
     wet_gains: (T, T),
     wet: T,
     width: T,
@@ -14,7 +26,14 @@ pub struct Freeverb<T: Float = f64> {
     input_gain: T,
     dampening: T,
     room_size: T,
+    shimmer: T,
+    shimmer_gain: T,
+    ducking: f32,
     frozen: bool,
+    sample_rate: usize,
+    silence_length: usize,
+    silent_frames: usize,
+    idle_bypass: bool,
 }
 
 impl<T: Float> Freeverb<T> {
@@ -26,58 +45,15 @@ impl<T: Float> Freeverb<T> {
     /// including 48kHz.
     pub fn new(sr: usize) -> Self {
         let mut freeverb = Freeverb::<T> {
-            combs: [
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L1, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R1, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L2, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R2, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L3, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R3, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L4, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R4, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L5, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R5, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L6, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R6, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L7, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R7, sr)),
-                ),
-                (
-                    Comb::new(adjust_length(COMB_TUNING_L8, sr)),
-                    Comb::new(adjust_length(COMB_TUNING_R8, sr)),
-                ),
-            ],
-            allpasses: [
-                (
-                    AllPass::new(adjust_length(ALLPASS_TUNING_L1, sr)),
-                    AllPass::new(adjust_length(ALLPASS_TUNING_R1, sr)),
-                ),
-                (
-                    AllPass::new(adjust_length(ALLPASS_TUNING_L2, sr)),
-                    AllPass::new(adjust_length(ALLPASS_TUNING_R2, sr)),
-                ),
-                (
-                    AllPass::new(adjust_length(ALLPASS_TUNING_L3, sr)),
-                    AllPass::new(adjust_length(ALLPASS_TUNING_R3, sr)),
-                ),
-                (
-                    AllPass::new(adjust_length(ALLPASS_TUNING_L4, sr)),
-                    AllPass::new(adjust_length(ALLPASS_TUNING_R4, sr)),
-                ),
-            ],
+            combs: make_combs(sr),
+            allpasses: make_allpasses(sr),
+            shimmers: (
+                PitchShifter::new(adjust_length(SHIMMER_WINDOW, sr)),
+                PitchShifter::new(adjust_length(SHIMMER_WINDOW, sr)),
+            ),
+            shimmer_input: (T::default(), T::default()),
+            tail_envelope: TailEnvelope::new(sr),
+            ducking_follower: EnvelopeFollower::new(sr),
             wet_gains: (T::default(), T::default()),
             wet: T::default(),
             dry: T::default(),
@@ -85,14 +61,31 @@ impl<T: Float> Freeverb<T> {
             width: T::default(),
             dampening: T::default(),
             room_size: T::default(),
+            shimmer: T::default(),
+            shimmer_gain: T::default(),
+            ducking: 0.0,
             frozen: false,
+            sample_rate: sr,
+            silence_length: sr / 10,
+            silent_frames: 0,
+            idle_bypass: false,
         };
 
         freeverb.set_wet(T::from(1.0) / T::from(SCALE_WET));
         freeverb.set_width(T::from(1.0));
         freeverb.set_dampening(T::from(0.5));
         freeverb.set_room_size(T::from(0.5));
+        freeverb.set_shimmer(T::from(0.0));
+        freeverb.set_shimmer_pitch(T::from(12.0));
+        freeverb.set_gate_threshold(T::from(0.01));
+        freeverb.set_gate_hold(T::from(0.25));
+        freeverb.set_gate_release(T::from(0.05));
+        freeverb.set_reverse_window(T::from(0.5));
+        freeverb.set_ducking(T::from(0.0));
+        freeverb.set_ducking_attack(T::from(0.01));
+        freeverb.set_ducking_release(T::from(0.25));
         freeverb.set_frozen(false);
+        freeverb.reset();
 
         freeverb
     }
@@ -103,13 +96,37 @@ impl<T: Float> Freeverb<T> {
     ///
     /// To process a buffer of frames this function should be called repeatedly.
     pub fn tick(&mut self, input: (T, T)) -> (T, T) {
+        self.tick_with_sidechain(input, input)
+    }
+
+    /// Processes a single pair of values, with a separate sidechain input for the ducker.
+    ///
+    /// The sidechain's level controls how much the wet signal gets ducked, see
+    /// [set_ducking](Self::set_ducking). [tick](Self::tick) uses the input as the sidechain.
+    pub fn tick_with_sidechain(&mut self, input: (T, T), sidechain: (T, T)) -> (T, T) {
+        let input_level = input.0.to_f32().abs().max(input.1.to_f32().abs());
+        let input_is_silent = input_level < SILENCE_THRESHOLD;
+
+        if self.idle_bypass && input_is_silent && self.is_silent() {
+            return (input.0 * self.dry, input.1 * self.dry);
+        }
+
         let input_mixed = (input.0 + input.1) * T::from(FIXED_GAIN) * self.input_gain;
 
+        let comb_input = if self.shimmer_gain == T::from(0.0) {
+            (input_mixed, input_mixed)
+        } else {
+            (
+                input_mixed + self.shimmers.0.tick(self.shimmer_input.0) * self.shimmer_gain,
+                input_mixed + self.shimmers.1.tick(self.shimmer_input.1) * self.shimmer_gain,
+            )
+        };
+
         let mut out = (T::from(0.0), T::from(0.0));
 
         for combs in self.combs.iter_mut() {
-            out.0 += combs.0.tick(input_mixed);
-            out.1 += combs.1.tick(input_mixed);
+            out.0 += combs.0.tick(comb_input.0);
+            out.1 += combs.1.tick(comb_input.1);
         }
 
         for allpasses in self.allpasses.iter_mut() {
@@ -117,12 +134,125 @@ impl<T: Float> Freeverb<T> {
             out.1 = allpasses.1.tick(out.1);
         }
 
+        self.shimmer_input = out;
+
+        // The reverb's output is checked before the wet gains are applied,
+        // so that a tail that's currently gated or ducked isn't considered to be silent.
+        if input_is_silent
+            && out.0.to_f32().abs() < SILENCE_THRESHOLD
+            && out.1.to_f32().abs() < SILENCE_THRESHOLD
+        {
+            self.silent_frames += 1;
+            if self.idle_bypass && self.is_silent() {
+                self.reset();
+            }
+        } else {
+            self.silent_frames = 0;
+        }
+
+        let tail_gain = self.tail_envelope.tick(input_level);
+
+        let ducking_level = self
+            .ducking_follower
+            .tick(sidechain.0.to_f32().abs().max(sidechain.1.to_f32().abs()));
+        let ducking_gain = 1.0 - self.ducking * ducking_level.min(1.0);
+
+        let wet_gain = T::from(tail_gain * ducking_gain);
+
         (
-            out.0 * self.wet_gains.0 + out.1 * self.wet_gains.1 + input.0 * self.dry,
-            out.1 * self.wet_gains.0 + out.0 * self.wet_gains.1 + input.1 * self.dry,
+            (out.0 * self.wet_gains.0 + out.1 * self.wet_gains.1) * wet_gain + input.0 * self.dry,
+            (out.1 * self.wet_gains.0 + out.0 * self.wet_gains.1) * wet_gain + input.1 * self.dry,
         )
     }
 
+    /// Changes the processor's sample rate.
+    ///
+    /// The delay lines are resized for the new sample rate, which clears the reverb's tail.
+    /// Parameter values are left unchanged.
+    pub fn set_sample_rate(&mut self, sr: usize) {
+        self.combs = make_combs(sr);
+        self.allpasses = make_allpasses(sr);
+
+        self.shimmers
+            .0
+            .set_window_length(adjust_length(SHIMMER_WINDOW, sr));
+        self.shimmers
+            .1
+            .set_window_length(adjust_length(SHIMMER_WINDOW, sr));
+        self.tail_envelope.set_sample_rate(sr);
+        self.ducking_follower.set_sample_rate(sr);
+
+        self.sample_rate = sr;
+        self.silence_length = sr / 10;
+
+        self.update_combs();
+        self.reset();
+    }
+
+    /// Clears the processor's internal state, silencing the reverb's tail.
+    ///
+    /// Parameter values are left unchanged.
+    pub fn reset(&mut self) {
+        for combs in self.combs.iter_mut() {
+            combs.0.reset();
+            combs.1.reset();
+        }
+
+        for allpasses in self.allpasses.iter_mut() {
+            allpasses.0.reset();
+            allpasses.1.reset();
+        }
+
+        self.shimmers.0.reset();
+        self.shimmers.1.reset();
+        self.shimmer_input = (T::from(0.0), T::from(0.0));
+        self.tail_envelope.reset();
+        self.ducking_follower.reset();
+        self.silent_frames = self.silence_length;
+    }
+
+    /// Returns true if the reverb's tail has decayed to silence.
+    ///
+    /// The tail is considered to be silent once the input and the reverb's output have stayed
+    /// below -120dB for 100ms.
+    pub fn is_silent(&self) -> bool {
+        self.silent_frames >= self.silence_length
+    }
+
+    /// Returns an estimate of how many frames it takes for the reverb's tail to decay to silence.
+    ///
+    /// The estimate is based on the room size and the length of the longest comb filter, and
+    /// doesn't take the shimmer feedback into account. `None` is returned while the reverb is
+    /// frozen, given that the tail will never decay.
+    pub fn tail_length_samples(&self) -> Option<usize> {
+        if self.frozen {
+            return None;
+        }
+
+        // The tail's level is reduced by the feedback amount with each pass through a comb
+        let passes = (SILENCE_THRESHOLD.ln() / self.room_size.to_f32().ln()).ceil() as usize;
+
+        let allpasses_length = [
+            ALLPASS_TUNING_R1,
+            ALLPASS_TUNING_R2,
+            ALLPASS_TUNING_R3,
+            ALLPASS_TUNING_R4,
+        ]
+        .iter()
+        .map(|&length| adjust_length(length, self.sample_rate))
+        .sum::<usize>();
+
+        Some(passes * adjust_length(COMB_TUNING_R8, self.sample_rate) + allpasses_length)
+    }
+
+    /// Enables or disables skipping of the reverb's processing while it's idle.
+    ///
+    /// When enabled, once the tail has decayed to silence the processor's state is reset, and
+    /// processing is skipped until non-silent input arrives.
+    pub fn set_idle_bypass(&mut self, enabled: bool) {
+        self.idle_bypass = enabled;
+    }
+
     /// Sets the processors dampening value.
     ///
     /// The value should be in the range `0..=1`.
@@ -137,6 +267,7 @@ impl<T: Float> Freeverb<T> {
     pub fn set_freeze(&mut self, frozen: bool) {
         self.frozen = frozen;
         self.update_combs();
+        self.update_shimmer_gain();
     }
 
     /// Sets the amount of the 'dry' signal to include in the processor's output.
@@ -174,6 +305,72 @@ impl<T: Float> Freeverb<T> {
         self.update_combs();
     }
 
+    /// Sets the amount of pitch-shifted reverb output that gets fed back into the reverb.
+    ///
+    /// Feeding the shifted output back in produces a 'shimmer' effect, with the tail rising in
+    /// pitch as it decays. Shimmer is disabled while the reverb is frozen.
+    ///
+    /// The value should be in the range `0..=1`.
+    pub fn set_shimmer(&mut self, value: T) {
+        self.shimmer = value * T::from(SCALE_SHIMMER) * T::from(FIXED_GAIN);
+        self.update_shimmer_gain();
+    }
+
+    /// Sets the pitch shift applied to the shimmer feedback, in semitones.
+    ///
+    /// Typical values are 12 for an octave, or 7 for a fifth.
+    pub fn set_shimmer_pitch(&mut self, semitones: T) {
+        let ratio = (semitones.to_f32() / 12.0).exp2();
+        self.shimmers.0.set_ratio(ratio);
+        self.shimmers.1.set_ratio(ratio);
+    }
+
+    /// Sets the shape of the reverb's tail, see [TailMode].
+    pub fn set_tail_mode(&mut self, mode: TailMode) {
+        self.tail_envelope.set_mode(mode);
+    }
+
+    /// Sets the input level that opens the gate in the gated and reverse tail modes.
+    ///
+    /// The value is a linear amplitude and should be in the range `0..=1`.
+    pub fn set_gate_threshold(&mut self, value: T) {
+        self.tail_envelope.set_threshold(value.to_f32());
+    }
+
+    /// Sets how long the gate stays open after the input falls below the threshold, in seconds.
+    pub fn set_gate_hold(&mut self, seconds: T) {
+        self.tail_envelope.set_hold(seconds.to_f32());
+    }
+
+    /// Sets how long the gate takes to close after the hold time has passed, in seconds.
+    pub fn set_gate_release(&mut self, seconds: T) {
+        self.tail_envelope.set_release(seconds.to_f32());
+    }
+
+    /// Sets the length of the swell that follows each onset in the reverse tail mode, in seconds.
+    pub fn set_reverse_window(&mut self, seconds: T) {
+        self.tail_envelope.set_reverse_window(seconds.to_f32());
+    }
+
+    /// Sets how much the wet signal gets ducked by the sidechain's level.
+    ///
+    /// With a value of 1 the wet signal is fully ducked when the sidechain reaches full scale.
+    ///
+    /// The value should be in the range `0..=1`.
+    pub fn set_ducking(&mut self, value: T) {
+        self.ducking = value.to_f32();
+    }
+
+    /// Sets how quickly the ducking responds to a rise in the sidechain's level, in seconds.
+    pub fn set_ducking_attack(&mut self, seconds: T) {
+        self.ducking_follower.set_attack(seconds.to_f32());
+    }
+
+    /// Sets how quickly the ducking recovers after a fall in the sidechain's level, in seconds.
+    pub fn set_ducking_release(&mut self, seconds: T) {
+        self.ducking_follower.set_release(seconds.to_f32());
+    }
+
     fn update_wet_gains(&mut self) {
         self.wet_gains = (
             self.wet * (self.width / T::from(2.0) + T::from(0.5)),
@@ -185,6 +382,15 @@ impl<T: Float> Freeverb<T> {
         self.frozen = frozen;
         self.input_gain = if frozen { T::from(0.0) } else { T::from(1.0) };
         self.update_combs();
+        self.update_shimmer_gain();
+    }
+
+    fn update_shimmer_gain(&mut self) {
+        self.shimmer_gain = if self.frozen {
+            T::from(0.0)
+        } else {
+            self.shimmer
+        };
     }
 
     fn update_combs(&mut self) {
@@ -204,6 +410,66 @@ impl<T: Float> Freeverb<T> {
     }
 }
 
+// Makes the comb filters for a sample rate, with the tuning adjusted to match
+fn make_combs<T: Float>(sr: usize) -> [(Comb<T>, Comb<T>); 8] {
+    [
+        (
+            Comb::new(adjust_length(COMB_TUNING_L1, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R1, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L2, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R2, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L3, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R3, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L4, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R4, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L5, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R5, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L6, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R6, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L7, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R7, sr)),
+        ),
+        (
+            Comb::new(adjust_length(COMB_TUNING_L8, sr)),
+            Comb::new(adjust_length(COMB_TUNING_R8, sr)),
+        ),
+    ]
+}
+
+// Makes the allpass filters for a sample rate, with the tuning adjusted to match
+fn make_allpasses<T: Float>(sr: usize) -> [(AllPass<T>, AllPass<T>); 4] {
+    [
+        (
+            AllPass::new(adjust_length(ALLPASS_TUNING_L1, sr)),
+            AllPass::new(adjust_length(ALLPASS_TUNING_R1, sr)),
+        ),
+        (
+            AllPass::new(adjust_length(ALLPASS_TUNING_L2, sr)),
+            AllPass::new(adjust_length(ALLPASS_TUNING_R2, sr)),
+        ),
+        (
+            AllPass::new(adjust_length(ALLPASS_TUNING_L3, sr)),
+            AllPass::new(adjust_length(ALLPASS_TUNING_R3, sr)),
+        ),
+        (
+            AllPass::new(adjust_length(ALLPASS_TUNING_L4, sr)),
+            AllPass::new(adjust_length(ALLPASS_TUNING_R4, sr)),
+        ),
+    ]
+}
+
 fn adjust_length(length: usize, sr: usize) -> usize {
     (length as f64 * sr as f64 / 44100.0) as usize
 }
@@ -247,6 +513,136 @@ mod tests {
         check_almost_equal(freeverb.tick(silence), (-0.0000806401, -0.0060492903));
     }
 
+    #[test]
+    fn shimmer_tail_decays() {
+        let mut freeverb = Freeverb::<f64>::new(44100);
+        freeverb.set_room_size(1.0);
+        freeverb.set_dampening(0.0);
+        freeverb.set_shimmer(1.0);
+
+        let mut peak_per_second = (0..10).map(|second| {
+            (0..44100).fold(0.0f64, |peak, i| {
+                let input = if second == 0 && i < 100 { 1.0 } else { 0.0 };
+                let output = freeverb.tick((input, input));
+                peak.max(output.0.abs()).max(output.1.abs())
+            })
+        });
+
+        let first = peak_per_second.next().unwrap();
+        let last = peak_per_second.last().unwrap();
+        assert!(last < first * 0.01, "first: {first}, last: {last}");
+    }
+
+    #[test]
+    fn gated_tail_is_cut_off() {
+        let mut freeverb = Freeverb::<f32>::new(44100);
+        freeverb.set_tail_mode(TailMode::Gated);
+        freeverb.set_gate_hold(0.1);
+        freeverb.set_gate_release(0.1);
+
+        let peak = |freeverb: &mut Freeverb<f32>, input: f32, frames: usize| {
+            (0..frames).fold(0.0f32, |peak, _| {
+                let output = freeverb.tick((input, input));
+                peak.max(output.0.abs()).max(output.1.abs())
+            })
+        };
+
+        assert!(peak(&mut freeverb, 0.5, 4410) > 0.01);
+        // The detector release, the hold time and the release time have all passed
+        peak(&mut freeverb, 0.0, 20000);
+        assert_eq!(peak(&mut freeverb, 0.0, 4410), 0.0);
+    }
+
+    #[test]
+    fn sidechain_ducks_the_wet_signal() {
+        let mut ducked = Freeverb::<f32>::new(44100);
+        ducked.set_ducking(1.0);
+        ducked.set_ducking_attack(0.0);
+        let mut unducked = Freeverb::<f32>::new(44100);
+
+        for i in 0..4410 {
+            let input = if i < 100 { 0.5 } else { 0.0 };
+            let sidechain = if i < 2000 { 0.0 } else { 1.0 };
+
+            let ducked = ducked.tick_with_sidechain((input, input), (sidechain, sidechain));
+            let unducked = unducked.tick((input, input));
+
+            if i < 2000 {
+                assert_eq!(ducked, unducked);
+            } else {
+                assert_eq!(ducked, (0.0, 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn set_sample_rate() {
+        let configure = |freeverb: &mut Freeverb<f32>| {
+            freeverb.set_room_size(0.8);
+            freeverb.set_shimmer(0.5);
+            freeverb.set_shimmer_pitch(7.0);
+            freeverb.set_ducking(0.5);
+            freeverb.set_ducking_attack(0.1);
+            freeverb.set_tail_mode(TailMode::Gated);
+            freeverb.set_gate_hold(0.1);
+        };
+
+        let mut resampled = Freeverb::<f32>::new(44100);
+        configure(&mut resampled);
+        resampled.tick((1.0, 1.0));
+        resampled.set_sample_rate(96000);
+
+        let mut reference = Freeverb::<f32>::new(96000);
+        configure(&mut reference);
+
+        for i in 0..20000 {
+            let input = if i < 100 { (0.5, -0.5) } else { (0.0, 0.0) };
+            assert_eq!(resampled.tick(input), reference.tick(input));
+        }
+    }
+
+    #[test]
+    fn silence_detection() {
+        let mut freeverb = Freeverb::<f32>::new(44100);
+        assert!(freeverb.is_silent());
+
+        freeverb.tick((1.0, 1.0));
+        assert!(!freeverb.is_silent());
+
+        let tail_length = freeverb.tail_length_samples().unwrap();
+        let frames_until_silent = (0..tail_length)
+            .position(|_| {
+                freeverb.tick((0.0, 0.0));
+                freeverb.is_silent()
+            })
+            .expect("The tail should be silent within the estimated tail length");
+        assert!(frames_until_silent > 44100);
+
+        freeverb.set_freeze(true);
+        assert_eq!(freeverb.tail_length_samples(), None);
+    }
+
+    #[test]
+    fn idle_bypass() {
+        let mut freeverb = Freeverb::<f32>::new(44100);
+        freeverb.set_idle_bypass(true);
+        freeverb.set_dry(0.5);
+
+        freeverb.tick((1.0, 1.0));
+        while !freeverb.is_silent() {
+            freeverb.tick((0.0, 0.0));
+        }
+        assert_eq!(freeverb.tick((0.0, 0.0)), (0.0, 0.0));
+
+        // Processing resumes with the next non-silent input, matching a freshly reset processor
+        let mut reference = Freeverb::<f32>::new(44100);
+        reference.set_dry(0.5);
+        for i in 0..3000 {
+            let input = if i == 0 { (1.0, 1.0) } else { (0.0, 0.0) };
+            assert_eq!(freeverb.tick(input), reference.tick(input));
+        }
+    }
+
     #[track_caller]
     fn check_almost_equal(output: (f32, f32), expected: (f32, f32)) {
         let difference = ((output.0 - expected.0).abs(), (output.1 - expected.1).abs());
@@ -0,0 +1,73 @@
+use crate::{float::Float, freeverb::Freeverb};
+
+/// Renders the stereo impulse response of a [Freeverb] processor.
+///
+/// A processor is created with the given sample rate, and is then configured by `configure` before
+/// a unit impulse is passed into both input channels.
+///
+/// Rendering stops when the output has stayed below `threshold` for 100ms, or when `max_length`
+/// frames have been rendered. Trailing frames that are below the threshold are then removed.
+pub fn render_impulse_response<T: Float>(
+    sample_rate: usize,
+    max_length: usize,
+    threshold: f32,
+    configure: impl FnOnce(&mut Freeverb<T>),
+) -> Vec<(T, T)> {
+    let mut freeverb = Freeverb::new(sample_rate);
+    configure(&mut freeverb);
+
+    // The silence needs to last longer than the reverb's longest delay before the tail can be
+    // considered to have ended.
+    let silence_length = sample_rate / 10;
+
+    let mut result = Vec::new();
+    let mut length = 0;
+
+    for i in 0..max_length {
+        let input = if i == 0 { T::from(1.0) } else { T::from(0.0) };
+        let output = freeverb.tick((input, input));
+        result.push(output);
+
+        if output.0.to_f32().abs() >= threshold || output.1.to_f32().abs() >= threshold {
+            length = i + 1;
+        } else if i - length >= silence_length {
+            break;
+        }
+    }
+
+    result.truncate(length);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_is_trimmed() {
+        let threshold = 1.0e-4;
+        let ir = render_impulse_response::<f32>(44100, 44100 * 30, threshold, |_| {});
+
+        assert!(ir.len() > 4410);
+        assert!(ir.len() < 44100 * 30);
+
+        let last = ir.last().unwrap();
+        assert!(last.0.abs() >= threshold || last.1.abs() >= threshold);
+    }
+
+    #[test]
+    fn max_length() {
+        let ir = render_impulse_response::<f32>(44100, 1000, 1.0e-4, |freeverb| {
+            freeverb.set_room_size(1.0)
+        });
+        assert!(ir.len() <= 1000);
+    }
+
+    #[test]
+    fn frozen_reverb_reaches_max_length() {
+        let ir = render_impulse_response::<f32>(44100, 44100, 1.0e-4, |freeverb| {
+            freeverb.set_freeze(true)
+        });
+        assert_eq!(ir.len(), 44100);
+    }
+}
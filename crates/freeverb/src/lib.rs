@@ -10,8 +10,15 @@
 mod all_pass;
 mod comb;
 mod delay_line;
+mod envelope_follower;
 mod float;
 mod freeverb;
+mod impulse_response;
+mod pitch_shifter;
+mod tail_envelope;
 mod tuning;
 
-pub use self::{float::Float, freeverb::Freeverb};
+pub use self::{
+    float::Float, freeverb::Freeverb, impulse_response::render_impulse_response,
+    tail_envelope::TailMode,
+};
@@ -0,0 +1,129 @@
+use crate::{delay_line::DelayLine, float::Float};
+
+/// A delay-based pitch shifter.
+///
+/// Two taps sweep through a short delay line at a rate determined by the pitch ratio, half a
+/// window apart. Each tap's gain follows a triangular window, so a tap jumping back to the start
+/// of the window is hidden while the other tap is at full gain.
+///
+/// DC is removed from the output, which keeps the reverb stable when the shifted signal is fed back
+/// into the comb filters.
+pub struct PitchShifter<T> {
+    delay_line: DelayLine<T>,
+    window_length: f32,
+    ratio: f32,
+    phase: f32,
+    phase_increment: f32,
+    dc_blocker: (T, T),
+}
+
+impl<T: Float> PitchShifter<T> {
+    pub fn new(window_length: usize) -> Self {
+        Self {
+            // The interpolated taps read up to one sample beyond the window
+            delay_line: DelayLine::new(window_length + 1),
+            window_length: window_length as f32,
+            ratio: 1.0,
+            phase: 0.0,
+            phase_increment: 0.0,
+            dc_blocker: (T::default(), T::default()),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.delay_line.reset();
+        self.phase = 0.0;
+        self.dc_blocker = (T::from(0.0), T::from(0.0));
+    }
+
+    /// Sets the ratio between the output and input frequencies, e.g. 2.0 for an octave up.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio;
+        self.phase_increment = (1.0 - ratio) / self.window_length;
+    }
+
+    /// Resizes the shifter's window, resetting its state.
+    pub fn set_window_length(&mut self, window_length: usize) {
+        self.delay_line = DelayLine::new(window_length + 1);
+        self.window_length = window_length as f32;
+        self.reset();
+        self.set_ratio(self.ratio);
+    }
+
+    pub fn tick(&mut self, input: T) -> T {
+        self.delay_line.write_and_advance(input);
+
+        let shifted = self.tap(self.phase) + self.tap(wrap(self.phase + 0.5));
+
+        self.phase = wrap(self.phase + self.phase_increment);
+
+        let output = shifted - self.dc_blocker.0 + self.dc_blocker.1 * T::from(0.995);
+        self.dc_blocker = (shifted, output);
+        output
+    }
+
+    fn tap(&self, phase: f32) -> T {
+        let delay = 1.0 + phase * self.window_length;
+        let index = delay as usize;
+        let fraction = T::from(delay - index as f32);
+
+        let a = self.delay_line.read_at(index);
+        let b = self.delay_line.read_at(index + 1);
+
+        let gain = 1.0 - (2.0 * phase - 1.0).abs();
+
+        (a + (b - a) * fraction) * T::from(gain)
+    }
+}
+
+fn wrap(phase: f32) -> f32 {
+    phase - phase.floor()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unity_ratio_is_a_fixed_delay() {
+        let mut shifter = PitchShifter::<f32>::new(8);
+        shifter.set_ratio(1.0);
+
+        // The taps sit at delays of 1 and 5 with gains of 0 and 1
+        let output: Vec<f32> = (0..8)
+            .map(|i| shifter.tick(if i == 0 { 1.0 } else { 0.0 }))
+            .collect();
+        assert_eq!(output[..5], [0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert!(output[5..].iter().all(|x| x.abs() < 0.01));
+    }
+
+    #[test]
+    fn octave_up_doubles_the_frequency() {
+        let sample_rate = 44100.0;
+        let frequency = 441.0;
+        let mut shifter = PitchShifter::<f64>::new(2048);
+        shifter.set_ratio(2.0);
+
+        let output: Vec<f64> = (0..8192)
+            .map(|i| {
+                let phase = i as f64 * frequency / sample_rate;
+                shifter.tick((phase * std::f64::consts::TAU).sin())
+            })
+            .collect();
+
+        // Compare the output's energy at the input frequency and at the octave above
+        let energy_at = |frequency: f64| {
+            let (re, im) =
+                output[4096..]
+                    .iter()
+                    .enumerate()
+                    .fold((0.0, 0.0), |(re, im), (i, sample)| {
+                        let phase = i as f64 * frequency / sample_rate * std::f64::consts::TAU;
+                        (re + sample * phase.cos(), im + sample * phase.sin())
+                    });
+            re * re + im * im
+        };
+
+        assert!(energy_at(frequency * 2.0) > energy_at(frequency) * 100.0);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::envelope_follower::EnvelopeFollower;
+
+// The follower's release smooths over the gaps between peaks in the input
+const DETECTOR_RELEASE_SECONDS: f32 = 0.01;
+
+/// The shape applied to the reverb's tail.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TailMode {
+    /// The natural exponential decay of the reverb.
+    #[default]
+    Natural,
+    /// The tail is cut off after the input falls below the gate threshold.
+    ///
+    /// The tail is held for the gate's hold time, and then fades out over the release time.
+    Gated,
+    /// The tail swells up after each onset in the input, and is then cut off.
+    ///
+    /// An onset is detected when the input rises above the gate threshold,
+    /// and the swell lasts for the reverse window's length.
+    Reverse,
+}
+
+/// Produces a gain for the reverb's wet signal, following the selected [TailMode].
+pub struct TailEnvelope {
+    mode: TailMode,
+    sample_rate: f32,
+    detector: EnvelopeFollower,
+    threshold: f32,
+    hold_seconds: f32,
+    release_seconds: f32,
+    reverse_window_seconds: f32,
+    hold_samples: usize,
+    release_samples: usize,
+    reverse_window_samples: usize,
+    above_threshold: bool,
+    counter: usize,
+    gain: f32,
+}
+
+impl TailEnvelope {
+    pub fn new(sample_rate: usize) -> Self {
+        let mut detector = EnvelopeFollower::new(sample_rate);
+        detector.set_release(DETECTOR_RELEASE_SECONDS);
+
+        Self {
+            mode: TailMode::default(),
+            sample_rate: sample_rate as f32,
+            detector,
+            threshold: 0.0,
+            hold_seconds: 0.0,
+            release_seconds: 0.0,
+            reverse_window_seconds: 0.0,
+            hold_samples: 0,
+            release_samples: 0,
+            reverse_window_samples: 0,
+            above_threshold: false,
+            counter: 0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: TailMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.reset();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.above_threshold = false;
+        // Start with the gate closed and without an active swell
+        self.counter = usize::MAX;
+        self.gain = match self.mode {
+            TailMode::Natural => 1.0,
+            TailMode::Gated | TailMode::Reverse => 0.0,
+        };
+    }
+
+    pub fn set_threshold(&mut self, value: f32) {
+        self.threshold = value;
+    }
+
+    pub fn set_hold(&mut self, seconds: f32) {
+        self.hold_seconds = seconds;
+        self.hold_samples = self.seconds_to_samples(seconds);
+    }
+
+    pub fn set_release(&mut self, seconds: f32) {
+        self.release_seconds = seconds;
+        self.release_samples = self.seconds_to_samples(seconds);
+    }
+
+    pub fn set_reverse_window(&mut self, seconds: f32) {
+        self.reverse_window_seconds = seconds;
+        self.reverse_window_samples = self.seconds_to_samples(seconds).max(1);
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.sample_rate = sample_rate as f32;
+        self.detector.set_sample_rate(sample_rate);
+        self.set_hold(self.hold_seconds);
+        self.set_release(self.release_seconds);
+        self.set_reverse_window(self.reverse_window_seconds);
+    }
+
+    /// Processes the input level for a single frame, returning the gain to apply to the wet signal.
+    pub fn tick(&mut self, input: f32) -> f32 {
+        match self.mode {
+            TailMode::Natural => {}
+            TailMode::Gated => {
+                if self.detector.tick(input) > self.threshold {
+                    self.counter = 0;
+                    self.gain = 1.0;
+                } else {
+                    self.counter = self.counter.saturating_add(1);
+                    if self.counter > self.hold_samples {
+                        self.gain = if self.release_samples > 0 {
+                            (self.gain - 1.0 / self.release_samples as f32).max(0.0)
+                        } else {
+                            0.0
+                        };
+                    }
+                }
+            }
+            TailMode::Reverse => {
+                let above_threshold = self.detector.tick(input) > self.threshold;
+                if above_threshold && !self.above_threshold {
+                    self.counter = 0;
+                }
+                self.above_threshold = above_threshold;
+
+                if self.counter < self.reverse_window_samples {
+                    let position = self.counter as f32 / self.reverse_window_samples as f32;
+                    self.gain = position * position;
+                    self.counter += 1;
+                } else {
+                    self.gain = 0.0;
+                }
+            }
+        }
+
+        self.gain
+    }
+
+    fn seconds_to_samples(&self, seconds: f32) -> usize {
+        (seconds.max(0.0) * self.sample_rate) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_envelope(mode: TailMode) -> TailEnvelope {
+        let mut envelope = TailEnvelope::new(1000);
+        envelope.set_threshold(0.1);
+        envelope.set_hold(0.005);
+        envelope.set_release(0.004);
+        envelope.set_reverse_window(0.004);
+        envelope.set_mode(mode);
+        envelope
+    }
+
+    #[test]
+    fn natural() {
+        let mut envelope = make_envelope(TailMode::Natural);
+        assert_eq!(envelope.tick(0.0), 1.0);
+        assert_eq!(envelope.tick(1.0), 1.0);
+        assert_eq!(envelope.tick(0.0), 1.0);
+    }
+
+    #[test]
+    fn gated() {
+        let mut envelope = make_envelope(TailMode::Gated);
+        assert_eq!(envelope.tick(0.0), 0.0);
+        assert_eq!(envelope.tick(1.0), 1.0);
+
+        // The detector's release keeps the gate open for a few samples,
+        // followed by the hold time and then the release ramp.
+        let gains: Vec<f32> = (0..40).map(|_| envelope.tick(0.0)).collect();
+        let open = gains.iter().take_while(|&&gain| gain == 1.0).count();
+        assert!(open > 5);
+        assert_eq!(gains[open..open + 4], [0.75, 0.5, 0.25, 0.0]);
+        assert_eq!(*gains.last().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn reverse() {
+        let mut envelope = make_envelope(TailMode::Reverse);
+        assert_eq!(envelope.tick(0.0), 0.0);
+
+        let gains: Vec<f32> = (0..6).map(|_| envelope.tick(1.0)).collect();
+        assert_eq!(gains, [0.0, 0.0625, 0.25, 0.5625, 0.0, 0.0]);
+    }
+}
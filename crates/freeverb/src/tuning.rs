@@ -6,6 +6,11 @@ pub const SCALE_DAMPENING: f32 = 0.4;
 pub const SCALE_ROOM: f32 = 0.28;
 pub const OFFSET_ROOM: f32 = 0.7;
 
+pub const SILENCE_THRESHOLD: f32 = 1.0e-6; // -120 dB
+
+pub const SCALE_SHIMMER: f32 = 0.5;
+pub const SHIMMER_WINDOW: usize = 2048;
+
 pub const STEREO_SPREAD: usize = 23;
 
 pub const COMB_TUNING_L1: usize = 1116;
@@ -1,16 +1,22 @@
 #[macro_use]
 extern crate num_derive;
 
+mod presets;
+
 use {
     audio_module::{
-        AudioModule, AudioProcessor, BoolParameter, Command, CommandHandler, FloatParameter,
-        Parameter, ParameterProvider, percent_string_converter,
+        AudioModule, AudioProcessor, BoolParameter, ChoiceParameter, Command, CommandError,
+        CommandHandler, FloatParameter, IntParameter, Notification, NotificationSender, Parameter,
+        ParameterProvider, ParameterStore, Preset, PresetProvider, db_to_gain,
+        decibel_string_converter, decibel_value_converter, percent_string_converter,
+        skew_value_converter, time_string_converter,
     },
-    freeverb::{Float, Freeverb},
+    freeverb::{Float, Freeverb, TailMode},
     num_traits::FromPrimitive,
+    std::sync::Arc,
 };
 
-#[derive(FromPrimitive)]
+#[derive(Clone, Copy, FromPrimitive)]
 pub enum Parameters {
     Dampening,
     Width,
@@ -18,61 +24,329 @@ pub enum Parameters {
     Freeze,
     Dry,
     Wet,
+    Shimmer,
+    ShimmerPitch,
+    TailMode,
+    GateThreshold,
+    GateHold,
+    GateRelease,
+    ReverseWindow,
+    Ducking,
+    DuckingAttack,
+    DuckingRelease,
+}
+
+// The maximum number of timestamped parameter changes that can be queued for a single block
+const MAX_QUEUED_EVENTS: usize = 1024;
+
+// The number of meter notifications that are sent per second
+const METER_RATE: usize = 30;
+
+#[derive(Clone, Copy)]
+struct ParameterEvent {
+    parameter: Parameters,
+    value: f32,
+    frame_offset: usize,
 }
 
 pub struct FreeverbProcessor<T: Float = f64> {
     freeverb: Freeverb<T>,
+    events: Vec<ParameterEvent>,
+    parameter_store: Option<Arc<ParameterStore>>,
+    notification_sender: Option<NotificationSender>,
+    meter: Meter,
+    meter_interval: usize,
+    // Needed to separate the wet signal from the output for metering
+    dry: f32,
+    tail_active: bool,
+}
+
+// Levels that are accumulated between meter notifications
+#[derive(Default)]
+struct Meter {
+    input_peak: f32,
+    output_peak: f32,
+    wet_sum_of_squares: f32,
+    frames: usize,
 }
 
 impl FreeverbProcessor {
     fn new(sample_rate: usize) -> Self {
+        let mut freeverb = Freeverb::new(sample_rate);
+        freeverb.set_idle_bypass(true);
+
         Self {
-            freeverb: Freeverb::new(sample_rate),
+            freeverb,
+            events: Vec::with_capacity(MAX_QUEUED_EVENTS),
+            parameter_store: None,
+            notification_sender: None,
+            meter: Meter::default(),
+            meter_interval: meter_interval(sample_rate),
+            dry: 0.0,
+            tail_active: false,
+        }
+    }
+}
+
+impl<T: Float> FreeverbProcessor<T> {
+    fn set_parameter(&mut self, parameter: Parameters, value: f32) {
+        match parameter {
+            Parameters::Dampening => {
+                self.freeverb.set_dampening(value.into());
+            }
+            Parameters::Width => {
+                self.freeverb.set_width(value.into());
+            }
+            Parameters::RoomSize => {
+                self.freeverb.set_room_size(value.into());
+            }
+            Parameters::Freeze => {
+                self.freeverb.set_freeze(value != 0.0);
+            }
+            Parameters::Dry => {
+                self.dry = value;
+                self.freeverb.set_dry(value.into());
+            }
+            Parameters::Wet => {
+                self.freeverb.set_wet(value.into());
+            }
+            Parameters::Shimmer => {
+                self.freeverb.set_shimmer(value.into());
+            }
+            Parameters::ShimmerPitch => {
+                self.freeverb.set_shimmer_pitch(value.into());
+            }
+            Parameters::TailMode => {
+                self.freeverb.set_tail_mode(match value.round() as usize {
+                    1 => TailMode::Gated,
+                    2 => TailMode::Reverse,
+                    _ => TailMode::Natural,
+                });
+            }
+            Parameters::GateThreshold => {
+                self.freeverb.set_gate_threshold(db_to_gain(value).into());
+            }
+            Parameters::GateHold => {
+                self.freeverb.set_gate_hold((value / 1000.0).into());
+            }
+            Parameters::GateRelease => {
+                self.freeverb.set_gate_release((value / 1000.0).into());
+            }
+            Parameters::ReverseWindow => {
+                self.freeverb.set_reverse_window((value / 1000.0).into());
+            }
+            Parameters::Ducking => {
+                self.freeverb.set_ducking(value.into());
+            }
+            Parameters::DuckingAttack => {
+                self.freeverb.set_ducking_attack((value / 1000.0).into());
+            }
+            Parameters::DuckingRelease => {
+                self.freeverb.set_ducking_release((value / 1000.0).into());
+            }
+        }
+    }
+
+    fn pull_parameter_changes(&mut self) {
+        // The store is taken temporarily so that it can be borrowed while parameters are set
+        if let Some(store) = self.parameter_store.take() {
+            store.pull_audio_changes(|id, value| {
+                if let Some(parameter) = Parameters::from_usize(id) {
+                    self.set_parameter(parameter, value);
+                }
+            });
+            self.parameter_store = Some(store);
+        }
+    }
+
+    // Values that are set via commands are published, so that the GUI can follow them
+    fn publish_parameter_change(&mut self, id: usize, value: f32) {
+        if let Some(store) = &self.parameter_store {
+            let _ = store.set_from_audio(id, value);
+        }
+        if let Some(sender) = &mut self.notification_sender {
+            sender.send(Notification::ParameterChanged { id, value });
+        }
+    }
+
+    fn update_meters(&mut self, input: &[f32], output: &[f32]) {
+        let Some(sender) = &mut self.notification_sender else {
+            return;
+        };
+
+        let meter = &mut self.meter;
+        for (input, output) in input.chunks_exact(2).zip(output.chunks_exact(2)) {
+            for (input, output) in input.iter().zip(output) {
+                let wet = output - input * self.dry;
+                meter.input_peak = meter.input_peak.max(input.abs());
+                meter.output_peak = meter.output_peak.max(output.abs());
+                meter.wet_sum_of_squares += wet * wet;
+            }
+            meter.frames += 1;
+
+            if meter.frames >= self.meter_interval {
+                // Dropped notifications are fine, the next one will follow shortly
+                sender.send(Notification::Meter {
+                    input_peak: meter.input_peak,
+                    output_peak: meter.output_peak,
+                    wet_rms: (meter.wet_sum_of_squares / (meter.frames * 2) as f32).sqrt(),
+                });
+                *meter = Meter::default();
+            }
+        }
+
+        let tail_active = !self.freeverb.is_silent();
+        if tail_active != self.tail_active && sender.send(Notification::TailActive(tail_active)) {
+            self.tail_active = tail_active;
+        }
+    }
+
+    fn queue_event(&mut self, event: ParameterEvent) {
+        if self.events.len() == self.events.capacity() {
+            // Applying the event early is preferable to allocating on the audio thread
+            self.apply_event(event);
+            return;
+        }
+
+        // Events are kept sorted by offset, preserving the order of events with the same offset
+        let index = self
+            .events
+            .partition_point(|queued| queued.frame_offset <= event.frame_offset);
+        self.events.insert(index, event);
+    }
+
+    fn apply_event(&mut self, event: ParameterEvent) {
+        self.set_parameter(event.parameter, event.value);
+        self.publish_parameter_change(event.parameter as usize, event.value);
+    }
+
+    fn process_frames(&mut self, input: &[f32], sidechain: &[f32], output: &mut [f32]) {
+        for i in (0..input.len()).step_by(2) {
+            let result = self.freeverb.tick_with_sidechain(
+                (input[i].into(), input[i + 1].into()),
+                (sidechain[i].into(), sidechain[i + 1].into()),
+            );
+
+            output[i] = result.0.to_f32();
+            output[i + 1] = result.1.to_f32();
         }
     }
 }
 
 impl<T: Float> CommandHandler for FreeverbProcessor<T> {
-    fn handle_command(&mut self, command: Command) {
+    fn handle_command(&mut self, command: Command) -> Result<(), CommandError> {
         match command {
-            Command::SetParameter(id, value) => match Parameters::from_usize(id).unwrap() {
-                Parameters::Dampening => {
-                    self.freeverb.set_dampening(value.into());
-                }
-                Parameters::Width => {
-                    self.freeverb.set_width(value.into());
-                }
-                Parameters::RoomSize => {
-                    self.freeverb.set_room_size(value.into());
-                }
-                Parameters::Freeze => {
-                    self.freeverb.set_freeze(value != 0.0);
-                }
-                Parameters::Dry => {
-                    self.freeverb.set_dry(value.into());
-                }
-                Parameters::Wet => {
-                    self.freeverb.set_wet(value.into());
-                }
-            },
+            Command::SetParameter(id, value) => {
+                let parameter =
+                    Parameters::from_usize(id).ok_or(CommandError::UnknownParameter(id))?;
+                self.set_parameter(parameter, value);
+                self.publish_parameter_change(id, value);
+            }
+            Command::SetParameterAt {
+                id,
+                value,
+                frame_offset,
+            } => {
+                let parameter =
+                    Parameters::from_usize(id).ok_or(CommandError::UnknownParameter(id))?;
+                // The change is published when the event is applied during processing
+                self.queue_event(ParameterEvent {
+                    parameter,
+                    value,
+                    frame_offset,
+                });
+            }
         }
+
+        Ok(())
     }
 }
 
 impl<T: Float> AudioProcessor for FreeverbProcessor<T> {
     fn process(&mut self, input: &[f32], output: &mut [f32], channels: u32) {
+        self.process_with_sidechain(input, input, output, channels);
+    }
+
+    fn process_with_sidechain(
+        &mut self,
+        input: &[f32],
+        sidechain: &[f32],
+        output: &mut [f32],
+        channels: u32,
+    ) {
         debug_assert_eq!(channels, 2);
         debug_assert_eq!(input.len(), output.len());
+        debug_assert_eq!(input.len(), sidechain.len());
 
-        for i in (0..input.len()).step_by(2) {
-            let result = self.freeverb.tick((input[i].into(), input[i + 1].into()));
+        self.pull_parameter_changes();
 
-            output[i] = result.0.to_f32();
-            output[i + 1] = result.1.to_f32();
+        // The block is split at each queued event's offset, so that parameter changes are applied
+        // at the frame where they were scheduled.
+        let frames = input.len() / 2;
+        let mut start = 0;
+        let mut next_event = 0;
+
+        while start < frames {
+            while let Some(event) = self.events.get(next_event).copied() {
+                if event.frame_offset > start {
+                    break;
+                }
+                self.apply_event(event);
+                next_event += 1;
+            }
+
+            let end = match self.events.get(next_event) {
+                Some(event) => event.frame_offset.min(frames),
+                None => frames,
+            };
+
+            self.process_frames(
+                &input[start * 2..end * 2],
+                &sidechain[start * 2..end * 2],
+                &mut output[start * 2..end * 2],
+            );
+
+            start = end;
         }
+
+        self.update_meters(input, output);
+
+        // Events scheduled beyond the end of the block are applied after it
+        for i in next_event..self.events.len() {
+            let event = self.events[i];
+            self.apply_event(event);
+        }
+        self.events.clear();
+    }
+
+    fn tail_samples(&self) -> Option<usize> {
+        self.freeverb.tail_length_samples()
+    }
+
+    fn set_parameter_store(&mut self, store: Arc<ParameterStore>) {
+        self.parameter_store = Some(store);
+    }
+
+    fn set_notification_sender(&mut self, sender: NotificationSender) {
+        self.notification_sender = Some(sender);
+    }
+
+    fn reset(&mut self) {
+        self.freeverb.reset();
+        self.meter = Meter::default();
+        self.events.clear();
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: usize) {
+        self.freeverb.set_sample_rate(sample_rate);
+        self.meter_interval = meter_interval(sample_rate);
     }
 }
 
+fn meter_interval(sample_rate: usize) -> usize {
+    (sample_rate / METER_RATE).max(1)
+}
+
 pub struct FreeverbModule {}
 
 impl AudioModule for FreeverbModule {
@@ -83,6 +357,12 @@ impl AudioModule for FreeverbModule {
     }
 }
 
+impl PresetProvider for FreeverbModule {
+    fn factory_presets() -> Vec<Preset> {
+        presets::factory_presets()
+    }
+}
+
 impl ParameterProvider for FreeverbModule {
     fn parameter_count() -> usize {
         (0..usize::MAX)
@@ -90,34 +370,310 @@ impl ParameterProvider for FreeverbModule {
             .count()
     }
 
-    fn parameter(id: usize) -> Box<dyn Parameter> {
-        match Parameters::from_usize(id).unwrap() {
+    fn parameter(id: usize) -> Option<Box<dyn Parameter>> {
+        let parameter: Box<dyn Parameter> = match Parameters::from_usize(id)? {
             Parameters::Dampening => Box::new(
-                FloatParameter::new("Dampening")
+                FloatParameter::new("dampening", "Dampening")
                     .string_converter(percent_string_converter)
                     .default_user_value(0.5),
             ),
             Parameters::Width => Box::new(
-                FloatParameter::new("Width")
+                FloatParameter::new("width", "Width")
                     .string_converter(percent_string_converter)
                     .default_user_value(0.5),
             ),
             Parameters::RoomSize => Box::new(
-                FloatParameter::new("Room Size")
+                FloatParameter::new("room_size", "Room Size")
                     .string_converter(percent_string_converter)
                     .default_user_value(0.5),
             ),
-            Parameters::Freeze => Box::new(BoolParameter::new("Freeze")),
+            Parameters::Freeze => Box::new(BoolParameter::new("freeze", "Freeze")),
             Parameters::Dry => Box::new(
-                FloatParameter::new("Dry")
-                    .string_converter(percent_string_converter)
+                FloatParameter::new("dry", "Dry")
+                    .value_converter(decibel_value_converter)
+                    .string_converter(decibel_string_converter)
                     .default_user_value(0.0),
             ),
             Parameters::Wet => Box::new(
-                FloatParameter::new("Wet")
-                    .string_converter(percent_string_converter)
+                FloatParameter::new("wet", "Wet")
+                    .value_converter(decibel_value_converter)
+                    .string_converter(decibel_string_converter)
                     .default_user_value(1.0),
             ),
+            Parameters::Shimmer => Box::new(
+                FloatParameter::new("shimmer", "Shimmer")
+                    .string_converter(percent_string_converter)
+                    .default_user_value(0.0),
+            ),
+            Parameters::ShimmerPitch => Box::new(
+                IntParameter::new("shimmer_pitch", "Shimmer Pitch")
+                    .unit("st")
+                    .range(0, 12)
+                    .default_user_value(12),
+            ),
+            Parameters::TailMode => Box::new(ChoiceParameter::new(
+                "tail_mode",
+                "Tail Mode",
+                &["Natural", "Gated", "Reverse"],
+            )),
+            Parameters::GateThreshold => Box::new(
+                FloatParameter::new("gate_threshold", "Gate Threshold")
+                    .unit("dB")
+                    .precision(1)
+                    .range(-60.0, 0.0)
+                    .default_user_value(-40.0),
+            ),
+            Parameters::GateHold => Box::new(
+                FloatParameter::new("gate_hold", "Gate Hold")
+                    .unit("ms")
+                    .string_converter(time_string_converter)
+                    .range(0.0, 1000.0)
+                    .default_user_value(250.0)
+                    .center_user_value(250.0)
+                    .value_converter(skew_value_converter),
+            ),
+            Parameters::GateRelease => Box::new(
+                FloatParameter::new("gate_release", "Gate Release")
+                    .unit("ms")
+                    .string_converter(time_string_converter)
+                    .range(0.0, 1000.0)
+                    .default_user_value(50.0)
+                    .center_user_value(50.0)
+                    .value_converter(skew_value_converter),
+            ),
+            Parameters::ReverseWindow => Box::new(
+                FloatParameter::new("reverse_window", "Reverse Window")
+                    .unit("ms")
+                    .string_converter(time_string_converter)
+                    .range(50.0, 2000.0)
+                    .default_user_value(500.0)
+                    .center_user_value(500.0)
+                    .value_converter(skew_value_converter),
+            ),
+            Parameters::Ducking => Box::new(
+                FloatParameter::new("ducking", "Ducking")
+                    .string_converter(percent_string_converter)
+                    .default_user_value(0.0),
+            ),
+            Parameters::DuckingAttack => Box::new(
+                FloatParameter::new("ducking_attack", "Ducking Attack")
+                    .unit("ms")
+                    .string_converter(time_string_converter)
+                    .range(0.0, 500.0)
+                    .default_user_value(10.0)
+                    .center_user_value(10.0)
+                    .value_converter(skew_value_converter),
+            ),
+            Parameters::DuckingRelease => Box::new(
+                FloatParameter::new("ducking_release", "Ducking Release")
+                    .unit("ms")
+                    .string_converter(time_string_converter)
+                    .range(10.0, 2000.0)
+                    .default_user_value(250.0)
+                    .center_user_value(250.0)
+                    .value_converter(skew_value_converter),
+            ),
+        };
+
+        Some(parameter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamped_parameter_changes() {
+        let input: Vec<f32> = (0..512).map(|i| (i as f32 * 0.1).sin()).collect();
+
+        let mut scheduled = FreeverbModule::create_processor(44100);
+        scheduled
+            .handle_command(Command::SetParameterAt {
+                id: Parameters::Dry as usize,
+                value: 1.0,
+                frame_offset: 100,
+            })
+            .unwrap();
+        scheduled
+            .handle_command(Command::SetParameterAt {
+                id: Parameters::RoomSize as usize,
+                value: 0.2,
+                frame_offset: 10,
+            })
+            .unwrap();
+        let mut scheduled_output = vec![0.0; input.len()];
+        scheduled.process(&input, &mut scheduled_output, 2);
+
+        // The same changes applied between separately processed blocks
+        let mut split = FreeverbModule::create_processor(44100);
+        let mut split_output = vec![0.0; input.len()];
+        split.process(&input[..20], &mut split_output[..20], 2);
+        split
+            .handle_command(Command::SetParameter(Parameters::RoomSize as usize, 0.2))
+            .unwrap();
+        split.process(&input[20..200], &mut split_output[20..200], 2);
+        split
+            .handle_command(Command::SetParameter(Parameters::Dry as usize, 1.0))
+            .unwrap();
+        split.process(&input[200..], &mut split_output[200..], 2);
+
+        assert_eq!(scheduled_output, split_output);
+    }
+
+    #[test]
+    fn parameter_store() {
+        let store = Arc::new(ParameterStore::with_defaults::<FreeverbModule>());
+        let mut processor = FreeverbModule::create_processor(44100);
+        processor.set_parameter_store(store.clone());
+
+        let input = [0.5; 64];
+        let mut output = [0.0; 64];
+        processor.process(&input, &mut output, 2);
+
+        // A change from the GUI is applied in the next block, with only the dry signal passed on
+        store.set(Parameters::Wet as usize, 0.0).unwrap();
+        store.set(Parameters::Dry as usize, 1.0).unwrap();
+        processor.process(&input, &mut output, 2);
+        assert!(output.iter().all(|&sample| (sample - 0.5).abs() < 1.0e-6));
+
+        // Values set via commands are published for the GUI
+        processor
+            .handle_command(Command::SetParameter(Parameters::RoomSize as usize, 0.9))
+            .unwrap();
+        let mut changes = Vec::new();
+        store.pull_gui_changes(|id, value| changes.push((id, value)));
+        assert_eq!(changes, [(Parameters::RoomSize as usize, 0.9)]);
+
+        // Timestamped values are only published once they've been applied
+        processor
+            .handle_command(Command::SetParameterAt {
+                id: Parameters::Width as usize,
+                value: 0.25,
+                frame_offset: 8,
+            })
+            .unwrap();
+        assert_ne!(store.get(Parameters::Width as usize), Some(0.25));
+        processor.process(&input, &mut output, 2);
+        assert_eq!(store.get(Parameters::Width as usize), Some(0.25));
+        let mut changes = Vec::new();
+        store.pull_gui_changes(|id, value| changes.push((id, value)));
+        assert_eq!(changes, [(Parameters::Width as usize, 0.25)]);
+    }
+
+    #[test]
+    fn notifications() {
+        let (sender, mut receiver) = audio_module::notification_channel(64);
+        let sample_rate = 44100;
+        let mut processor = FreeverbModule::create_processor(sample_rate);
+        processor.set_notification_sender(sender);
+        processor
+            .handle_command(Command::SetParameter(Parameters::Dry as usize, 1.0))
+            .unwrap();
+        assert_eq!(
+            receiver.try_recv(),
+            Some(Notification::ParameterChanged {
+                id: Parameters::Dry as usize,
+                value: 1.0
+            })
+        );
+
+        // Enough input for a single meter notification
+        let input = vec![0.5; meter_interval(sample_rate) * 2];
+        let mut output = vec![0.0; input.len()];
+        processor.process(&input, &mut output, 2);
+
+        let Some(Notification::Meter {
+            input_peak,
+            output_peak,
+            wet_rms,
+        }) = receiver.try_recv()
+        else {
+            panic!("expected a meter notification");
+        };
+        assert_eq!(input_peak, 0.5);
+        assert!(output_peak > 0.5);
+        assert!(wet_rms > 0.0);
+        assert_eq!(receiver.try_recv(), Some(Notification::TailActive(true)));
+        assert_eq!(receiver.try_recv(), None);
+    }
+
+    #[test]
+    fn factory_presets() {
+        let presets = FreeverbModule::factory_presets();
+        assert!(presets.len() >= 10);
+
+        for (index, preset) in presets.iter().enumerate() {
+            assert!(
+                presets[..index]
+                    .iter()
+                    .all(|other| other.name != preset.name),
+                "duplicate preset name: {}",
+                preset.name
+            );
+
+            for (id, value) in preset.values.iter() {
+                let parameter = FreeverbModule::parameter_index(id)
+                    .and_then(FreeverbModule::parameter)
+                    .unwrap_or_else(|| panic!("{}: unknown parameter '{id}'", preset.name));
+                let linear = parameter.make_value_converter().user_to_linear(*value);
+                assert!((0.0..=1.0).contains(&linear), "{}: {id}", preset.name);
+            }
+
+            // Presets other than the default pass the dry signal through unchanged
+            if index > 0 {
+                assert_eq!(preset.values.get("dry"), Some(&1.0), "{}", preset.name);
+            }
+
+            // Presets survive being saved and loaded
+            let loaded = Preset::from_text::<FreeverbModule>(&preset.to_text()).unwrap();
+            assert_eq!(&loaded, preset);
+        }
+    }
+
+    #[test]
+    fn unknown_parameter() {
+        let count = FreeverbModule::parameter_count();
+        assert!(FreeverbModule::parameter(count - 1).is_some());
+        assert!(FreeverbModule::parameter(count).is_none());
+
+        let mut processor = FreeverbModule::create_processor(44100);
+        assert_eq!(
+            processor.handle_command(Command::SetParameter(count, 1.0)),
+            Err(CommandError::UnknownParameter(count))
+        );
+        assert_eq!(
+            processor.handle_command(Command::SetParameterAt {
+                id: count,
+                value: 1.0,
+                frame_offset: 0
+            }),
+            Err(CommandError::UnknownParameter(count))
+        );
+    }
+
+    #[test]
+    fn parameter_ids() {
+        for index in 0..FreeverbModule::parameter_count() {
+            let id = FreeverbModule::parameter(index).unwrap().id();
+            assert_eq!(FreeverbModule::parameter_index(id), Some(index), "{id}");
+        }
+
+        assert_eq!(
+            FreeverbModule::parameter_index("room_size"),
+            Some(Parameters::RoomSize as usize)
+        );
+        assert_eq!(FreeverbModule::parameter_index("unknown"), None);
+    }
+
+    #[test]
+    fn default_values_round_trip() {
+        for index in 0..FreeverbModule::parameter_count() {
+            let parameter = FreeverbModule::parameter(index).unwrap();
+            let converter = parameter.make_value_converter();
+            let default = parameter.default_user_value();
+            let round_trip = converter.linear_to_user(converter.user_to_linear(default));
+            assert!((round_trip - default).abs() < 1.0e-3, "{}", parameter.id());
         }
     }
 }
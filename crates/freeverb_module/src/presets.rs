@@ -0,0 +1,77 @@
+use audio_module::Preset;
+
+/// Freeverb's factory presets, starting with the default settings.
+///
+/// The presets other than the default pass the dry signal through unchanged, so that they can be
+/// used as an insert effect.
+pub fn factory_presets() -> Vec<Preset> {
+    vec![
+        Preset::new("Default"),
+        Preset::new("Small Room")
+            .value("room_size", 0.3)
+            .value("dampening", 0.6)
+            .value("width", 0.8)
+            .value("dry", 1.0)
+            .value("wet", 0.25),
+        Preset::new("Drum Room")
+            .value("room_size", 0.45)
+            .value("dampening", 0.3)
+            .value("width", 1.0)
+            .value("dry", 1.0)
+            .value("wet", 0.35),
+        Preset::new("Vocal Plate")
+            .value("room_size", 0.65)
+            .value("dampening", 0.2)
+            .value("width", 1.0)
+            .value("dry", 1.0)
+            .value("wet", 0.3),
+        Preset::new("Concert Hall")
+            .value("room_size", 0.8)
+            .value("dampening", 0.5)
+            .value("width", 1.0)
+            .value("dry", 1.0)
+            .value("wet", 0.4),
+        Preset::new("Cathedral")
+            .value("room_size", 0.95)
+            .value("dampening", 0.35)
+            .value("width", 1.0)
+            .value("dry", 1.0)
+            .value("wet", 0.5),
+        Preset::new("Infinite Freeze Pad")
+            .value("freeze", 1.0)
+            .value("width", 1.0)
+            .value("dry", 1.0)
+            .value("wet", 0.6),
+        Preset::new("Shimmer Heaven")
+            .value("room_size", 0.85)
+            .value("dampening", 0.4)
+            .value("width", 1.0)
+            .value("shimmer", 0.5)
+            .value("shimmer_pitch", 12.0)
+            .value("dry", 1.0)
+            .value("wet", 0.5),
+        Preset::new("Gated Snare")
+            .value("room_size", 0.7)
+            .value("dampening", 0.3)
+            .value("tail_mode", 1.0)
+            .value("gate_threshold", -30.0)
+            .value("gate_hold", 150.0)
+            .value("gate_release", 20.0)
+            .value("dry", 1.0)
+            .value("wet", 0.6),
+        Preset::new("Reverse Swell")
+            .value("room_size", 0.75)
+            .value("tail_mode", 2.0)
+            .value("reverse_window", 800.0)
+            .value("dry", 1.0)
+            .value("wet", 0.6),
+        Preset::new("Ducked Vocal")
+            .value("room_size", 0.7)
+            .value("dampening", 0.4)
+            .value("ducking", 0.7)
+            .value("ducking_attack", 10.0)
+            .value("ducking_release", 400.0)
+            .value("dry", 1.0)
+            .value("wet", 0.4),
+    ]
+}
@@ -0,0 +1,343 @@
+use {
+    crate::{
+        Lv2Definition,
+        ports::{AUDIO_PORTS, ControlPort},
+    },
+    audio_module::{AudioModule, AudioProcessor, Command, CommandHandler},
+    std::{
+        ffi::{c_char, c_void},
+        ptr, slice,
+    },
+};
+
+// The number of channels processed by the module, matching the stereo audio ports
+const PROCESSOR_CHANNELS: usize = 2;
+
+// The largest number of frames that's processed at once, larger blocks are split up
+const MAX_BLOCK_FRAMES: usize = 1024;
+
+type Lv2Handle = *mut c_void;
+
+/// A feature provided by the host, see `LV2_Feature` in `lv2/core/lv2.h`.
+#[repr(C)]
+pub struct Lv2Feature {
+    pub uri: *const c_char,
+    pub data: *mut c_void,
+}
+
+/// The plugin's entry points, see `LV2_Descriptor` in `lv2/core/lv2.h`.
+///
+/// A descriptor is returned to the host by the library's `lv2_descriptor` function, which is
+/// exported by [export_lv2_plugin](crate::export_lv2_plugin).
+#[repr(C)]
+pub struct Lv2Descriptor {
+    pub(crate) uri: *const c_char,
+    pub(crate) instantiate: unsafe extern "C" fn(
+        descriptor: *const Lv2Descriptor,
+        sample_rate: f64,
+        bundle_path: *const c_char,
+        features: *const *const Lv2Feature,
+    ) -> Lv2Handle,
+    pub(crate) connect_port: unsafe extern "C" fn(Lv2Handle, u32, *mut c_void),
+    pub(crate) activate: unsafe extern "C" fn(Lv2Handle),
+    pub(crate) run: unsafe extern "C" fn(Lv2Handle, u32),
+    pub(crate) deactivate: unsafe extern "C" fn(Lv2Handle),
+    pub(crate) cleanup: unsafe extern "C" fn(Lv2Handle),
+    pub(crate) extension_data: unsafe extern "C" fn(*const c_char) -> *const c_void,
+}
+
+// The descriptor only refers to static data, so it can be shared between threads
+unsafe impl Sync for Lv2Descriptor {}
+
+impl Lv2Descriptor {
+    pub const fn new<Definition: Lv2Definition>() -> Self {
+        Self {
+            uri: Definition::URI.as_ptr(),
+            instantiate: instantiate::<Definition>,
+            connect_port: connect_port::<Definition>,
+            activate: activate::<Definition>,
+            run: run::<Definition>,
+            deactivate,
+            cleanup: cleanup::<Definition>,
+            extension_data,
+        }
+    }
+}
+
+type Processor<Definition> = <<Definition as Lv2Definition>::Module as AudioModule>::Processor;
+
+/// A running instance of the plugin, with the buffers that the host has connected to its ports.
+struct Lv2Instance<Definition: Lv2Definition> {
+    processor: Processor<Definition>,
+    audio_inputs: [*const f32; PROCESSOR_CHANNELS],
+    audio_outputs: [*mut f32; PROCESSOR_CHANNELS],
+    controls: Vec<*const f32>,
+    control_ports: Vec<ControlPort>,
+    // The values that were last passed to the processor, `None` until the first run
+    control_values: Vec<Option<f32>>,
+    // Interleaved buffers for the processor
+    input: Vec<f32>,
+    output: Vec<f32>,
+}
+
+impl<Definition: Lv2Definition> Lv2Instance<Definition> {
+    fn new(sample_rate: usize) -> Self {
+        let control_ports = ControlPort::all::<Definition::Module>();
+        Self {
+            processor: Definition::Module::create_processor(sample_rate),
+            audio_inputs: [ptr::null(); PROCESSOR_CHANNELS],
+            audio_outputs: [ptr::null_mut(); PROCESSOR_CHANNELS],
+            controls: vec![ptr::null(); control_ports.len()],
+            control_values: vec![None; control_ports.len()],
+            control_ports,
+            input: vec![0.0; MAX_BLOCK_FRAMES * PROCESSOR_CHANNELS],
+            output: vec![0.0; MAX_BLOCK_FRAMES * PROCESSOR_CHANNELS],
+        }
+    }
+
+    fn connect(&mut self, port: usize, data: *mut c_void) {
+        match port {
+            0..PROCESSOR_CHANNELS => self.audio_inputs[port] = data as *const f32,
+            PROCESSOR_CHANNELS..4 => self.audio_outputs[port - PROCESSOR_CHANNELS] = data.cast(),
+            _ => {
+                if let Some(control) = self.controls.get_mut(port - AUDIO_PORTS.len()) {
+                    *control = data as *const f32;
+                }
+            }
+        }
+    }
+
+    // Passes changed control values on to the processor
+    //
+    // Safety: the connected control ports must be valid for reads
+    unsafe fn update_controls(&mut self) {
+        for (index, control) in self.controls.iter().enumerate() {
+            if control.is_null() {
+                continue;
+            }
+
+            let value = self.control_ports[index].constrain(unsafe { **control });
+            if self.control_values[index] != Some(value) {
+                self.control_values[index] = Some(value);
+                // The index comes from the module's parameters, so the command can't fail
+                let _ = self
+                    .processor
+                    .handle_command(Command::SetParameter(index, value));
+            }
+        }
+    }
+
+    // Safety: the connected audio ports must be valid for `frames` samples
+    unsafe fn run(&mut self, frames: usize) {
+        unsafe { self.update_controls() };
+
+        let mut start = 0;
+        while start < frames {
+            let block_frames = (frames - start).min(MAX_BLOCK_FRAMES);
+            let samples = block_frames * PROCESSOR_CHANNELS;
+
+            // The inputs are copied before the outputs are written, which allows the host to use
+            // the same buffers for input and output
+            for (channel, input) in self.audio_inputs.iter().enumerate() {
+                if input.is_null() {
+                    continue;
+                }
+                let input = unsafe { slice::from_raw_parts(input.add(start), block_frames) };
+                for (frame, sample) in input.iter().enumerate() {
+                    self.input[frame * PROCESSOR_CHANNELS + channel] = *sample;
+                }
+            }
+
+            self.processor.process(
+                &self.input[..samples],
+                &mut self.output[..samples],
+                PROCESSOR_CHANNELS as u32,
+            );
+
+            for (channel, output) in self.audio_outputs.iter().enumerate() {
+                if output.is_null() {
+                    continue;
+                }
+                let output = unsafe { slice::from_raw_parts_mut(output.add(start), block_frames) };
+                for (frame, sample) in output.iter_mut().enumerate() {
+                    *sample = self.output[frame * PROCESSOR_CHANNELS + channel];
+                }
+            }
+
+            start += block_frames;
+        }
+    }
+}
+
+unsafe extern "C" fn instantiate<Definition: Lv2Definition>(
+    _descriptor: *const Lv2Descriptor,
+    sample_rate: f64,
+    _bundle_path: *const c_char,
+    _features: *const *const Lv2Feature,
+) -> Lv2Handle {
+    let instance = Box::new(Lv2Instance::<Definition>::new(sample_rate as usize));
+    Box::into_raw(instance).cast()
+}
+
+unsafe extern "C" fn connect_port<Definition: Lv2Definition>(
+    handle: Lv2Handle,
+    port: u32,
+    data: *mut c_void,
+) {
+    let instance = unsafe { &mut *handle.cast::<Lv2Instance<Definition>>() };
+    instance.connect(port as usize, data);
+}
+
+unsafe extern "C" fn activate<Definition: Lv2Definition>(handle: Lv2Handle) {
+    let instance = unsafe { &mut *handle.cast::<Lv2Instance<Definition>>() };
+    instance.processor.reset();
+}
+
+unsafe extern "C" fn run<Definition: Lv2Definition>(handle: Lv2Handle, sample_count: u32) {
+    let instance = unsafe { &mut *handle.cast::<Lv2Instance<Definition>>() };
+    unsafe { instance.run(sample_count as usize) };
+}
+
+unsafe extern "C" fn deactivate(_handle: Lv2Handle) {}
+
+unsafe extern "C" fn cleanup<Definition: Lv2Definition>(handle: Lv2Handle) {
+    drop(unsafe { Box::from_raw(handle.cast::<Lv2Instance<Definition>>()) });
+}
+
+unsafe extern "C" fn extension_data(_uri: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, crate::tests::TestDefinition, audio_module::ParameterProvider,
+        freeverb_module::FreeverbModule, std::ffi::CStr,
+    };
+
+    static DESCRIPTOR: Lv2Descriptor = Lv2Descriptor::new::<TestDefinition>();
+
+    const FRAMES: usize = 3000;
+
+    struct TestHost {
+        handle: Lv2Handle,
+        inputs: [Vec<f32>; 2],
+        outputs: [Vec<f32>; 2],
+        controls: Vec<f32>,
+    }
+
+    impl TestHost {
+        // The host's buffers are boxed so that their addresses stay fixed once connected
+        fn new() -> Box<Self> {
+            let handle = unsafe {
+                (DESCRIPTOR.instantiate)(&DESCRIPTOR, 44100.0, c"/test".as_ptr(), ptr::null())
+            };
+            let mut host = Box::new(Self {
+                handle,
+                inputs: [vec![0.0; FRAMES], vec![0.0; FRAMES]],
+                outputs: [vec![0.0; FRAMES], vec![0.0; FRAMES]],
+                controls: (0..FreeverbModule::parameter_count())
+                    .map(|index| {
+                        FreeverbModule::parameter(index)
+                            .unwrap()
+                            .default_user_value()
+                    })
+                    .collect(),
+            });
+
+            unsafe {
+                for channel in 0..2 {
+                    let input = host.inputs[channel].as_mut_ptr().cast();
+                    let output = host.outputs[channel].as_mut_ptr().cast();
+                    (DESCRIPTOR.connect_port)(handle, channel as u32, input);
+                    (DESCRIPTOR.connect_port)(handle, channel as u32 + 2, output);
+                }
+                for index in 0..host.controls.len() {
+                    let control = (&mut host.controls[index] as *mut f32).cast();
+                    (DESCRIPTOR.connect_port)(handle, (index + AUDIO_PORTS.len()) as u32, control);
+                }
+                (DESCRIPTOR.activate)(handle);
+            }
+
+            host
+        }
+
+        fn set_control(&mut self, id: &str, value: f32) {
+            self.controls[FreeverbModule::parameter_index(id).unwrap()] = value;
+        }
+
+        fn run(&mut self) {
+            unsafe { (DESCRIPTOR.run)(self.handle, FRAMES as u32) };
+        }
+    }
+
+    impl Drop for TestHost {
+        fn drop(&mut self) {
+            unsafe {
+                (DESCRIPTOR.deactivate)(self.handle);
+                (DESCRIPTOR.cleanup)(self.handle);
+            }
+        }
+    }
+
+    #[test]
+    fn descriptor_uri() {
+        let uri = unsafe { CStr::from_ptr(DESCRIPTOR.uri) };
+        assert_eq!(uri, TestDefinition::URI);
+        assert!(unsafe { (DESCRIPTOR.extension_data)(c"urn:unknown".as_ptr()) }.is_null());
+    }
+
+    #[test]
+    fn processing_with_controls() {
+        let mut host = TestHost::new();
+
+        // An impulse is heard in the reverb's tail, which is longer than a single block
+        host.inputs[0][0] = 1.0;
+        host.inputs[1][0] = 1.0;
+        host.run();
+        for output in &host.outputs {
+            assert_eq!(output[0], 0.0);
+            assert!(
+                output[MAX_BLOCK_FRAMES..]
+                    .iter()
+                    .any(|sample| *sample != 0.0)
+            );
+        }
+
+        // Changing the controls is picked up on the next run
+        host.set_control("dry", 1.0);
+        host.set_control("wet", 0.0);
+        host.set_control("freeze", 0.0);
+        host.run();
+        for output in &host.outputs {
+            assert_eq!(output[0], 1.0);
+            assert!(output[1..].iter().all(|sample| *sample == 0.0));
+        }
+    }
+
+    #[test]
+    fn in_place_processing() {
+        let mut host = TestHost::new();
+        host.set_control("dry", 1.0);
+        host.set_control("wet", 0.0);
+
+        let input: Vec<f32> = (0..FRAMES).map(|frame| frame as f32 / 10000.0).collect();
+        host.inputs = [input.clone(), input.clone()];
+
+        // The outputs are connected to the input buffers
+        for channel in 0..2 {
+            let buffer = host.inputs[channel].as_mut_ptr().cast();
+            unsafe {
+                (DESCRIPTOR.connect_port)(host.handle, channel as u32, buffer);
+                (DESCRIPTOR.connect_port)(host.handle, channel as u32 + 2, buffer);
+            }
+        }
+        host.run();
+
+        for channel in &host.inputs {
+            for (result, expected) in channel.iter().zip(&input) {
+                assert!((result - expected).abs() < 1.0e-6);
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! A generic [LV2](https://lv2plug.in) plugin wrapper for any [AudioModule].
+//!
+//! A plugin is described by implementing [Lv2Definition], and then exported from a `cdylib` crate
+//! with [export_lv2_plugin]:
+//!
+//! ```ignore
+//! struct Freeverb;
+//!
+//! impl lv2_plugin::Lv2Definition for Freeverb {
+//!     type Module = freeverb_module::FreeverbModule;
+//!
+//!     const URI: &'static std::ffi::CStr = c"urn:freeverb-rs:freeverb";
+//!     const NAME: &'static str = "Freeverb";
+//!     const CLASS: Option<&'static str> = Some("ReverbPlugin");
+//! }
+//!
+//! lv2_plugin::export_lv2_plugin!(Freeverb);
+//! ```
+//!
+//! LV2 hosts discover plugins via a bundle of Turtle files that describe the plugin's ports, which
+//! are generated from the module's parameters by [write_bundle]. The plugin has stereo audio
+//! inputs and outputs, followed by an input control port for each parameter.
+
+mod descriptor;
+mod ports;
+mod ttl;
+
+pub use {
+    descriptor::{Lv2Descriptor, Lv2Feature},
+    ttl::{PLUGIN_TTL, manifest_ttl, plugin_ttl, write_bundle},
+};
+
+use {audio_module::AudioModule, std::ffi::CStr};
+
+/// Describes a plugin that wraps an [AudioModule].
+pub trait Lv2Definition: 'static {
+    type Module: AudioModule;
+
+    /// A URI that uniquely identifies the plugin, e.g. `http://example.com/plugins/verb`.
+    const URI: &'static CStr;
+    const NAME: &'static str;
+    /// The plugin's class in the `lv2` namespace, e.g. `ReverbPlugin`.
+    const CLASS: Option<&'static str> = None;
+}
+
+/// Exports the `lv2_descriptor` symbol for a [Lv2Definition]'s plugin.
+///
+/// This should be used once in a `cdylib` crate, the resulting library can then be placed in a
+/// bundle folder alongside the files written by [write_bundle].
+#[macro_export]
+macro_rules! export_lv2_plugin {
+    ($definition:ty) => {
+        static LV2_DESCRIPTOR: $crate::Lv2Descriptor = $crate::Lv2Descriptor::new::<$definition>();
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn lv2_descriptor(index: u32) -> *const $crate::Lv2Descriptor {
+            if index == 0 {
+                &LV2_DESCRIPTOR
+            } else {
+                std::ptr::null()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, freeverb_module::FreeverbModule};
+
+    pub(crate) struct TestDefinition;
+
+    impl Lv2Definition for TestDefinition {
+        type Module = FreeverbModule;
+
+        const URI: &'static CStr = c"urn:freeverb-rs:test";
+        const NAME: &'static str = "Freeverb \"Test\"";
+        const CLASS: Option<&'static str> = Some("ReverbPlugin");
+    }
+}
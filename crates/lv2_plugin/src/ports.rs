@@ -0,0 +1,151 @@
+use audio_module::{ParameterProvider, ValueType};
+
+/// The plugin's audio ports, which come before the control ports.
+pub(crate) const AUDIO_PORTS: [AudioPort; 4] = [
+    AudioPort::new("in_l", "Left In", true),
+    AudioPort::new("in_r", "Right In", true),
+    AudioPort::new("out_l", "Left Out", false),
+    AudioPort::new("out_r", "Right Out", false),
+];
+
+pub(crate) struct AudioPort {
+    pub symbol: &'static str,
+    pub name: &'static str,
+    pub is_input: bool,
+}
+
+impl AudioPort {
+    const fn new(symbol: &'static str, name: &'static str, is_input: bool) -> Self {
+        Self {
+            symbol,
+            name,
+            is_input,
+        }
+    }
+}
+
+/// An input control port for one of a module's parameters.
+///
+/// Control ports carry user values, with the range taken from the parameter's value converter.
+pub(crate) struct ControlPort {
+    pub symbol: String,
+    pub name: String,
+    pub unit: String,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub kind: ControlKind,
+}
+
+pub(crate) enum ControlKind {
+    Float,
+    /// Whole numbers, e.g. from an `IntParameter`.
+    Integer,
+    Toggle,
+    /// A choice between options, with each option's label and user value.
+    Enumeration(Vec<(String, f32)>),
+}
+
+impl ControlPort {
+    /// Makes the control ports for all of a module's parameters, indexed by parameter index.
+    pub fn all<Provider: ParameterProvider>() -> Vec<Self> {
+        (0..Provider::parameter_count())
+            .filter_map(Provider::parameter)
+            .map(|parameter| {
+                let value_converter = parameter.make_value_converter();
+                let string_converter = parameter.make_string_converter();
+                let min = value_converter.linear_to_user(0.0);
+                let max = value_converter.linear_to_user(1.0);
+
+                let kind = match parameter.value_type() {
+                    ValueType::Float => ControlKind::Float,
+                    ValueType::Int { .. } => ControlKind::Integer,
+                    ValueType::Bool => ControlKind::Toggle,
+                    ValueType::Choice(options) => ControlKind::Enumeration(
+                        (0..options)
+                            .map(|option| {
+                                let value = option as f32;
+                                (string_converter.to_string(value), value)
+                            })
+                            .collect(),
+                    ),
+                };
+
+                Self {
+                    symbol: port_symbol(parameter.id()),
+                    name: parameter.name(),
+                    unit: parameter.unit(),
+                    min: min.min(max),
+                    max: max.max(min),
+                    default: parameter.default_user_value(),
+                    kind,
+                }
+            })
+            .collect()
+    }
+
+    /// Clamps a value received from the host to the port's range, rounding stepped values.
+    pub fn constrain(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        match self.kind {
+            ControlKind::Float => value,
+            ControlKind::Integer | ControlKind::Toggle | ControlKind::Enumeration(_) => {
+                value.round()
+            }
+        }
+    }
+}
+
+// LV2 port symbols need to be valid C identifiers
+fn port_symbol(id: &str) -> String {
+    let mut symbol: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !symbol.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        symbol.insert(0, '_');
+    }
+    symbol
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, freeverb_module::FreeverbModule};
+
+    fn port(id: &str) -> ControlPort {
+        let index = FreeverbModule::parameter_index(id).unwrap();
+        ControlPort::all::<FreeverbModule>().swap_remove(index)
+    }
+
+    #[test]
+    fn ranges() {
+        let gate_threshold = port("gate_threshold");
+        assert_eq!(gate_threshold.symbol, "gate_threshold");
+        assert_eq!(gate_threshold.unit, "dB");
+        assert_eq!((gate_threshold.min, gate_threshold.max), (-60.0, 0.0));
+        assert_eq!(gate_threshold.default, -40.0);
+        assert_eq!(gate_threshold.constrain(10.0), 0.0);
+
+        let tail_mode = port("tail_mode");
+        assert_eq!((tail_mode.min, tail_mode.max), (0.0, 2.0));
+        assert_eq!(tail_mode.constrain(1.4), 1.0);
+        let ControlKind::Enumeration(options) = tail_mode.kind else {
+            panic!("Expected an enumeration");
+        };
+        assert_eq!(options[2], ("Reverse".to_string(), 2.0));
+
+        assert!(matches!(port("freeze").kind, ControlKind::Toggle));
+
+        let shimmer_pitch = port("shimmer_pitch");
+        assert!(matches!(shimmer_pitch.kind, ControlKind::Integer));
+        assert_eq!((shimmer_pitch.min, shimmer_pitch.max), (0.0, 12.0));
+        assert_eq!(shimmer_pitch.constrain(6.6), 7.0);
+        assert_eq!(shimmer_pitch.constrain(-3.0), 0.0);
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!(port_symbol("room_size"), "room_size");
+        assert_eq!(port_symbol("2nd-stage"), "_2nd_stage");
+    }
+}
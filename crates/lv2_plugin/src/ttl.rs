@@ -0,0 +1,228 @@
+use {
+    crate::{
+        Lv2Definition,
+        ports::{AUDIO_PORTS, ControlKind, ControlPort},
+    },
+    std::{fmt::Write, fs, io, path::Path},
+};
+
+/// The name of the file in the bundle that describes the plugin.
+pub const PLUGIN_TTL: &str = "plugin.ttl";
+
+const PREFIXES: &str = "\
+@prefix doap: <http://usefulinc.com/ns/doap#> .
+@prefix lv2: <http://lv2plug.in/ns/lv2core#> .
+@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .
+@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+@prefix units: <http://lv2plug.in/ns/extensions/units#> .
+";
+
+/// Returns the bundle's `manifest.ttl`, which points hosts to the plugin's binary and description.
+///
+/// `binary` is the file name of the plugin's library within the bundle, e.g. `libfreeverb.so`.
+pub fn manifest_ttl<Definition: Lv2Definition>(binary: &str) -> String {
+    format!(
+        "{PREFIXES}
+<{uri}>
+    a lv2:Plugin ;
+    lv2:binary <{binary}> ;
+    rdfs:seeAlso <{PLUGIN_TTL}> .
+",
+        uri = plugin_uri::<Definition>(),
+    )
+}
+
+/// Returns the plugin's description, including its ports.
+///
+/// The audio ports are followed by an input control port for each of the module's parameters,
+/// with the port's symbol, name, range, default, and unit taken from the parameter.
+pub fn plugin_ttl<Definition: Lv2Definition>() -> String {
+    let mut ports = Vec::new();
+
+    for (index, port) in AUDIO_PORTS.iter().enumerate() {
+        let direction = if port.is_input { "Input" } else { "Output" };
+        ports.push(format!(
+            "a lv2:{direction}Port, lv2:AudioPort ;
+        lv2:index {index} ;
+        lv2:symbol \"{}\" ;
+        lv2:name \"{}\"",
+            port.symbol, port.name
+        ));
+    }
+
+    for (index, port) in ControlPort::all::<Definition::Module>().iter().enumerate() {
+        ports.push(control_port(AUDIO_PORTS.len() + index, port));
+    }
+
+    let mut classes = "lv2:Plugin".to_string();
+    if let Some(class) = Definition::CLASS {
+        write!(classes, ", lv2:{class}").unwrap();
+    }
+
+    format!(
+        "{PREFIXES}
+<{uri}>
+    a {classes} ;
+    doap:name \"{name}\" ;
+    lv2:optionalFeature lv2:hardRTCapable ;
+    lv2:port [
+        {ports}
+    ] .
+",
+        uri = plugin_uri::<Definition>(),
+        name = escape(Definition::NAME),
+        ports = ports.join("\n    ] , [\n        "),
+    )
+}
+
+/// Writes the bundle's `.ttl` files to a folder, see [manifest_ttl] and [plugin_ttl].
+///
+/// The plugin's binary should be copied to the same folder to complete the bundle.
+pub fn write_bundle<Definition: Lv2Definition>(folder: &Path, binary: &str) -> io::Result<()> {
+    fs::create_dir_all(folder)?;
+    fs::write(
+        folder.join("manifest.ttl"),
+        manifest_ttl::<Definition>(binary),
+    )?;
+    fs::write(folder.join(PLUGIN_TTL), plugin_ttl::<Definition>())
+}
+
+fn plugin_uri<Definition: Lv2Definition>() -> &'static str {
+    Definition::URI
+        .to_str()
+        .expect("the plugin's URI should be valid UTF-8")
+}
+
+fn control_port(index: usize, port: &ControlPort) -> String {
+    let mut ttl = format!(
+        "a lv2:InputPort, lv2:ControlPort ;
+        lv2:index {index} ;
+        lv2:symbol \"{}\" ;
+        lv2:name \"{}\" ;
+        lv2:default {:?} ;
+        lv2:minimum {:?} ;
+        lv2:maximum {:?}",
+        port.symbol,
+        escape(&port.name),
+        port.default,
+        port.min,
+        port.max,
+    );
+
+    match &port.kind {
+        ControlKind::Float => {}
+        ControlKind::Integer => ttl.push_str(" ;\n        lv2:portProperty lv2:integer"),
+        ControlKind::Toggle => ttl.push_str(" ;\n        lv2:portProperty lv2:toggled"),
+        ControlKind::Enumeration(options) => {
+            ttl.push_str(" ;\n        lv2:portProperty lv2:enumeration, lv2:integer");
+            for (label, value) in options {
+                write!(
+                    ttl,
+                    " ;\n        lv2:scalePoint [ rdfs:label \"{}\" ; rdf:value {value:?} ]",
+                    escape(label)
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    if !port.unit.is_empty() {
+        write!(ttl, " ;\n        units:unit {}", unit(&port.unit)).unwrap();
+    }
+
+    ttl
+}
+
+// Returns the LV2 unit for a parameter's unit, with unknown units being described inline
+fn unit(unit: &str) -> String {
+    let known = match unit {
+        "dB" => "db",
+        "ms" => "ms",
+        "s" => "s",
+        "Hz" => "hz",
+        "kHz" => "khz",
+        "st" => "semitone12TET",
+        "%" => "pc",
+        "bpm" => "bpm",
+        "ct" => "cent",
+        _ => {
+            let unit = escape(unit);
+            return format!(
+                "[ rdfs:label \"{unit}\" ; units:symbol \"{unit}\" ; units:render \"%f {unit}\" ]"
+            );
+        }
+    };
+    format!("units:{known}")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::tests::TestDefinition};
+
+    #[test]
+    fn manifest() {
+        let manifest = manifest_ttl::<TestDefinition>("libtest.so");
+        assert!(manifest.contains("<urn:freeverb-rs:test>\n    a lv2:Plugin ;"));
+        assert!(manifest.contains("lv2:binary <libtest.so> ;"));
+        assert!(manifest.contains("rdfs:seeAlso <plugin.ttl> ."));
+    }
+
+    #[test]
+    fn ports() {
+        let ttl = plugin_ttl::<TestDefinition>();
+        assert!(ttl.contains("a lv2:Plugin, lv2:ReverbPlugin ;"));
+        assert!(ttl.contains("doap:name \"Freeverb \\\"Test\\\"\" ;"));
+        assert!(ttl.contains(
+            "a lv2:OutputPort, lv2:AudioPort ;
+        lv2:index 3 ;
+        lv2:symbol \"out_r\""
+        ));
+
+        // The first parameter follows the audio ports
+        assert!(ttl.contains(
+            "a lv2:InputPort, lv2:ControlPort ;
+        lv2:index 4 ;
+        lv2:symbol \"dampening\" ;
+        lv2:name \"Dampening\" ;
+        lv2:default 0.5 ;
+        lv2:minimum 0.0 ;
+        lv2:maximum 1.0"
+        ));
+        assert!(ttl.contains(
+            "lv2:symbol \"freeze\" ;
+        lv2:name \"Freeze\" ;
+        lv2:default 0.0 ;
+        lv2:minimum 0.0 ;
+        lv2:maximum 1.0 ;
+        lv2:portProperty lv2:toggled"
+        ));
+        assert!(ttl.contains("lv2:scalePoint [ rdfs:label \"Gated\" ; rdf:value 1.0 ]"));
+        assert!(ttl.contains(
+            "lv2:minimum -60.0 ;
+        lv2:maximum 0.0 ;
+        units:unit units:db"
+        ));
+        assert!(ttl.contains(
+            "lv2:symbol \"shimmer_pitch\" ;
+        lv2:name \"Shimmer Pitch\" ;
+        lv2:default 12.0 ;
+        lv2:minimum 0.0 ;
+        lv2:maximum 12.0 ;
+        lv2:portProperty lv2:integer ;
+        units:unit units:semitone12TET"
+        ));
+    }
+
+    #[test]
+    fn custom_units() {
+        assert_eq!(unit("ms"), "units:ms");
+        assert_eq!(
+            unit("x"),
+            "[ rdfs:label \"x\" ; units:symbol \"x\" ; units:render \"%f x\" ]"
+        );
+    }
+}
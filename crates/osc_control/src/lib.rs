@@ -0,0 +1,560 @@
+//! Control of an audio module's parameters via OSC messages received over UDP.
+//!
+//! The address space is derived from the module's parameters, with each parameter's string id
+//! appended to a prefix, e.g. with a prefix of `/freeverb`:
+//!
+//! - `/freeverb/room_size 0.8` sets a parameter's user value, a string like `80 %` can also be
+//!   used.
+//! - `/freeverb/room_size/normalized 0.5` sets a parameter's value in the range `0..=1`.
+//! - Sending any of the above without an argument queries the current value.
+//! - `/freeverb/room_size/range` queries a parameter's minimum, maximum, and default user values.
+//! - `/freeverb/room_size/display` queries a parameter's value as a display string.
+//! - `/freeverb/parameters` queries the ids of all parameters.
+//!
+//! Replies are sent back to the sender, using the address of the query.
+
+use {
+    audio_module::{
+        Command, CommandError, Parameter, ParameterProvider, ParameterStore, StringConverter,
+        ValueConverter,
+    },
+    rosc::{OscMessage, OscPacket, OscType},
+    std::{
+        fmt, io,
+        net::{SocketAddr, ToSocketAddrs, UdpSocket},
+        sync::Arc,
+        thread,
+    },
+};
+
+struct OscParameter {
+    id: &'static str,
+    parameter: Box<dyn Parameter>,
+    value_converter: Box<dyn ValueConverter>,
+    string_converter: Box<dyn StringConverter>,
+}
+
+impl OscParameter {
+    fn range(&self) -> (f32, f32) {
+        (
+            self.value_converter.linear_to_user(0.0),
+            self.value_converter.linear_to_user(1.0),
+        )
+    }
+
+    fn clamp(&self, value: f32) -> f32 {
+        let (min, max) = self.range();
+        value.clamp(min.min(max), max.max(min))
+    }
+}
+
+/// The kinds of address that are provided for each parameter.
+#[derive(Clone, Copy)]
+enum Endpoint {
+    Value,
+    Normalized,
+    Range,
+    Display,
+}
+
+/// Receives OSC messages over UDP, turning them into parameter commands and answering queries.
+///
+/// Current values are read from a [ParameterStore], which is expected to be kept up to date by
+/// the processor that receives the commands.
+pub struct OscServer {
+    socket: UdpSocket,
+    prefix: String,
+    parameters: Vec<OscParameter>,
+    store: Arc<ParameterStore>,
+    buffer: Vec<u8>,
+}
+
+impl OscServer {
+    /// Makes a server for a module's parameters that listens on the given socket.
+    ///
+    /// The prefix is prepended to each parameter's address, e.g. `/freeverb`.
+    pub fn new<Provider: ParameterProvider>(
+        socket: UdpSocket,
+        prefix: &str,
+        store: Arc<ParameterStore>,
+    ) -> Self {
+        let parameters = (0..Provider::parameter_count())
+            .filter_map(Provider::parameter)
+            .map(|parameter| OscParameter {
+                id: parameter.id(),
+                value_converter: parameter.make_value_converter(),
+                string_converter: parameter.make_string_converter(),
+                parameter,
+            })
+            .collect();
+
+        Self {
+            socket,
+            prefix: prefix.trim_end_matches('/').to_string(),
+            parameters,
+            store,
+            buffer: vec![0; rosc::decoder::MTU],
+        }
+    }
+
+    /// Makes a server for a module's parameters that listens on a newly bound socket.
+    pub fn bind<Provider: ParameterProvider>(
+        address: impl ToSocketAddrs,
+        prefix: &str,
+        store: Arc<ParameterStore>,
+    ) -> Result<Self, OscControlError> {
+        let socket = UdpSocket::bind(address)?;
+        Ok(Self::new::<Provider>(socket, prefix, store))
+    }
+
+    /// Starts a thread that runs a server for a module's parameters on the given socket.
+    ///
+    /// Commands produced by received messages are passed to `handle_command`. Errors that occur
+    /// while handling a message are reported on stderr, and the server carries on receiving.
+    pub fn spawn<Provider: ParameterProvider>(
+        socket: UdpSocket,
+        prefix: &str,
+        store: Arc<ParameterStore>,
+        mut handle_command: impl FnMut(Command) + Send + 'static,
+    ) -> thread::JoinHandle<()> {
+        let prefix = prefix.to_string();
+
+        // The server is made on its own thread, as parameters can't be sent between threads
+        thread::spawn(move || {
+            let mut server = Self::new::<Provider>(socket, &prefix, store);
+            loop {
+                if let Err(error) = server.receive(&mut handle_command) {
+                    eprintln!("Error while handling OSC message: {error}");
+                }
+            }
+        })
+    }
+
+    /// Binds a UDP port on all interfaces, and starts a server thread on it, see [OscServer::spawn].
+    ///
+    /// Commands produced by received messages are sent to `command_sender`, e.g. for an audio
+    /// thread. Commands that don't fit in the channel are dropped with a warning.
+    pub fn start<Provider: ParameterProvider>(
+        port: u16,
+        prefix: &str,
+        store: Arc<ParameterStore>,
+        command_sender: crossbeam_channel::Sender<Command>,
+    ) -> Result<thread::JoinHandle<()>, OscControlError> {
+        let socket = UdpSocket::bind(("0.0.0.0", port))?;
+
+        Ok(Self::spawn::<Provider>(
+            socket,
+            prefix,
+            store,
+            move |command| {
+                if command_sender.try_send(command).is_err() {
+                    eprintln!("OSC command queue is full");
+                }
+            },
+        ))
+    }
+
+    /// Returns the address that the server is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr, OscControlError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Waits for a packet to arrive and handles it.
+    ///
+    /// Commands produced by the packet's messages are passed to `handle_command`, and replies to
+    /// queries are sent back to the packet's sender.
+    pub fn receive(&mut self, handle_command: impl FnMut(Command)) -> Result<(), OscControlError> {
+        let (size, sender) = self.socket.recv_from(&mut self.buffer)?;
+        let (_, packet) = rosc::decoder::decode_udp(&self.buffer[..size])?;
+
+        let replies = self.handle_packet(packet, handle_command)?;
+        for reply in replies {
+            let bytes = rosc::encoder::encode(&OscPacket::Message(reply))?;
+            self.socket.send_to(&bytes, sender)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a decoded packet, returning the replies to any queries that it contained.
+    ///
+    /// The messages in a bundle are handled in order, stopping at the first invalid message.
+    pub fn handle_packet(
+        &self,
+        packet: OscPacket,
+        mut handle_command: impl FnMut(Command),
+    ) -> Result<Vec<OscMessage>, OscControlError> {
+        let mut replies = Vec::new();
+        self.handle_packet_inner(packet, &mut handle_command, &mut replies)?;
+        Ok(replies)
+    }
+
+    fn handle_packet_inner(
+        &self,
+        packet: OscPacket,
+        handle_command: &mut impl FnMut(Command),
+        replies: &mut Vec<OscMessage>,
+    ) -> Result<(), OscControlError> {
+        match packet {
+            OscPacket::Message(message) => {
+                if let Some(reply) = self.handle_message(message, handle_command)? {
+                    replies.push(reply);
+                }
+            }
+            OscPacket::Bundle(bundle) => {
+                for packet in bundle.content {
+                    self.handle_packet_inner(packet, handle_command, replies)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_message(
+        &self,
+        message: OscMessage,
+        handle_command: &mut impl FnMut(Command),
+    ) -> Result<Option<OscMessage>, OscControlError> {
+        let Some(path) = message
+            .addr
+            .strip_prefix(&self.prefix)
+            .and_then(|path| path.strip_prefix('/'))
+        else {
+            return Err(OscControlError::UnknownAddress(message.addr));
+        };
+
+        if path == "parameters" {
+            let ids = self
+                .parameters
+                .iter()
+                .map(|parameter| OscType::String(parameter.id.to_string()))
+                .collect();
+            return Ok(Some(reply(message.addr, ids)));
+        }
+
+        let (id, endpoint) = match path.split_once('/') {
+            None => (path, Endpoint::Value),
+            Some((id, "normalized")) => (id, Endpoint::Normalized),
+            Some((id, "range")) => (id, Endpoint::Range),
+            Some((id, "display")) => (id, Endpoint::Display),
+            Some(_) => return Err(OscControlError::UnknownAddress(message.addr)),
+        };
+        let Some(index) = self.parameters.iter().position(|p| p.id == id) else {
+            return Err(OscControlError::UnknownAddress(message.addr));
+        };
+        let parameter = &self.parameters[index];
+        let current_value = self
+            .store
+            .get(index)
+            .ok_or(CommandError::UnknownParameter(index))?;
+
+        match (endpoint, message.args.as_slice()) {
+            (Endpoint::Value, []) => Ok(Some(reply(
+                message.addr,
+                vec![OscType::Float(current_value)],
+            ))),
+            (Endpoint::Value, [argument]) => {
+                let value = match argument {
+                    OscType::String(text) => parameter.parameter.parse_user_value(text),
+                    argument => number_argument(argument),
+                }
+                .ok_or_else(|| OscControlError::InvalidArguments(message.addr.clone()))?;
+                handle_command(Command::SetParameter(index, parameter.clamp(value)));
+                Ok(None)
+            }
+            (Endpoint::Normalized, []) => {
+                let normalized = parameter.value_converter.user_to_linear(current_value);
+                Ok(Some(reply(message.addr, vec![OscType::Float(normalized)])))
+            }
+            (Endpoint::Normalized, [argument]) => {
+                let normalized = number_argument(argument)
+                    .ok_or_else(|| OscControlError::InvalidArguments(message.addr.clone()))?;
+                let value = parameter
+                    .value_converter
+                    .linear_to_user(normalized.clamp(0.0, 1.0));
+                handle_command(Command::SetParameter(index, value));
+                Ok(None)
+            }
+            (Endpoint::Range, []) => {
+                let (min, max) = parameter.range();
+                let default = parameter.parameter.default_user_value();
+                Ok(Some(reply(
+                    message.addr,
+                    vec![
+                        OscType::Float(min),
+                        OscType::Float(max),
+                        OscType::Float(default),
+                    ],
+                )))
+            }
+            (Endpoint::Display, []) => {
+                let display = parameter.string_converter.to_string(current_value);
+                Ok(Some(reply(message.addr, vec![OscType::String(display)])))
+            }
+            _ => Err(OscControlError::InvalidArguments(message.addr)),
+        }
+    }
+}
+
+fn reply(addr: String, args: Vec<OscType>) -> OscMessage {
+    OscMessage { addr, args }
+}
+
+fn number_argument(argument: &OscType) -> Option<f32> {
+    let value = match argument {
+        OscType::Float(value) => *value,
+        OscType::Double(value) => *value as f32,
+        OscType::Int(value) => *value as f32,
+        OscType::Long(value) => *value as f32,
+        OscType::Bool(value) => f32::from(u8::from(*value)),
+        _ => return None,
+    };
+    (!value.is_nan()).then_some(value)
+}
+
+/// An error that occurred while receiving or handling an OSC message.
+#[derive(Debug)]
+pub enum OscControlError {
+    Io(io::Error),
+    /// The packet couldn't be decoded or a reply couldn't be encoded.
+    Osc(rosc::OscError),
+    /// The message's address doesn't refer to a parameter.
+    UnknownAddress(String),
+    /// The message's arguments aren't valid for its address.
+    InvalidArguments(String),
+    Command(CommandError),
+}
+
+impl fmt::Display for OscControlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Osc(error) => write!(f, "{error}"),
+            Self::UnknownAddress(address) => write!(f, "unknown address '{address}'"),
+            Self::InvalidArguments(address) => write!(f, "invalid arguments for '{address}'"),
+            Self::Command(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for OscControlError {}
+
+impl From<io::Error> for OscControlError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<rosc::OscError> for OscControlError {
+    fn from(error: rosc::OscError) -> Self {
+        Self::Osc(error)
+    }
+}
+
+impl From<CommandError> for OscControlError {
+    fn from(error: CommandError) -> Self {
+        Self::Command(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        freeverb_module::FreeverbModule,
+        rosc::OscBundle,
+        std::{sync::mpsc, time::Duration},
+    };
+
+    const PREFIX: &str = "/freeverb";
+
+    fn index(id: &str) -> usize {
+        FreeverbModule::parameter_index(id).unwrap()
+    }
+
+    fn message(addr: &str, args: Vec<OscType>) -> OscPacket {
+        OscPacket::Message(reply(addr.to_string(), args))
+    }
+
+    // Runs a server on a loopback socket, returning a connected client socket along with the
+    // receiver of the server's commands
+    fn start_server(store: Arc<ParameterStore>) -> (UdpSocket, mpsc::Receiver<(usize, f32)>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_address = socket.local_addr().unwrap();
+        let (sender, receiver) = mpsc::channel();
+
+        OscServer::spawn::<FreeverbModule>(socket, PREFIX, store, move |command| {
+            if let Command::SetParameter(id, value) = command {
+                sender.send((id, value)).unwrap();
+            }
+        });
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.connect(server_address).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        (client, receiver)
+    }
+
+    fn send(client: &UdpSocket, packet: &OscPacket) {
+        client
+            .send(&rosc::encoder::encode(packet).unwrap())
+            .unwrap();
+    }
+
+    fn query(client: &UdpSocket, addr: &str) -> OscMessage {
+        send(client, &message(addr, vec![]));
+        let mut buffer = [0; rosc::decoder::MTU];
+        let size = client.recv(&mut buffer).unwrap();
+        match rosc::decoder::decode_udp(&buffer[..size]).unwrap().1 {
+            OscPacket::Message(message) => message,
+            OscPacket::Bundle(_) => panic!("Unexpected bundle"),
+        }
+    }
+
+    #[test]
+    fn start_on_a_port_in_use() {
+        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let store = Arc::new(ParameterStore::with_defaults::<FreeverbModule>());
+        let (sender, _receiver) = crossbeam_channel::bounded(1);
+
+        let result = OscServer::start::<FreeverbModule>(port, PREFIX, store, sender);
+        assert!(matches!(result, Err(OscControlError::Io(_))));
+    }
+
+    #[test]
+    fn set_parameters_over_loopback() {
+        let store = Arc::new(ParameterStore::with_defaults::<FreeverbModule>());
+        let (client, commands) = start_server(store);
+        let timeout = Duration::from_secs(5);
+
+        send(
+            &client,
+            &message("/freeverb/room_size", vec![OscType::Float(0.8)]),
+        );
+        assert_eq!(
+            commands.recv_timeout(timeout),
+            Ok((index("room_size"), 0.8))
+        );
+
+        send(
+            &client,
+            &message("/freeverb/freeze", vec![OscType::String("on".into())]),
+        );
+        assert_eq!(commands.recv_timeout(timeout), Ok((index("freeze"), 1.0)));
+
+        // Out of range values are clamped
+        send(
+            &client,
+            &message("/freeverb/gate_hold", vec![OscType::Int(5000)]),
+        );
+        assert_eq!(
+            commands.recv_timeout(timeout),
+            Ok((index("gate_hold"), 1000.0))
+        );
+
+        send(
+            &client,
+            &message(
+                "/freeverb/dampening/normalized",
+                vec![OscType::Double(0.25)],
+            ),
+        );
+        assert_eq!(
+            commands.recv_timeout(timeout),
+            Ok((index("dampening"), 0.25))
+        );
+    }
+
+    #[test]
+    fn queries_over_loopback() {
+        let store = Arc::new(ParameterStore::with_defaults::<FreeverbModule>());
+        store.set(index("room_size"), 0.75).unwrap();
+        let (client, _commands) = start_server(store);
+
+        let value = query(&client, "/freeverb/room_size");
+        assert_eq!(value.addr, "/freeverb/room_size");
+        assert_eq!(value.args, [OscType::Float(0.75)]);
+
+        let range = query(&client, "/freeverb/gate_threshold/range");
+        assert_eq!(range.addr, "/freeverb/gate_threshold/range");
+        assert_eq!(
+            range.args,
+            [
+                OscType::Float(-60.0),
+                OscType::Float(0.0),
+                OscType::Float(-40.0)
+            ]
+        );
+
+        let display = query(&client, "/freeverb/room_size/display");
+        assert_eq!(display.args, [OscType::String("75 %".into())]);
+
+        let parameters = query(&client, "/freeverb/parameters");
+        assert_eq!(parameters.args.len(), FreeverbModule::parameter_count());
+        assert_eq!(parameters.args[index("wet")], OscType::String("wet".into()));
+    }
+
+    #[test]
+    fn bundles_are_handled_in_order() {
+        let store = Arc::new(ParameterStore::with_defaults::<FreeverbModule>());
+        let server = OscServer::bind::<FreeverbModule>("127.0.0.1:0", PREFIX, store).unwrap();
+
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: (0, 1).into(),
+            content: vec![
+                message("/freeverb/width", vec![OscType::Float(0.1)]),
+                message("/freeverb/width", vec![OscType::Float(0.2)]),
+                message("/freeverb/width", vec![]),
+            ],
+        });
+
+        let mut commands = Vec::new();
+        let replies = server
+            .handle_packet(bundle, |command| {
+                if let Command::SetParameter(id, value) = command {
+                    commands.push((id, value));
+                }
+            })
+            .unwrap();
+
+        let width = index("width");
+        assert_eq!(commands, [(width, 0.1), (width, 0.2)]);
+        // Values are read from the store, which hasn't been updated by a processor
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0].args, [OscType::Float(0.5)]);
+    }
+
+    #[test]
+    fn invalid_messages() {
+        let store = Arc::new(ParameterStore::with_defaults::<FreeverbModule>());
+        let server = OscServer::bind::<FreeverbModule>("127.0.0.1:0", PREFIX, store).unwrap();
+        let handle = |packet| server.handle_packet(packet, |_| panic!("Unexpected command"));
+
+        assert!(matches!(
+            handle(message("/other/room_size", vec![OscType::Float(0.5)])),
+            Err(OscControlError::UnknownAddress(_))
+        ));
+        assert!(matches!(
+            handle(message("/freeverb/size", vec![OscType::Float(0.5)])),
+            Err(OscControlError::UnknownAddress(_))
+        ));
+        assert!(matches!(
+            handle(message("/freeverb/room_size/volume", vec![])),
+            Err(OscControlError::UnknownAddress(_))
+        ));
+        assert!(matches!(
+            handle(message(
+                "/freeverb/room_size",
+                vec![OscType::String("huge".into())]
+            )),
+            Err(OscControlError::InvalidArguments(_))
+        ));
+        assert!(matches!(
+            handle(message("/freeverb/room_size/range", vec![OscType::Int(1)])),
+            Err(OscControlError::InvalidArguments(_))
+        ));
+    }
+}
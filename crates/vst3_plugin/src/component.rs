@@ -0,0 +1,662 @@
+use {
+    crate::{
+        Vst3Definition,
+        parameters::Vst3Parameter,
+        strings::{copy_wstring, read_wstring},
+    },
+    audio_module::{
+        AudioModule, AudioProcessor, Command, CommandHandler, HostParameter, ParameterStore, Preset,
+    },
+    std::{
+        ffi::{c_char, c_void},
+        marker::PhantomData,
+        ptr, slice,
+        sync::{
+            Mutex,
+            atomic::{AtomicU32, Ordering},
+        },
+    },
+    vst3::{
+        Class, ComPtr, ComRef,
+        Steinberg::{
+            IBStream, IBStreamTrait, IPlugView, IPluginBaseTrait, TBool, TUID, Vst::*,
+            kInvalidArgument, kNotImplemented, kResultFalse, kResultOk, kResultTrue, tresult,
+        },
+    },
+};
+
+// The number of channels in the plugin's audio buses, matching the processors' interleaved stereo
+const PROCESSOR_CHANNELS: usize = 2;
+
+// The size of the chunks that state is read in
+const STATE_CHUNK_SIZE: usize = 1024;
+
+/// A VST3 component that wraps the [AudioModule] of a [Vst3Definition].
+///
+/// The component acts as both the plugin's audio processor and its edit controller, so that the
+/// parameter values can be shared via a [ParameterStore]. Changes made by the host via the edit
+/// controller are pulled by the processor, and automation that arrives while processing is
+/// stored so that the host can query it.
+pub struct ModuleComponent<Definition: Vst3Definition> {
+    store: ParameterStore,
+    parameters: Vec<HostParameter>,
+    setup: Mutex<ProcessSetup>,
+    processing: Mutex<Option<ActiveProcessor<Definition>>>,
+    latency: AtomicU32,
+    component_handler: Mutex<Option<ComPtr<IComponentHandler>>>,
+    _definition: PhantomData<Definition>,
+}
+
+// The processor along with its buffers, which exists while the component is active
+struct ActiveProcessor<Definition: Vst3Definition> {
+    processor: <Definition::Module as AudioModule>::Processor,
+    // Interleaved buffers for the processor, allocated for the host's maximum block size
+    input: Vec<f32>,
+    output: Vec<f32>,
+    // Flags for the parameters that have automation points in the current block, indexed by
+    // parameter index
+    automated: Vec<bool>,
+}
+
+impl<Definition: Vst3Definition> Class for ModuleComponent<Definition> {
+    type Interfaces = (
+        IComponent,
+        IAudioProcessor,
+        IProcessContextRequirements,
+        IEditController,
+    );
+}
+
+impl<Definition: Vst3Definition> ModuleComponent<Definition> {
+    pub fn new() -> Self {
+        Self {
+            store: ParameterStore::with_defaults::<Definition::Module>(),
+            parameters: HostParameter::all::<Definition::Module>(),
+            setup: Mutex::new(ProcessSetup {
+                processMode: ProcessModes_::kRealtime as i32,
+                symbolicSampleSize: SymbolicSampleSizes_::kSample32 as i32,
+                maxSamplesPerBlock: 1024,
+                sampleRate: 44100.0,
+            }),
+            processing: Mutex::new(None),
+            latency: AtomicU32::new(0),
+            component_handler: Mutex::new(None),
+            _definition: PhantomData,
+        }
+    }
+
+    fn parameter(&self, id: ParamID) -> Option<(usize, &HostParameter)> {
+        let index = id as usize;
+        self.parameters
+            .get(index)
+            .map(|parameter| (index, parameter))
+    }
+
+    // Applies the parameter changes that arrived with a block, with each point being applied at
+    // its sample offset, and stores the latest values so that the host can query them
+    fn handle_parameter_changes(
+        &self,
+        processor: &mut impl CommandHandler,
+        changes: ComRef<'_, IParameterChanges>,
+    ) {
+        unsafe {
+            for change_index in 0..changes.getParameterCount() {
+                let Some(queue) = ComRef::from_raw(changes.getParameterData(change_index)) else {
+                    continue;
+                };
+                let Some((index, parameter)) = self.parameter(queue.getParameterId()) else {
+                    continue;
+                };
+
+                for point in 0..queue.getPointCount() {
+                    let mut offset = 0;
+                    let mut normalized = 0.0;
+                    if queue.getPoint(point, &mut offset, &mut normalized) != kResultOk {
+                        continue;
+                    }
+
+                    let value = parameter.normalized_to_user(normalized);
+                    // The index has been checked, so the commands can't fail
+                    let _ = processor.handle_command(Command::SetParameterAt {
+                        id: index,
+                        value,
+                        frame_offset: offset.max(0) as usize,
+                    });
+                    let _ = self.store.set_from_audio(index, value);
+                }
+            }
+        }
+    }
+
+    fn save_state(&self) -> String {
+        Preset::from_values::<Definition::Module>(Definition::NAME, &self.store.snapshot())
+            .to_text()
+    }
+
+    fn load_state(&self, text: &str) -> bool {
+        let Ok(preset) = Preset::from_text::<Definition::Module>(text) else {
+            return false;
+        };
+
+        for (index, value) in preset
+            .user_values::<Definition::Module>()
+            .into_iter()
+            .enumerate()
+        {
+            let _ = self.store.set(index, value);
+        }
+
+        // The host is asked to refresh its view of the parameters
+        if let Some(handler) = self.component_handler.lock().unwrap().as_ref() {
+            unsafe {
+                handler.restartComponent(RestartFlags_::kParamValuesChanged);
+            }
+        }
+
+        true
+    }
+}
+
+impl<Definition: Vst3Definition> Default for ModuleComponent<Definition> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Definition: Vst3Definition> ActiveProcessor<Definition> {
+    fn new(store: &ParameterStore, setup: &ProcessSetup) -> Self {
+        let mut processor = Definition::Module::create_processor(setup.sampleRate as usize);
+        let values = store.snapshot();
+        for (index, &value) in values.iter().enumerate() {
+            let _ = processor.handle_command(Command::SetParameter(index, value));
+        }
+
+        let buffer_size = setup.maxSamplesPerBlock.max(0) as usize * PROCESSOR_CHANNELS;
+        Self {
+            processor,
+            input: vec![0.0; buffer_size],
+            output: vec![0.0; buffer_size],
+            automated: vec![false; values.len()],
+        }
+    }
+
+    // Flags the parameters that have automation points in the block's parameter changes
+    fn find_automated_parameters(&mut self, changes: Option<ComRef<'_, IParameterChanges>>) {
+        self.automated.fill(false);
+        let Some(changes) = changes else {
+            return;
+        };
+
+        unsafe {
+            for change_index in 0..changes.getParameterCount() {
+                if let Some(queue) = ComRef::from_raw(changes.getParameterData(change_index))
+                    && queue.getPointCount() > 0
+                    && let Some(automated) = self.automated.get_mut(queue.getParameterId() as usize)
+                {
+                    *automated = true;
+                }
+            }
+        }
+    }
+
+    // Changes made via the edit controller are passed on to the processor
+    //
+    // Hosts also send automation to the edit controller, so changes to parameters that are
+    // automated in the current block are skipped, to avoid applying them ahead of their offsets
+    fn pull_parameter_changes(&mut self, store: &ParameterStore) {
+        let processor = &mut self.processor;
+        let automated = &self.automated;
+        store.pull_audio_changes(|index, value| {
+            if !automated.get(index).copied().unwrap_or_default() {
+                let _ = processor.handle_command(Command::SetParameter(index, value));
+            }
+        });
+    }
+
+    // Processes the main buses, returning false if the buffers can't be processed
+    unsafe fn process(&mut self, data: &ProcessData) -> bool {
+        let frames = data.numSamples.max(0) as usize;
+        let samples = frames * PROCESSOR_CHANNELS;
+        if samples > self.input.len() {
+            return false;
+        }
+
+        let (inputs, outputs) = unsafe {
+            (
+                main_bus(data.inputs, data.numInputs),
+                main_bus(data.outputs, data.numOutputs),
+            )
+        };
+
+        // Mono input is copied to both of the processor's channels
+        self.input[..samples].fill(0.0);
+        if !inputs.is_empty() {
+            for channel in 0..PROCESSOR_CHANNELS {
+                let source = inputs[channel.min(inputs.len() - 1)];
+                if source.is_null() {
+                    continue;
+                }
+                let source = unsafe { slice::from_raw_parts(source, frames) };
+                for (frame, sample) in source.iter().enumerate() {
+                    self.input[frame * PROCESSOR_CHANNELS + channel] = *sample;
+                }
+            }
+        }
+
+        self.processor.process(
+            &self.input[..samples],
+            &mut self.output[..samples],
+            PROCESSOR_CHANNELS as u32,
+        );
+
+        // The input has already been copied, so writing to buffers that are shared with the input
+        // is fine
+        for (channel, output) in outputs.iter().enumerate() {
+            if output.is_null() {
+                continue;
+            }
+            let output = unsafe { slice::from_raw_parts_mut(*output, frames) };
+            if channel < PROCESSOR_CHANNELS {
+                for (frame, sample) in output.iter_mut().enumerate() {
+                    *sample = self.output[frame * PROCESSOR_CHANNELS + channel];
+                }
+            } else {
+                output.fill(0.0);
+            }
+        }
+
+        true
+    }
+}
+
+// Returns the 32-bit channel buffers of the first bus, if there is one
+unsafe fn main_bus<'a>(buses: *mut AudioBusBuffers, count: i32) -> &'a [*mut f32] {
+    if buses.is_null() || count < 1 {
+        return &[];
+    }
+
+    unsafe {
+        let bus = &*buses;
+        let channels = bus.__field0.channelBuffers32;
+        if channels.is_null() || bus.numChannels < 1 {
+            &[]
+        } else {
+            slice::from_raw_parts(channels, bus.numChannels as usize)
+        }
+    }
+}
+
+impl<Definition: Vst3Definition> IPluginBaseTrait for ModuleComponent<Definition> {
+    unsafe fn initialize(&self, _context: *mut vst3::Steinberg::FUnknown) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn terminate(&self) -> tresult {
+        *self.component_handler.lock().unwrap() = None;
+        kResultOk
+    }
+}
+
+impl<Definition: Vst3Definition> IComponentTrait for ModuleComponent<Definition> {
+    unsafe fn getControllerClassId(&self, _class_id: *mut TUID) -> tresult {
+        // The component is its own edit controller
+        kNotImplemented
+    }
+
+    unsafe fn setIoMode(&self, _mode: IoMode) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn getBusCount(&self, media_type: MediaType, _direction: BusDirection) -> i32 {
+        match media_type as MediaTypes {
+            MediaTypes_::kAudio => 1,
+            _ => 0,
+        }
+    }
+
+    unsafe fn getBusInfo(
+        &self,
+        media_type: MediaType,
+        direction: BusDirection,
+        index: i32,
+        bus: *mut BusInfo,
+    ) -> tresult {
+        if media_type as MediaTypes != MediaTypes_::kAudio || index != 0 || bus.is_null() {
+            return kInvalidArgument;
+        }
+
+        let bus = unsafe { &mut *bus };
+        bus.mediaType = media_type;
+        bus.direction = direction;
+        bus.channelCount = PROCESSOR_CHANNELS as i32;
+        let name = match direction as BusDirections {
+            BusDirections_::kInput => "Main In",
+            _ => "Main Out",
+        };
+        copy_wstring(name, &mut bus.name);
+        bus.busType = BusTypes_::kMain as BusType;
+        bus.flags = BusInfo_::BusFlags_::kDefaultActive;
+
+        kResultOk
+    }
+
+    unsafe fn getRoutingInfo(
+        &self,
+        _input_info: *mut RoutingInfo,
+        _output_info: *mut RoutingInfo,
+    ) -> tresult {
+        kNotImplemented
+    }
+
+    unsafe fn activateBus(
+        &self,
+        _media_type: MediaType,
+        _direction: BusDirection,
+        _index: i32,
+        _state: TBool,
+    ) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn setActive(&self, state: TBool) -> tresult {
+        let mut processing = self.processing.lock().unwrap();
+        if state == 0 {
+            *processing = None;
+        } else {
+            let active =
+                ActiveProcessor::<Definition>::new(&self.store, &self.setup.lock().unwrap());
+            self.latency
+                .store(active.processor.latency_samples() as u32, Ordering::Relaxed);
+            *processing = Some(active);
+        }
+        kResultOk
+    }
+
+    unsafe fn setState(&self, state: *mut IBStream) -> tresult {
+        let Some(stream) = (unsafe { ComRef::from_raw(state) }) else {
+            return kInvalidArgument;
+        };
+
+        let mut bytes = Vec::new();
+        let mut chunk = [0u8; STATE_CHUNK_SIZE];
+        loop {
+            let mut read = 0;
+            let result = unsafe {
+                stream.read(
+                    chunk.as_mut_ptr().cast::<c_void>(),
+                    chunk.len() as i32,
+                    &mut read,
+                )
+            };
+            if result != kResultOk || read <= 0 {
+                break;
+            }
+            bytes.extend_from_slice(&chunk[..read as usize]);
+        }
+
+        match String::from_utf8(bytes) {
+            Ok(text) if self.load_state(&text) => kResultOk,
+            _ => kResultFalse,
+        }
+    }
+
+    unsafe fn getState(&self, state: *mut IBStream) -> tresult {
+        let Some(stream) = (unsafe { ComRef::from_raw(state) }) else {
+            return kInvalidArgument;
+        };
+
+        let text = self.save_state();
+        let mut written = 0;
+        let result = unsafe {
+            stream.write(
+                text.as_ptr().cast_mut().cast::<c_void>(),
+                text.len() as i32,
+                &mut written,
+            )
+        };
+        if result == kResultOk && written == text.len() as i32 {
+            kResultOk
+        } else {
+            kResultFalse
+        }
+    }
+}
+
+impl<Definition: Vst3Definition> IAudioProcessorTrait for ModuleComponent<Definition> {
+    unsafe fn setBusArrangements(
+        &self,
+        inputs: *mut SpeakerArrangement,
+        input_count: i32,
+        outputs: *mut SpeakerArrangement,
+        output_count: i32,
+    ) -> tresult {
+        if input_count != 1 || output_count != 1 || inputs.is_null() || outputs.is_null() {
+            return kResultFalse;
+        }
+
+        if unsafe { *inputs == SpeakerArr::kStereo && *outputs == SpeakerArr::kStereo } {
+            kResultTrue
+        } else {
+            kResultFalse
+        }
+    }
+
+    unsafe fn getBusArrangement(
+        &self,
+        _direction: BusDirection,
+        index: i32,
+        arrangement: *mut SpeakerArrangement,
+    ) -> tresult {
+        if index != 0 || arrangement.is_null() {
+            return kInvalidArgument;
+        }
+        unsafe { *arrangement = SpeakerArr::kStereo };
+        kResultOk
+    }
+
+    unsafe fn canProcessSampleSize(&self, symbolic_sample_size: i32) -> tresult {
+        match symbolic_sample_size as SymbolicSampleSizes {
+            SymbolicSampleSizes_::kSample32 => kResultTrue,
+            _ => kResultFalse,
+        }
+    }
+
+    unsafe fn getLatencySamples(&self) -> u32 {
+        self.latency.load(Ordering::Relaxed)
+    }
+
+    unsafe fn setupProcessing(&self, setup: *mut ProcessSetup) -> tresult {
+        if setup.is_null() {
+            return kInvalidArgument;
+        }
+        let setup = unsafe { *setup };
+        if setup.symbolicSampleSize != SymbolicSampleSizes_::kSample32 as i32 {
+            return kResultFalse;
+        }
+        *self.setup.lock().unwrap() = setup;
+        kResultOk
+    }
+
+    unsafe fn setProcessing(&self, _state: TBool) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn process(&self, data: *mut ProcessData) -> tresult {
+        let Some(data) = (unsafe { data.as_ref() }) else {
+            return kInvalidArgument;
+        };
+
+        // The lock is only contended while the host is activating or deactivating the component
+        let Ok(mut processing) = self.processing.try_lock() else {
+            return kResultFalse;
+        };
+        let Some(active) = processing.as_mut() else {
+            return kResultFalse;
+        };
+
+        let changes = unsafe { ComRef::from_raw(data.inputParameterChanges) };
+        active.find_automated_parameters(changes);
+        active.pull_parameter_changes(&self.store);
+        if let Some(changes) = changes {
+            self.handle_parameter_changes(&mut active.processor, changes);
+        }
+
+        // Blocks without audio are used by some hosts to deliver parameter changes
+        if data.numSamples == 0 {
+            return kResultOk;
+        }
+
+        if data.symbolicSampleSize != SymbolicSampleSizes_::kSample32 as i32 {
+            return kInvalidArgument;
+        }
+
+        if unsafe { active.process(data) } {
+            kResultOk
+        } else {
+            kInvalidArgument
+        }
+    }
+
+    unsafe fn getTailSamples(&self) -> u32 {
+        let processing = self.processing.lock().unwrap();
+        match processing
+            .as_ref()
+            .map(|active| active.processor.tail_samples())
+        {
+            Some(Some(samples)) => u32::try_from(samples).unwrap_or(kInfiniteTail),
+            Some(None) => kInfiniteTail,
+            None => 0,
+        }
+    }
+}
+
+impl<Definition: Vst3Definition> IProcessContextRequirementsTrait for ModuleComponent<Definition> {
+    unsafe fn getProcessContextRequirements(&self) -> u32 {
+        0
+    }
+}
+
+impl<Definition: Vst3Definition> IEditControllerTrait for ModuleComponent<Definition> {
+    unsafe fn setComponentState(&self, _state: *mut IBStream) -> tresult {
+        // The component's state is shared with the controller, so there's nothing to load
+        kResultOk
+    }
+
+    unsafe fn setState(&self, _state: *mut IBStream) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn getState(&self, _state: *mut IBStream) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn getParameterCount(&self) -> i32 {
+        self.parameters.len() as i32
+    }
+
+    unsafe fn getParameterInfo(&self, index: i32, info: *mut ParameterInfo) -> tresult {
+        let Some((index, parameter)) = self.parameter(index as ParamID) else {
+            return kInvalidArgument;
+        };
+        let Some(info) = (unsafe { info.as_mut() }) else {
+            return kInvalidArgument;
+        };
+
+        info.id = index as ParamID;
+        copy_wstring(parameter.name(), &mut info.title);
+        copy_wstring(parameter.name(), &mut info.shortTitle);
+        copy_wstring(parameter.unit(), &mut info.units);
+        info.stepCount = parameter.step_count();
+        info.defaultNormalizedValue = parameter.default_normalized_value();
+        info.unitId = kRootUnitId;
+        info.flags = ParameterInfo_::ParameterFlags_::kCanAutomate;
+        if parameter.is_list() {
+            info.flags |= ParameterInfo_::ParameterFlags_::kIsList;
+        }
+
+        kResultOk
+    }
+
+    unsafe fn getParamStringByValue(
+        &self,
+        id: ParamID,
+        normalized: ParamValue,
+        string: *mut String128,
+    ) -> tresult {
+        let Some((_, parameter)) = self.parameter(id) else {
+            return kInvalidArgument;
+        };
+        let Some(string) = (unsafe { string.as_mut() }) else {
+            return kInvalidArgument;
+        };
+
+        copy_wstring(&parameter.normalized_to_string(normalized), string);
+        kResultOk
+    }
+
+    unsafe fn getParamValueByString(
+        &self,
+        id: ParamID,
+        string: *mut TChar,
+        normalized: *mut ParamValue,
+    ) -> tresult {
+        let Some((_, parameter)) = self.parameter(id) else {
+            return kInvalidArgument;
+        };
+        if normalized.is_null() {
+            return kInvalidArgument;
+        }
+
+        match unsafe { read_wstring(string) }.and_then(|text| parameter.string_to_normalized(&text))
+        {
+            Some(value) => {
+                unsafe { *normalized = value };
+                kResultOk
+            }
+            None => kResultFalse,
+        }
+    }
+
+    unsafe fn normalizedParamToPlain(&self, id: ParamID, normalized: ParamValue) -> ParamValue {
+        self.parameter(id).map_or(0.0, |(_, parameter)| {
+            parameter.normalized_to_user(normalized) as f64
+        })
+    }
+
+    unsafe fn plainParamToNormalized(&self, id: ParamID, plain: ParamValue) -> ParamValue {
+        self.parameter(id).map_or(0.0, |(_, parameter)| {
+            parameter.user_to_normalized(plain as f32)
+        })
+    }
+
+    unsafe fn getParamNormalized(&self, id: ParamID) -> ParamValue {
+        self.parameter(id).map_or(0.0, |(index, parameter)| {
+            self.store
+                .get(index)
+                .map_or(0.0, |value| parameter.user_to_normalized(value))
+        })
+    }
+
+    unsafe fn setParamNormalized(&self, id: ParamID, normalized: ParamValue) -> tresult {
+        let Some((index, parameter)) = self.parameter(id) else {
+            return kInvalidArgument;
+        };
+
+        // The value is stored so that it's picked up by the processor, which is how changes are
+        // passed on while the host isn't processing
+        let _ = self
+            .store
+            .set(index, parameter.normalized_to_user(normalized));
+        kResultOk
+    }
+
+    unsafe fn setComponentHandler(&self, handler: *mut IComponentHandler) -> tresult {
+        *self.component_handler.lock().unwrap() =
+            unsafe { ComRef::from_raw(handler) }.map(|handler| handler.to_com_ptr());
+        kResultOk
+    }
+
+    unsafe fn createView(&self, _name: *const c_char) -> *mut IPlugView {
+        ptr::null_mut()
+    }
+}
@@ -0,0 +1,130 @@
+use {
+    crate::{Vst3Definition, component::ModuleComponent, strings::copy_cstring},
+    std::{ffi::c_void, marker::PhantomData, ptr},
+    vst3::{
+        Class, ComWrapper,
+        Steinberg::{
+            FIDString, FUnknown, IPluginFactory2, IPluginFactory2Trait, IPluginFactoryTrait,
+            PClassInfo, PClassInfo_, PClassInfo2, PFactoryInfo, PFactoryInfo_, TUID,
+            kInvalidArgument, kResultFalse, kResultOk, tresult,
+        },
+    },
+};
+
+// The category that hosts use to identify audio processor components
+const AUDIO_MODULE_CATEGORY: &str = "Audio Module Class";
+
+// The SDK version that the plugin's interfaces correspond to
+const SDK_VERSION: &str = "VST 3.7";
+
+/// The plugin factory that's returned to the host by `GetPluginFactory`.
+///
+/// The factory provides a single class, a [ModuleComponent] for the [Vst3Definition]'s module.
+pub struct ModuleFactory<Definition: Vst3Definition> {
+    _definition: PhantomData<Definition>,
+}
+
+impl<Definition: Vst3Definition> ModuleFactory<Definition> {
+    pub fn new() -> Self {
+        Self {
+            _definition: PhantomData,
+        }
+    }
+}
+
+impl<Definition: Vst3Definition> Default for ModuleFactory<Definition> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Definition: Vst3Definition> Class for ModuleFactory<Definition> {
+    type Interfaces = (IPluginFactory2,);
+}
+
+impl<Definition: Vst3Definition> IPluginFactoryTrait for ModuleFactory<Definition> {
+    unsafe fn getFactoryInfo(&self, info: *mut PFactoryInfo) -> tresult {
+        let Some(info) = (unsafe { info.as_mut() }) else {
+            return kInvalidArgument;
+        };
+
+        copy_cstring(Definition::VENDOR, &mut info.vendor);
+        copy_cstring(Definition::URL, &mut info.url);
+        copy_cstring(Definition::EMAIL, &mut info.email);
+        info.flags = PFactoryInfo_::FactoryFlags_::kUnicode as i32;
+
+        kResultOk
+    }
+
+    unsafe fn countClasses(&self) -> i32 {
+        1
+    }
+
+    unsafe fn getClassInfo(&self, index: i32, info: *mut PClassInfo) -> tresult {
+        let Some(info) = (unsafe { info.as_mut() }) else {
+            return kInvalidArgument;
+        };
+        if index != 0 {
+            return kInvalidArgument;
+        }
+
+        info.cid = Definition::CLASS_ID;
+        info.cardinality = PClassInfo_::ClassCardinality_::kManyInstances as i32;
+        copy_cstring(AUDIO_MODULE_CATEGORY, &mut info.category);
+        copy_cstring(Definition::NAME, &mut info.name);
+
+        kResultOk
+    }
+
+    unsafe fn createInstance(
+        &self,
+        class_id: FIDString,
+        interface_id: FIDString,
+        instance: *mut *mut c_void,
+    ) -> tresult {
+        if class_id.is_null() || interface_id.is_null() || instance.is_null() {
+            return kInvalidArgument;
+        }
+
+        unsafe { *instance = ptr::null_mut() };
+        if unsafe { *class_id.cast::<TUID>() } != Definition::CLASS_ID {
+            return kResultFalse;
+        }
+
+        let Some(component) =
+            ComWrapper::new(ModuleComponent::<Definition>::new()).to_com_ptr::<FUnknown>()
+        else {
+            return kResultFalse;
+        };
+
+        // The host's reference is added by the query, the wrapper's reference is then released
+        // when the component goes out of scope
+        let component = component.as_ptr();
+        unsafe {
+            ((*(*component).vtbl).queryInterface)(component, interface_id.cast::<TUID>(), instance)
+        }
+    }
+}
+
+impl<Definition: Vst3Definition> IPluginFactory2Trait for ModuleFactory<Definition> {
+    unsafe fn getClassInfo2(&self, index: i32, info: *mut PClassInfo2) -> tresult {
+        let Some(info) = (unsafe { info.as_mut() }) else {
+            return kInvalidArgument;
+        };
+        if index != 0 {
+            return kInvalidArgument;
+        }
+
+        info.cid = Definition::CLASS_ID;
+        info.cardinality = PClassInfo_::ClassCardinality_::kManyInstances as i32;
+        copy_cstring(AUDIO_MODULE_CATEGORY, &mut info.category);
+        copy_cstring(Definition::NAME, &mut info.name);
+        info.classFlags = 0;
+        copy_cstring(Definition::SUBCATEGORIES, &mut info.subCategories);
+        copy_cstring(Definition::VENDOR, &mut info.vendor);
+        copy_cstring(Definition::VERSION, &mut info.version);
+        copy_cstring(SDK_VERSION, &mut info.sdkVersion);
+
+        kResultOk
+    }
+}
@@ -0,0 +1,519 @@
+//! A generic [VST3](https://steinbergmedia.github.io/vst3_dev_portal) plugin wrapper for any
+//! [AudioModule].
+//!
+//! A plugin is described by implementing [Vst3Definition], and then exported from a `cdylib`
+//! crate with [export_vst3_plugin]:
+//!
+//! ```ignore
+//! struct Freeverb;
+//!
+//! impl vst3_plugin::Vst3Definition for Freeverb {
+//!     type Module = freeverb_module::FreeverbModule;
+//!
+//!     const CLASS_ID: vst3_plugin::TUID =
+//!         vst3_plugin::uid(0x5A1E3C0B, 0x6F2D4B87, 0x9E41C3D8, 0x2B7F6A10);
+//!     const NAME: &'static str = "Freeverb";
+//!     const VENDOR: &'static str = "freeverb-rs";
+//!     const VERSION: &'static str = "0.1.0";
+//!     const SUBCATEGORIES: &'static str = "Fx|Reverb";
+//! }
+//!
+//! vst3_plugin::export_vst3_plugin!(Freeverb);
+//! ```
+//!
+//! The plugin is a single component that acts as both the audio processor and the edit
+//! controller, with the module's parameters exposed to the host via their value and string
+//! converters. As with `clap_plugin`, the plugin's state is saved in the
+//! [Preset](audio_module::Preset) text format, and the plugin has a single stereo input and
+//! output.
+
+mod component;
+mod factory;
+mod parameters;
+mod strings;
+
+pub use {
+    component::ModuleComponent,
+    factory::ModuleFactory,
+    vst3::{Steinberg::TUID, uid},
+};
+
+#[doc(hidden)]
+pub use vst3;
+
+use {
+    audio_module::AudioModule,
+    vst3::{ComWrapper, Steinberg::IPluginFactory},
+};
+
+/// Describes a plugin that wraps an [AudioModule].
+pub trait Vst3Definition: 'static {
+    type Module: AudioModule;
+
+    /// A unique identifier for the plugin's component, which can be made with [uid].
+    ///
+    /// Hosts use the id to find the plugin when loading a project, so it shouldn't be changed once
+    /// the plugin has been released.
+    const CLASS_ID: TUID;
+    const NAME: &'static str;
+    const VENDOR: &'static str;
+    const VERSION: &'static str;
+    const URL: &'static str = "";
+    const EMAIL: &'static str = "";
+    /// The categories that hosts use to sort the plugin, separated by `|`, e.g. `Fx|Reverb`.
+    const SUBCATEGORIES: &'static str;
+}
+
+/// Makes the plugin factory for a [Vst3Definition], which is returned by `GetPluginFactory`.
+///
+/// The caller takes ownership of the factory's reference.
+pub fn plugin_factory<Definition: Vst3Definition>() -> *mut IPluginFactory {
+    ComWrapper::new(ModuleFactory::<Definition>::new())
+        .to_com_ptr::<IPluginFactory>()
+        .map_or(std::ptr::null_mut(), |factory| factory.into_raw())
+}
+
+/// Exports the `GetPluginFactory` symbol for a [Vst3Definition]'s plugin, along with the
+/// platform's module entry and exit functions.
+///
+/// This should be used once in a `cdylib` crate, the resulting library can then be placed in a
+/// `.vst3` bundle and installed in a VST3 plugin folder.
+#[macro_export]
+macro_rules! export_vst3_plugin {
+    ($definition:ty) => {
+        #[unsafe(no_mangle)]
+        extern "system" fn GetPluginFactory() -> *mut $crate::vst3::Steinberg::IPluginFactory {
+            $crate::plugin_factory::<$definition>()
+        }
+
+        #[cfg(target_os = "linux")]
+        #[unsafe(no_mangle)]
+        extern "system" fn ModuleEntry(_library_handle: *mut ::std::ffi::c_void) -> bool {
+            true
+        }
+
+        #[cfg(target_os = "linux")]
+        #[unsafe(no_mangle)]
+        extern "system" fn ModuleExit() -> bool {
+            true
+        }
+
+        #[cfg(target_os = "macos")]
+        #[unsafe(no_mangle)]
+        extern "system" fn bundleEntry(_bundle_ref: *mut ::std::ffi::c_void) -> bool {
+            true
+        }
+
+        #[cfg(target_os = "macos")]
+        #[unsafe(no_mangle)]
+        extern "system" fn bundleExit() -> bool {
+            true
+        }
+
+        #[cfg(target_os = "windows")]
+        #[unsafe(no_mangle)]
+        extern "system" fn InitDll() -> bool {
+            true
+        }
+
+        #[cfg(target_os = "windows")]
+        #[unsafe(no_mangle)]
+        extern "system" fn ExitDll() -> bool {
+            true
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        audio_module::ParameterProvider,
+        freeverb_module::FreeverbModule,
+        std::{
+            cell::{Cell, RefCell},
+            ffi::{CStr, c_void},
+            ptr,
+        },
+        vst3::{
+            Class, ComPtr, Interface,
+            Steinberg::{
+                IBStream, IBStreamTrait, IPluginBaseTrait, IPluginFactory2, IPluginFactory2Trait,
+                IPluginFactoryTrait, PClassInfo2, Vst::*, kResultFalse, kResultOk, kResultTrue,
+                tresult,
+            },
+        },
+    };
+
+    struct TestDefinition;
+
+    impl Vst3Definition for TestDefinition {
+        type Module = FreeverbModule;
+
+        const CLASS_ID: TUID = uid(0x1F0E2D3C, 0x4B5A6978, 0x8796A5B4, 0xC3D2E1F0);
+        const NAME: &'static str = "Freeverb Test";
+        const VENDOR: &'static str = "freeverb-rs";
+        const VERSION: &'static str = "0.1.0";
+        const SUBCATEGORIES: &'static str = "Fx|Reverb";
+    }
+
+    const BLOCK_FRAMES: usize = 64;
+
+    // A stream that reads and writes an in-memory buffer
+    #[derive(Default)]
+    struct MemoryStream {
+        data: RefCell<Vec<u8>>,
+        position: Cell<usize>,
+    }
+
+    impl Class for MemoryStream {
+        type Interfaces = (IBStream,);
+    }
+
+    impl IBStreamTrait for MemoryStream {
+        unsafe fn read(&self, buffer: *mut c_void, bytes: i32, bytes_read: *mut i32) -> tresult {
+            let data = self.data.borrow();
+            let start = self.position.get().min(data.len());
+            let count = (bytes.max(0) as usize).min(data.len() - start);
+            unsafe {
+                ptr::copy_nonoverlapping(data[start..].as_ptr(), buffer.cast(), count);
+                if !bytes_read.is_null() {
+                    *bytes_read = count as i32;
+                }
+            }
+            self.position.set(start + count);
+            kResultOk
+        }
+
+        unsafe fn write(
+            &self,
+            buffer: *mut c_void,
+            bytes: i32,
+            bytes_written: *mut i32,
+        ) -> tresult {
+            let bytes = bytes.max(0) as usize;
+            let source = unsafe { std::slice::from_raw_parts(buffer.cast::<u8>(), bytes) };
+            self.data.borrow_mut().extend_from_slice(source);
+            self.position.set(self.position.get() + bytes);
+            if !bytes_written.is_null() {
+                unsafe { *bytes_written = bytes as i32 };
+            }
+            kResultOk
+        }
+
+        unsafe fn seek(&self, _position: i64, _mode: i32, _result: *mut i64) -> tresult {
+            kResultFalse
+        }
+
+        unsafe fn tell(&self, position: *mut i64) -> tresult {
+            unsafe { *position = self.position.get() as i64 };
+            kResultOk
+        }
+    }
+
+    // A single parameter's changes within a block
+    struct ValueQueue {
+        id: ParamID,
+        points: Vec<(i32, ParamValue)>,
+    }
+
+    impl Class for ValueQueue {
+        type Interfaces = (IParamValueQueue,);
+    }
+
+    impl IParamValueQueueTrait for ValueQueue {
+        unsafe fn getParameterId(&self) -> ParamID {
+            self.id
+        }
+
+        unsafe fn getPointCount(&self) -> i32 {
+            self.points.len() as i32
+        }
+
+        unsafe fn getPoint(&self, index: i32, offset: *mut i32, value: *mut ParamValue) -> tresult {
+            let (point_offset, point_value) = self.points[index as usize];
+            unsafe {
+                *offset = point_offset;
+                *value = point_value;
+            }
+            kResultOk
+        }
+
+        unsafe fn addPoint(&self, _offset: i32, _value: ParamValue, _index: *mut i32) -> tresult {
+            kResultFalse
+        }
+    }
+
+    // The parameter changes that are passed to the plugin with a block
+    struct ParameterChanges {
+        queues: Vec<ComPtr<IParamValueQueue>>,
+    }
+
+    impl ParameterChanges {
+        fn new(changes: &[(&str, i32, ParamValue)]) -> Self {
+            let queues = changes
+                .iter()
+                .map(|(id, offset, value)| {
+                    ComWrapper::new(ValueQueue {
+                        id: parameter_id(id),
+                        points: vec![(*offset, *value)],
+                    })
+                    .to_com_ptr()
+                    .unwrap()
+                })
+                .collect();
+            Self { queues }
+        }
+    }
+
+    impl Class for ParameterChanges {
+        type Interfaces = (IParameterChanges,);
+    }
+
+    impl IParameterChangesTrait for ParameterChanges {
+        unsafe fn getParameterCount(&self) -> i32 {
+            self.queues.len() as i32
+        }
+
+        unsafe fn getParameterData(&self, index: i32) -> *mut IParamValueQueue {
+            self.queues[index as usize].as_ptr()
+        }
+
+        unsafe fn addParameterData(
+            &self,
+            _id: *const ParamID,
+            _index: *mut i32,
+        ) -> *mut IParamValueQueue {
+            ptr::null_mut()
+        }
+    }
+
+    fn parameter_id(id: &str) -> ParamID {
+        FreeverbModule::parameter_index(id).unwrap() as ParamID
+    }
+
+    fn factory() -> ComPtr<IPluginFactory2> {
+        let factory = unsafe { ComPtr::from_raw(plugin_factory::<TestDefinition>()) }.unwrap();
+        factory.cast().unwrap()
+    }
+
+    fn make_component() -> ComPtr<IComponent> {
+        let mut instance = ptr::null_mut();
+        let result = unsafe {
+            factory().createInstance(
+                TestDefinition::CLASS_ID.as_ptr(),
+                IComponent::IID.as_ptr().cast(),
+                &mut instance,
+            )
+        };
+        assert_eq!(result, kResultOk);
+
+        let component = unsafe { ComPtr::from_raw(instance.cast::<IComponent>()) }.unwrap();
+        assert_eq!(unsafe { component.initialize(ptr::null_mut()) }, kResultOk);
+        component
+    }
+
+    fn c_string(string: &[i8]) -> &CStr {
+        unsafe { CStr::from_ptr(string.as_ptr()) }
+    }
+
+    // Processes a stereo block, returning the output channels
+    fn process_block(
+        processor: &ComPtr<IAudioProcessor>,
+        input: &mut [[f32; BLOCK_FRAMES]; 2],
+        changes: &[(&str, i32, ParamValue)],
+    ) -> [[f32; BLOCK_FRAMES]; 2] {
+        let mut output = [[0.0; BLOCK_FRAMES]; 2];
+        let mut input_channels = input.each_mut().map(|channel| channel.as_mut_ptr());
+        let mut output_channels = output.each_mut().map(|channel| channel.as_mut_ptr());
+
+        let mut input_bus: AudioBusBuffers = unsafe { std::mem::zeroed() };
+        input_bus.numChannels = 2;
+        input_bus.__field0.channelBuffers32 = input_channels.as_mut_ptr();
+        let mut output_bus: AudioBusBuffers = unsafe { std::mem::zeroed() };
+        output_bus.numChannels = 2;
+        output_bus.__field0.channelBuffers32 = output_channels.as_mut_ptr();
+
+        let changes = ComWrapper::new(ParameterChanges::new(changes));
+        let changes = changes.to_com_ptr::<IParameterChanges>().unwrap();
+
+        let mut data: ProcessData = unsafe { std::mem::zeroed() };
+        data.processMode = ProcessModes_::kRealtime as i32;
+        data.symbolicSampleSize = SymbolicSampleSizes_::kSample32 as i32;
+        data.numSamples = BLOCK_FRAMES as i32;
+        data.numInputs = 1;
+        data.numOutputs = 1;
+        data.inputs = &mut input_bus;
+        data.outputs = &mut output_bus;
+        data.inputParameterChanges = changes.as_ptr();
+
+        assert_eq!(unsafe { processor.process(&mut data) }, kResultOk);
+
+        output
+    }
+
+    #[test]
+    fn class_info() {
+        let factory = factory();
+        assert_eq!(unsafe { factory.countClasses() }, 1);
+
+        let mut info: PClassInfo2 = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { factory.getClassInfo2(0, &mut info) }, kResultOk);
+        assert_eq!(info.cid, TestDefinition::CLASS_ID);
+        assert_eq!(c_string(&info.category), c"Audio Module Class");
+        assert_eq!(c_string(&info.name), c"Freeverb Test");
+        assert_eq!(c_string(&info.subCategories), c"Fx|Reverb");
+        assert_eq!(c_string(&info.vendor), c"freeverb-rs");
+
+        // Unknown classes can't be created
+        let mut instance = ptr::null_mut();
+        let unknown = uid(1, 2, 3, 4);
+        let result = unsafe {
+            factory.createInstance(
+                unknown.as_ptr(),
+                IComponent::IID.as_ptr().cast(),
+                &mut instance,
+            )
+        };
+        assert_eq!(result, kResultFalse);
+        assert!(instance.is_null());
+    }
+
+    #[test]
+    fn parameters() {
+        let controller: ComPtr<IEditController> = make_component().cast().unwrap();
+        assert_eq!(
+            unsafe { controller.getParameterCount() } as usize,
+            FreeverbModule::parameter_count()
+        );
+
+        let room_size = parameter_id("room_size");
+        assert_eq!(unsafe { controller.getParamNormalized(room_size) }, 0.5);
+
+        let mut string = [0; 128];
+        unsafe { controller.getParamStringByValue(room_size, 0.25, &mut string) };
+        assert_eq!(
+            unsafe { strings::read_wstring(string.as_ptr()) }.unwrap(),
+            "25 %"
+        );
+
+        let mut text: Vec<u16> = "80 %".encode_utf16().chain([0]).collect();
+        let mut value = 0.0;
+        let result =
+            unsafe { controller.getParamValueByString(room_size, text.as_mut_ptr(), &mut value) };
+        assert_eq!(result, kResultOk);
+        assert!((value - 0.8).abs() < 1.0e-6);
+
+        // Integer parameters are stepped, with their user range as the plain values
+        let shimmer_pitch = parameter_id("shimmer_pitch");
+        let mut info: ParameterInfo = unsafe { std::mem::zeroed() };
+        let result = unsafe { controller.getParameterInfo(shimmer_pitch as i32, &mut info) };
+        assert_eq!(result, kResultOk);
+        assert_eq!(info.id, shimmer_pitch);
+        assert_eq!(info.stepCount, 12);
+        assert_eq!(info.defaultNormalizedValue, 1.0);
+        assert_eq!(
+            unsafe { controller.normalizedParamToPlain(shimmer_pitch, 0.5) },
+            6.0
+        );
+
+        let tail_mode = parameter_id("tail_mode");
+        unsafe { controller.getParameterInfo(tail_mode as i32, &mut info) };
+        assert_ne!(info.flags & ParameterInfo_::ParameterFlags_::kIsList, 0);
+
+        let count = FreeverbModule::parameter_count() as i32;
+        assert_ne!(
+            unsafe { controller.getParameterInfo(count, &mut info) },
+            kResultOk
+        );
+    }
+
+    #[test]
+    fn state_round_trip() {
+        let room_size = parameter_id("room_size");
+        let freeze = parameter_id("freeze");
+
+        let component = make_component();
+        let controller: ComPtr<IEditController> = component.cast().unwrap();
+        unsafe {
+            controller.setParamNormalized(room_size, 0.75);
+            controller.setParamNormalized(freeze, 1.0);
+        }
+
+        let saved = ComWrapper::new(MemoryStream::default());
+        let stream = saved.to_com_ptr::<IBStream>().unwrap();
+        assert_eq!(unsafe { component.getState(stream.as_ptr()) }, kResultOk);
+        let text = String::from_utf8(saved.data.borrow().clone()).unwrap();
+        assert!(
+            text.contains("room_size = 0.75"),
+            "Unexpected state: {text}"
+        );
+
+        let restored = make_component();
+        let controller: ComPtr<IEditController> = restored.cast().unwrap();
+        saved.position.set(0);
+        assert_eq!(unsafe { restored.setState(stream.as_ptr()) }, kResultOk);
+        assert_eq!(unsafe { controller.getParamNormalized(room_size) }, 0.75);
+        assert_eq!(unsafe { controller.getParamNormalized(freeze) }, 1.0);
+
+        let invalid = ComWrapper::new(MemoryStream::default());
+        invalid.data.borrow_mut().extend_from_slice(b"width = wide");
+        let stream = invalid.to_com_ptr::<IBStream>().unwrap();
+        assert_eq!(unsafe { restored.setState(stream.as_ptr()) }, kResultFalse);
+    }
+
+    #[test]
+    fn processing_with_automation() {
+        let component = make_component();
+        let processor: ComPtr<IAudioProcessor> = component.cast().unwrap();
+        let controller: ComPtr<IEditController> = component.cast().unwrap();
+
+        let mut stereo = SpeakerArr::kStereo;
+        let mut stereo_out = SpeakerArr::kStereo;
+        assert_eq!(
+            unsafe { processor.setBusArrangements(&mut stereo, 1, &mut stereo_out, 1) },
+            kResultTrue
+        );
+        let mut setup = ProcessSetup {
+            processMode: ProcessModes_::kRealtime as i32,
+            symbolicSampleSize: SymbolicSampleSizes_::kSample32 as i32,
+            maxSamplesPerBlock: BLOCK_FRAMES as i32,
+            sampleRate: 44100.0,
+        };
+        unsafe {
+            assert_eq!(processor.setupProcessing(&mut setup), kResultOk);
+            assert_eq!(component.setActive(1), kResultOk);
+            assert_eq!(processor.setProcessing(1), kResultOk);
+        }
+
+        // The dry signal is enabled partway through the block, so only the second impulse is heard
+        // before the reverb's delay lines have filled
+        let mut input = [[0.0; BLOCK_FRAMES]; 2];
+        for channel in input.iter_mut() {
+            channel[0] = 1.0;
+            channel[32] = 1.0;
+        }
+        // Hosts also send automation to the edit controller, which mustn't be applied early
+        unsafe { controller.setParamNormalized(parameter_id("dry"), 1.0) };
+        let output = process_block(&processor, &mut input, &[("dry", 32, 1.0)]);
+        for channel in output {
+            assert_eq!(channel[0], 0.0);
+            assert!(channel[32] > 0.0);
+        }
+
+        // Silencing the wet and dry signals via automation silences the output
+        let changes = [("dry", 0, 0.0), ("wet", 0, 0.0)];
+        let output = process_block(&processor, &mut input, &changes);
+        assert!(output.iter().flatten().all(|sample| *sample == 0.0));
+
+        // Automated values are reported back to the host
+        unsafe {
+            processor.setProcessing(0);
+            component.setActive(0);
+        }
+        assert_eq!(
+            unsafe { controller.getParamNormalized(parameter_id("wet")) },
+            0.0
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use audio_module::{HostParameter, ValueType};
+
+/// A module parameter as it's presented to the host.
+///
+/// VST3 hosts only deal with values in the normalized range `0..=1`, see
+/// [NormalizedValueConverter](audio_module::NormalizedValueConverter). Stepped parameters report
+/// their number of steps to the host, and choices are shown as lists.
+pub(crate) trait Vst3Parameter {
+    /// Returns the number of steps in the parameter's range, or 0 for continuous parameters.
+    fn step_count(&self) -> i32;
+    fn is_list(&self) -> bool;
+    fn default_normalized_value(&self) -> f64;
+    fn user_to_normalized(&self, value: f32) -> f64;
+    fn normalized_to_user(&self, value: f64) -> f32;
+
+    /// Returns the display string for a normalized value.
+    fn normalized_to_string(&self, value: f64) -> String;
+
+    /// Parses a display string or plain user value, returning a normalized value.
+    fn string_to_normalized(&self, text: &str) -> Option<f64>;
+}
+
+impl Vst3Parameter for HostParameter {
+    fn step_count(&self) -> i32 {
+        self.converter().step_count().unwrap_or(0) as i32
+    }
+
+    fn is_list(&self) -> bool {
+        matches!(self.converter().value_type(), ValueType::Choice(_))
+    }
+
+    fn default_normalized_value(&self) -> f64 {
+        self.user_to_normalized(self.default_user_value())
+    }
+
+    fn user_to_normalized(&self, value: f32) -> f64 {
+        self.converter().user_to_normalized(value) as f64
+    }
+
+    fn normalized_to_user(&self, value: f64) -> f32 {
+        self.converter().normalized_to_user(value as f32)
+    }
+
+    fn normalized_to_string(&self, value: f64) -> String {
+        self.user_to_string(self.normalized_to_user(value))
+    }
+
+    fn string_to_normalized(&self, text: &str) -> Option<f64> {
+        self.parse_user_value(text)
+            .map(|value| self.user_to_normalized(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, audio_module::ParameterProvider, freeverb_module::FreeverbModule};
+
+    fn parameter(id: &str) -> HostParameter {
+        let index = FreeverbModule::parameter_index(id).unwrap();
+        HostParameter::all::<FreeverbModule>().swap_remove(index)
+    }
+
+    #[test]
+    fn float_parameters_are_continuous() {
+        let gate_hold = parameter("gate_hold");
+        assert_eq!(gate_hold.step_count(), 0);
+        assert!(!gate_hold.is_list());
+
+        // The skew converter puts the default value in the middle of the range
+        assert!((gate_hold.default_normalized_value() - 0.5).abs() < 1.0e-6);
+        assert!((gate_hold.normalized_to_user(0.5) - 250.0).abs() < 1.0e-3);
+        assert_eq!(gate_hold.normalized_to_user(2.0), 1000.0);
+    }
+
+    #[test]
+    fn stepped_parameters() {
+        let freeze = parameter("freeze");
+        assert_eq!(freeze.step_count(), 1);
+        assert_eq!(freeze.normalized_to_user(0.7), 1.0);
+
+        let shimmer_pitch = parameter("shimmer_pitch");
+        assert_eq!(shimmer_pitch.step_count(), 12);
+        assert_eq!(shimmer_pitch.default_normalized_value(), 1.0);
+        assert_eq!(shimmer_pitch.normalized_to_user(0.5), 6.0);
+        assert_eq!(shimmer_pitch.user_to_normalized(3.0), 0.25);
+        assert_eq!(shimmer_pitch.normalized_to_string(7.0 / 12.0), "7 st");
+
+        let tail_mode = parameter("tail_mode");
+        assert!(tail_mode.is_list());
+        assert_eq!(tail_mode.step_count(), 2);
+        assert_eq!(tail_mode.normalized_to_user(0.6), 1.0);
+        assert_eq!(tail_mode.normalized_to_string(1.0), "Reverse");
+        assert_eq!(tail_mode.string_to_normalized("Gated"), Some(0.5));
+    }
+
+    #[test]
+    fn strings() {
+        let room_size = parameter("room_size");
+        assert_eq!(room_size.normalized_to_string(0.75), "75 %");
+        assert_eq!(room_size.string_to_normalized("25 %"), Some(0.25));
+        assert_eq!(room_size.string_to_normalized("150 %"), None);
+        assert_eq!(room_size.string_to_normalized("big"), None);
+    }
+}
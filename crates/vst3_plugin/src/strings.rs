@@ -0,0 +1,81 @@
+use {std::ffi::c_char, vst3::Steinberg::Vst::TChar};
+
+// The longest UTF-16 string that's read from the host, matching the size of a `String128`
+const MAX_WSTRING_LENGTH: usize = 128;
+
+/// Copies a string into a fixed size, null-terminated C string, truncating it if necessary.
+pub(crate) fn copy_cstring(source: &str, destination: &mut [c_char]) {
+    let Some((last, destination)) = destination.split_last_mut() else {
+        return;
+    };
+
+    let mut length = 0;
+    for (source, destination) in source.bytes().zip(destination.iter_mut()) {
+        *destination = source as c_char;
+        length += 1;
+    }
+
+    if let Some(terminator) = destination.get_mut(length) {
+        *terminator = 0;
+    }
+    *last = 0;
+}
+
+/// Copies a string into a fixed size, null-terminated UTF-16 string, truncating it if necessary.
+pub(crate) fn copy_wstring(source: &str, destination: &mut [TChar]) {
+    let Some((last, destination)) = destination.split_last_mut() else {
+        return;
+    };
+
+    let mut length = 0;
+    for (source, destination) in source.encode_utf16().zip(destination.iter_mut()) {
+        *destination = source;
+        length += 1;
+    }
+
+    if let Some(terminator) = destination.get_mut(length) {
+        *terminator = 0;
+    }
+    *last = 0;
+}
+
+/// Reads a null-terminated UTF-16 string from the host.
+///
+/// Safety: the string must be null-terminated, or valid for `MAX_WSTRING_LENGTH` characters.
+pub(crate) unsafe fn read_wstring(string: *const TChar) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+
+    let mut length = 0;
+    while length < MAX_WSTRING_LENGTH && unsafe { *string.add(length) } != 0 {
+        length += 1;
+    }
+
+    String::from_utf16(unsafe { std::slice::from_raw_parts(string, length) }).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_strings() {
+        let mut c_string = [1 as c_char; 4];
+        copy_cstring("Freeverb", &mut c_string);
+        assert_eq!(
+            c_string,
+            [b'F' as c_char, b'r' as c_char, b'e' as c_char, 0]
+        );
+
+        let mut w_string = [1; 8];
+        copy_wstring("Dry", &mut w_string);
+        assert_eq!(unsafe { read_wstring(w_string.as_ptr()) }.unwrap(), "Dry");
+
+        copy_wstring("Room Size", &mut w_string);
+        assert_eq!(
+            unsafe { read_wstring(w_string.as_ptr()) }.unwrap(),
+            "Room Si"
+        );
+    }
+}
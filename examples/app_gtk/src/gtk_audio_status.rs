@@ -0,0 +1,59 @@
+use {
+    cpal_host::AudioEngine,
+    gtk::{Label, prelude::*},
+    std::cell::Cell,
+};
+
+/// Shows the audio engine's xrun and drift compensation counters.
+pub struct AudioStatus {
+    label: Label,
+    // The number of failed commands that have already been reported
+    reported_failed_commands: Cell<u64>,
+}
+
+impl AudioStatus {
+    /// Updates the label with the engine's current counters, and reports any stream errors and
+    /// commands that the processor failed to handle.
+    pub fn update(&self, engine: &AudioEngine) {
+        while let Some(error) = engine.try_recv_error() {
+            eprintln!("Error on audio stream: {error}");
+        }
+
+        let stats = engine.stats();
+        let failed_commands = stats.failed_commands();
+        if failed_commands > self.reported_failed_commands.get() {
+            eprintln!(
+                "{} command(s) couldn't be handled by the processor",
+                failed_commands - self.reported_failed_commands.get()
+            );
+            self.reported_failed_commands.set(failed_commands);
+        }
+
+        self.label.set_text(&format!(
+            "Xruns: {} (underruns: {}, overflows: {})  Drift: -{} / +{} frames",
+            stats.xruns(),
+            stats.output_underruns(),
+            stats.input_overflows(),
+            stats.dropped_frames(),
+            stats.inserted_frames(),
+        ));
+    }
+}
+
+/// Makes the status label, which shows the given error if audio couldn't be started.
+pub fn make_audio_status(error: Option<&str>) -> (Label, AudioStatus) {
+    let text = match error {
+        Some(error) => format!("Audio unavailable: {error}"),
+        None => String::new(),
+    };
+    let label = Label::builder().label(text).xalign(0.0).build();
+    label.add_css_class("dim-label");
+
+    (
+        label.clone(),
+        AudioStatus {
+            label,
+            reported_failed_commands: Cell::new(0),
+        },
+    )
+}
@@ -0,0 +1,117 @@
+use {
+    crate::parameter_widgets::ParameterWidgets,
+    audio_module::{Slot, StateHistory},
+    gtk::{
+        Application, ApplicationWindow, Button, Orientation, ToggleButton, gio, glib, prelude::*,
+    },
+    std::{cell::RefCell, rc::Rc},
+};
+
+/// Makes a bar with buttons for A/B comparison and undo/redo, along with keyboard shortcuts.
+///
+/// Changes to the parameter values need to be recorded in the history with [record_changes].
+pub fn make_history_bar(
+    app: &Application,
+    window: &ApplicationWindow,
+    widgets: Rc<ParameterWidgets>,
+    history: Rc<RefCell<StateHistory>>,
+) -> gtk::Box {
+    let undo = gio::SimpleAction::new("undo", None);
+    let redo = gio::SimpleAction::new("redo", None);
+    let slot = gio::SimpleAction::new_stateful("slot", Some(glib::VariantTy::STRING), &"a".into());
+    let copy_a_to_b = gio::SimpleAction::new("copy-a-to-b", None);
+
+    // Pending changes are recorded first, so that the history is up to date before it's used
+    let with_history = move |f: &dyn Fn(&mut StateHistory, &ParameterWidgets)| {
+        let mut history = history.borrow_mut();
+        record_changes(&mut history, &widgets);
+        f(&mut history, &widgets);
+    };
+
+    undo.connect_activate({
+        let with_history = with_history.clone();
+        move |_, _| {
+            with_history(&|history, widgets| {
+                if let Some((id, value)) = history.undo() {
+                    widgets.set_value(id, value);
+                }
+            })
+        }
+    });
+
+    redo.connect_activate({
+        let with_history = with_history.clone();
+        move |_, _| {
+            with_history(&|history, widgets| {
+                if let Some((id, value)) = history.redo() {
+                    widgets.set_value(id, value);
+                }
+            })
+        }
+    });
+
+    slot.connect_change_state({
+        let with_history = with_history.clone();
+        move |action, state| {
+            let Some(state) = state else {
+                return;
+            };
+            let selected = match state.str() {
+                Some("b") => Slot::B,
+                _ => Slot::A,
+            };
+            with_history(&|history, widgets| widgets.set_values(history.select(selected)));
+            action.set_state(state);
+        }
+    });
+
+    copy_a_to_b.connect_activate(move |_, _| {
+        with_history(&|history, widgets| {
+            history.copy(Slot::A, Slot::B);
+            if history.active_slot() == Slot::B {
+                widgets.set_values(history.values());
+            }
+        })
+    });
+
+    window.add_action(&undo);
+    window.add_action(&redo);
+    window.add_action(&slot);
+    window.add_action(&copy_a_to_b);
+
+    app.set_accels_for_action("win.undo", &["<Control>z"]);
+    app.set_accels_for_action("win.redo", &["<Control><Shift>z", "<Control>y"]);
+    app.set_accels_for_action("win.slot::a", &["<Control>1"]);
+    app.set_accels_for_action("win.slot::b", &["<Control>2"]);
+
+    let slot_button = |label: &str, target: &str| {
+        let button = ToggleButton::with_label(label);
+        button.set_action_name(Some("win.slot"));
+        button.set_action_target_value(Some(&target.into()));
+        button
+    };
+
+    let action_button = |label: &str, action: &str| {
+        let button = Button::with_label(label);
+        button.set_action_name(Some(action));
+        button
+    };
+
+    let container = gtk::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(4)
+        .build();
+    container.append(&slot_button("A", "a"));
+    container.append(&slot_button("B", "b"));
+    container.append(&action_button("Copy A → B", "win.copy-a-to-b"));
+    container.append(&action_button("Undo", "win.undo"));
+    container.append(&action_button("Redo", "win.redo"));
+    container
+}
+
+/// Records changes to the parameter values in the history.
+pub fn record_changes(history: &mut StateHistory, widgets: &ParameterWidgets) {
+    for (id, value) in widgets.store().snapshot().into_iter().enumerate() {
+        history.set(id, value);
+    }
+}
@@ -0,0 +1,91 @@
+use {
+    audio_module::{DecibelValueConverter, Notification, ValueConverter},
+    gtk::{Label, LevelBar, Orientation, prelude::*},
+};
+
+// The lowest level shown by the meters
+const METER_MIN_DB: f32 = -60.0;
+
+/// Level meters for the input and output, along with an indicator that shows when the reverb's
+/// tail is active.
+pub struct Meters {
+    input: LevelBar,
+    output: LevelBar,
+    wet: LevelBar,
+    tail: Label,
+    converter: DecibelValueConverter,
+}
+
+impl Meters {
+    /// Updates the meters with a notification from the processor.
+    pub fn update(&self, notification: Notification) {
+        match notification {
+            Notification::Meter {
+                input_peak,
+                output_peak,
+                wet_rms,
+            } => {
+                self.input.set_value(self.level(input_peak));
+                self.output.set_value(self.level(output_peak));
+                self.wet.set_value(self.level(wet_rms));
+            }
+            Notification::TailActive(active) => self.tail.set_sensitive(active),
+            // Parameter changes are picked up from the parameter store
+            Notification::ParameterChanged { .. } => {}
+        }
+    }
+
+    fn level(&self, gain: f32) -> f64 {
+        self.converter.user_to_linear(gain).min(1.0) as f64
+    }
+}
+
+pub fn make_meters() -> (gtk::Box, Meters) {
+    let bars = gtk::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(4)
+        .vexpand(true)
+        .build();
+
+    let make_bar = |name: &str| {
+        let bar = LevelBar::builder()
+            .orientation(Orientation::Vertical)
+            .inverted(true)
+            .vexpand(true)
+            .build();
+
+        let container = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(2)
+            .build();
+        container.append(&bar);
+        container.append(&Label::new(Some(name)));
+        bars.append(&container);
+
+        bar
+    };
+
+    let input = make_bar("In");
+    let output = make_bar("Out");
+    let wet = make_bar("Wet");
+
+    let tail = Label::new(Some("Tail"));
+    tail.set_sensitive(false);
+
+    let container = gtk::Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .build();
+    container.append(&bars);
+    container.append(&tail);
+
+    let meters = Meters {
+        input,
+        output,
+        wet,
+        tail,
+        converter: DecibelValueConverter::new(METER_MIN_DB, 0.0),
+    };
+
+    (container, meters)
+}
@@ -0,0 +1,60 @@
+use {
+    audio_module::{MidiMapping, ParameterProvider},
+    gtk::{Align, DropDown, Label, Orientation, ToggleButton, glib, prelude::*},
+    std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+};
+
+// How often the learn button checks whether a mapping has been learned
+const LEARN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Makes a bar for learning MIDI mappings.
+///
+/// A parameter is chosen from the list, and then after the learn button has been pressed, the
+/// next MIDI controller that's moved is mapped to the parameter.
+pub fn make_midi_learn_bar<Module: ParameterProvider>(
+    mapping: Arc<Mutex<MidiMapping>>,
+) -> gtk::Box {
+    let names: Vec<String> = (0..Module::parameter_count())
+        .map(|id| Module::parameter(id).map_or_else(String::new, |parameter| parameter.name()))
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let selector = DropDown::from_strings(&names);
+
+    let learn_button = ToggleButton::with_label("Learn");
+    learn_button.connect_toggled({
+        let mapping = mapping.clone();
+        let selector = selector.clone();
+        move |button| {
+            let mut mapping = mapping.lock().unwrap();
+            if button.is_active() {
+                mapping.learn(selector.selected() as usize);
+            } else {
+                mapping.cancel_learn();
+            }
+        }
+    });
+
+    // The button is released once the mapping has been learned
+    glib::timeout_add_local(LEARN_POLL_INTERVAL, {
+        let learn_button = learn_button.clone();
+        move || {
+            if learn_button.is_active() && mapping.lock().unwrap().learning().is_none() {
+                learn_button.set_active(false);
+            }
+            glib::ControlFlow::Continue
+        }
+    });
+
+    let container = gtk::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(4)
+        .valign(Align::Center)
+        .build();
+    container.append(&Label::new(Some("MIDI")));
+    container.append(&selector);
+    container.append(&learn_button);
+    container
+}
@@ -0,0 +1,43 @@
+use {
+    crate::parameter_widgets::WidgetUpdater,
+    audio_module::{Parameter, ParameterStore},
+    gtk::{Align, DropDown, Label, Orientation, prelude::*},
+    std::sync::Arc,
+};
+
+pub fn make_dropdown(
+    parameter: Box<dyn Parameter>,
+    id: usize,
+    option_count: usize,
+    store: Arc<ParameterStore>,
+) -> (gtk::Box, WidgetUpdater) {
+    let string_converter = parameter.make_string_converter();
+
+    // The choice's user value is the option's index
+    let options: Vec<String> = (0..option_count)
+        .map(|index| string_converter.to_string(index as f32))
+        .collect();
+    let options: Vec<&str> = options.iter().map(String::as_str).collect();
+
+    let label = Label::new(Some(parameter.name().as_str()));
+
+    let dropdown = DropDown::from_strings(&options);
+    dropdown.set_selected(store.get(id).unwrap_or_default() as u32);
+    dropdown.connect_selected_notify(move |dropdown| {
+        if let Err(error) = store.set(id, dropdown.selected() as f32) {
+            eprintln!("Error while setting parameter: {error}");
+        }
+    });
+
+    let container = gtk::Box::builder()
+        .orientation(Orientation::Vertical)
+        .spacing(2)
+        .valign(Align::Center)
+        .build();
+    container.append(&label);
+    container.append(&dropdown);
+
+    let updater = Box::new(move |value: f32| dropdown.set_selected(value.round() as u32));
+
+    (container, updater)
+}
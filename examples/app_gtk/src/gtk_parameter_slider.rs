@@ -1,22 +1,27 @@
 use {
-    audio_module::{Command, Parameter},
-    gtk::{Label, Orientation, PositionType, Scale, prelude::*},
+    crate::parameter_widgets::WidgetUpdater,
+    audio_module::{Parameter, ParameterStore, StringConverter, ValueConverter},
+    gtk::{Entry, Label, Orientation, Scale, prelude::*},
+    std::{cell::Cell, rc::Rc, sync::Arc},
 };
 
 pub fn make_slider(
     parameter: Box<dyn Parameter>,
     id: usize,
-    command_sender: crossbeam_channel::Sender<Command>,
-) -> gtk::Box {
-    let value_converter = parameter.make_value_converter();
-    let string_converter = parameter.make_string_converter();
+    store: Arc<ParameterStore>,
+) -> (gtk::Box, WidgetUpdater) {
+    let value_converter: Rc<dyn ValueConverter> = parameter.make_value_converter().into();
+    let string_converter: Rc<dyn StringConverter> = parameter.make_string_converter().into();
 
     let label = Label::new(Some(parameter.name().as_str()));
+    let initial_value = store
+        .get(id)
+        .unwrap_or_else(|| parameter.default_user_value());
 
     let adjustment = gtk::Adjustment::builder()
         .lower(0.0)
         .upper(1.0)
-        .value(value_converter.user_to_linear(parameter.default_user_value()) as f64)
+        .value(value_converter.user_to_linear(initial_value) as f64)
         .step_increment(0.01)
         .page_increment(0.1)
         .build();
@@ -24,23 +29,53 @@ pub fn make_slider(
     let scale = Scale::builder()
         .adjustment(&adjustment)
         .inverted(true)
-        .value_pos(PositionType::Bottom)
         .orientation(Orientation::Vertical)
-        .draw_value(true)
-        .digits(2)
+        .draw_value(false)
         .vexpand(true)
         .hexpand(true)
         .build();
 
     scale.set_increments(0.01, 0.001);
-    scale.set_format_value_func(move |_, x| {
-        string_converter.to_string(value_converter.linear_to_user(x as f32))
+
+    // The value is displayed in an entry, which also allows a value to be typed in
+    let entry = Entry::builder()
+        .text(string_converter.to_string(initial_value))
+        .width_chars(8)
+        .xalign(0.5)
+        .build();
+
+    // Set while the slider is following a value from the store, so that the value isn't written
+    // back after being converted to and from the slider's position
+    let following_store = Rc::new(Cell::new(false));
+
+    scale.connect_value_changed({
+        let entry = entry.clone();
+        let value_converter = value_converter.clone();
+        let string_converter = string_converter.clone();
+        let following_store = following_store.clone();
+        move |scale| {
+            let value = value_converter.linear_to_user(scale.value() as f32);
+            entry.set_text(&string_converter.to_string(value));
+            if !following_store.get()
+                && let Err(error) = store.set(id, value)
+            {
+                eprintln!("Error while setting parameter: {error}");
+            }
+        }
     });
 
-    scale.connect_value_changed(move |scale| {
-        command_sender
-            .send(Command::SetParameter(id, scale.value() as f32))
-            .unwrap();
+    entry.connect_activate({
+        let adjustment = adjustment.clone();
+        let value_converter = value_converter.clone();
+        let string_converter = string_converter.clone();
+        move |entry| {
+            if let Some(value) = string_converter.from_string(&entry.text()) {
+                adjustment.set_value(value_converter.user_to_linear(value) as f64);
+            }
+            // Show the value in its canonical form, or restore it if the text couldn't be parsed
+            let value = value_converter.linear_to_user(adjustment.value() as f32);
+            entry.set_text(&string_converter.to_string(value));
+        }
     });
 
     let container = gtk::Box::builder()
@@ -50,6 +85,15 @@ pub fn make_slider(
 
     container.append(&label);
     container.append(&scale);
+    container.append(&entry);
+
+    let updater = Box::new(move |value: f32| {
+        following_store.set(true);
+        adjustment.set_value(value_converter.user_to_linear(value) as f64);
+        following_store.set(false);
+        // The entry shows the exact value rather than the one at the slider's position
+        entry.set_text(&string_converter.to_string(value));
+    });
 
-    container
+    (container, updater)
 }
@@ -1,22 +1,21 @@
 use {
-    audio_module::{Command, Parameter},
+    crate::parameter_widgets::WidgetUpdater,
+    audio_module::{Parameter, ParameterStore},
     gtk::{Align, Orientation, ToggleButton, prelude::*},
+    std::sync::Arc,
 };
 
 pub fn make_toggle(
     parameter: Box<dyn Parameter>,
     id: usize,
-    command_sender: crossbeam_channel::Sender<Command>,
-) -> gtk::Box {
+    store: Arc<ParameterStore>,
+) -> (gtk::Box, WidgetUpdater) {
     let button = ToggleButton::with_label(parameter.name().as_str());
-    button.set_active(parameter.default_user_value() != 0.0);
+    button.set_active(store.get(id).unwrap_or_default() != 0.0);
     button.connect_toggled(move |button| {
-        command_sender
-            .send(Command::SetParameter(
-                id,
-                if button.is_active() { 1.0f32 } else { 0.0f32 },
-            ))
-            .unwrap();
+        if let Err(error) = store.set(id, if button.is_active() { 1.0 } else { 0.0 }) {
+            eprintln!("Error while setting parameter: {error}");
+        }
     });
 
     let container = gtk::Box::builder()
@@ -27,5 +26,8 @@ pub fn make_toggle(
         .valign(Align::Center)
         .build();
     container.append(&button);
-    container
+
+    let updater = Box::new(move |value: f32| button.set_active(value != 0.0));
+
+    (container, updater)
 }
@@ -0,0 +1,90 @@
+use {
+    crate::parameter_widgets::ParameterWidgets,
+    audio_module::{Preset, PresetProvider},
+    gtk::{ApplicationWindow, Button, DropDown, FileDialog, Label, Orientation, gio, prelude::*},
+    std::{fs, path::Path, rc::Rc},
+};
+
+/// Makes a bar containing a selector for the module's factory presets, along with buttons for
+/// saving and loading preset files.
+pub fn make_preset_bar<Module: PresetProvider>(
+    window: &ApplicationWindow,
+    widgets: Rc<ParameterWidgets>,
+) -> gtk::Box {
+    let apply_preset = Rc::new({
+        let widgets = widgets.clone();
+        move |preset: &Preset| widgets.set_values(&preset.user_values::<Module>())
+    });
+
+    let presets = Module::factory_presets();
+    let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+    let selector = DropDown::from_strings(&names);
+    selector.connect_selected_notify({
+        let apply_preset = apply_preset.clone();
+        move |selector| {
+            if let Some(preset) = presets.get(selector.selected() as usize) {
+                apply_preset(preset);
+            }
+        }
+    });
+
+    let save_button = Button::with_label("Save…");
+    save_button.connect_clicked({
+        let window = window.clone();
+        move |_| {
+            let widgets = widgets.clone();
+            FileDialog::builder()
+                .initial_name("preset.txt")
+                .build()
+                .save(Some(&window), None::<&gio::Cancellable>, move |file| {
+                    let Some(path) = file.ok().and_then(|file| file.path()) else {
+                        return;
+                    };
+                    let values = widgets.store().snapshot();
+                    let preset = Preset::from_values::<Module>(&preset_name(&path), &values);
+                    if let Err(error) = fs::write(&path, preset.to_text()) {
+                        eprintln!("Error while saving '{}': {error}", path.display());
+                    }
+                });
+        }
+    });
+
+    let load_button = Button::with_label("Load…");
+    load_button.connect_clicked({
+        let window = window.clone();
+        move |_| {
+            let apply_preset = apply_preset.clone();
+            FileDialog::new().open(Some(&window), None::<&gio::Cancellable>, move |file| {
+                let Some(path) = file.ok().and_then(|file| file.path()) else {
+                    return;
+                };
+                let preset = fs::read_to_string(&path)
+                    .map_err(|error| error.to_string())
+                    .and_then(|text| {
+                        Preset::from_text::<Module>(&text).map_err(|error| error.to_string())
+                    });
+                match preset {
+                    Ok(preset) => apply_preset(&preset),
+                    Err(error) => eprintln!("Error while loading '{}': {error}", path.display()),
+                }
+            });
+        }
+    });
+
+    let container = gtk::Box::builder()
+        .orientation(Orientation::Horizontal)
+        .spacing(4)
+        .build();
+    container.append(&Label::new(Some("Preset")));
+    container.append(&selector);
+    container.append(&save_button);
+    container.append(&load_button);
+    container
+}
+
+// Saved presets are named after their file
+fn preset_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
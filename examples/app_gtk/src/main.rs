@@ -1,28 +1,124 @@
 use {
-    audio_module::{AudioModule, ValueType},
+    audio_module::{
+        AudioModule, ParameterStore, PresetProvider, StateHistory, ValueType, notification_channel,
+    },
+    cpal_host::HostConfig,
     freeverb_module::FreeverbModule,
-    gtk::{Application, ApplicationWindow, Orientation, prelude::*},
+    gtk::{Application, ApplicationWindow, Orientation, glib, prelude::*},
+    parameter_widgets::ParameterWidgets,
+    std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration},
 };
 
 mod audio_thread;
+mod gtk_audio_status;
+mod gtk_history;
+mod gtk_meters;
+#[cfg(feature = "midi")]
+mod gtk_midi_learn;
+mod gtk_parameter_dropdown;
 mod gtk_parameter_slider;
 mod gtk_parameter_toggle;
+mod gtk_presets;
+#[cfg(feature = "midi")]
+mod midi_input;
+mod parameter_widgets;
+
+// The number of input frames that are buffered ahead of the output
+const AUDIO_LATENCY_FRAMES: u32 = 256;
+
+// The number of commands that can be queued for the audio thread
+const COMMAND_CAPACITY: usize = 1024;
+
+// Notes and the sustain pedal hold this parameter when no MIDI mappings have been saved, so that
+// the app can be played as a 'freeze pad'
+#[cfg(feature = "midi")]
+const MIDI_HOLD_PARAMETER: &str = "freeze";
+
+// The UDP port that OSC messages are received on, and the prefix of the OSC addresses
+#[cfg(feature = "osc")]
+const OSC_PORT: u16 = 9000;
+#[cfg(feature = "osc")]
+const OSC_PREFIX: &str = "/freeverb";
+
+// The number of notifications from the audio thread that can be queued before they're dropped
+const NOTIFICATION_CAPACITY: usize = 256;
+
+// The number of changes that can be undone in each of the A/B slots
+const MAX_UNDO_STEPS: usize = 100;
+
+// How often the GUI checks for parameter changes and notifications from the audio thread
+const GUI_UPDATE_INTERVAL: Duration = Duration::from_millis(50);
 
 fn main() {
     run_main::<FreeverbModule>();
 }
 
-fn run_main<Module: AudioModule>() {
+fn run_main<Module: AudioModule + PresetProvider>() {
     if gtk::init().is_err() {
         println!("Error initializing GTK");
         return;
     }
 
-    let (command_sender, command_receiver) = crossbeam_channel::bounded(1024);
+    let store = Arc::new(ParameterStore::with_defaults::<Module>());
+
+    let (notification_sender, notification_receiver) = notification_channel(NOTIFICATION_CAPACITY);
 
-    let sample_rate = 44100;
-    let _audio_streams = audio_thread::start_audio::<Module>(command_receiver, sample_rate)
-        .expect("Failed to start audio");
+    let (command_sender, command_receiver) = crossbeam_channel::bounded(COMMAND_CAPACITY);
+
+    // The host's default buffer size is used, with the engine handling callbacks of any size
+    let audio_config = HostConfig {
+        latency_frames: AUDIO_LATENCY_FRAMES,
+        ..Default::default()
+    };
+    // The GUI is still shown if audio can't be started, with the error shown in the status bar
+    let (audio_engine, audio_error) = match audio_thread::start_audio::<Module>(
+        &audio_config,
+        store.clone(),
+        notification_sender,
+        command_receiver,
+    ) {
+        Ok(engine) => (Some(Rc::new(engine)), None),
+        Err(error) => {
+            eprintln!("Failed to start audio: {error}");
+            (None, Some(error.to_string()))
+        }
+    };
+
+    #[cfg(feature = "osc")]
+    match osc_control::OscServer::start::<Module>(
+        OSC_PORT,
+        OSC_PREFIX,
+        store.clone(),
+        command_sender.clone(),
+    ) {
+        Ok(_) => println!("Receiving OSC on port {OSC_PORT}"),
+        Err(error) => eprintln!("OSC input is unavailable: {error}"),
+    }
+
+    #[cfg(feature = "midi")]
+    let (midi_mapping, _midi_connection) = {
+        let (mapping, save_path) = midi_input::load_mappings::<Module>(
+            &midi_input::mappings_path(),
+            Some(MIDI_HOLD_PARAMETER),
+        );
+        let mapping = std::sync::Arc::new(std::sync::Mutex::new(mapping));
+        let connection =
+            match midi_input::start_midi::<Module>(mapping.clone(), save_path, command_sender) {
+                Ok(connection) => Some(connection),
+                Err(error) => {
+                    eprintln!("MIDI input is unavailable: {error}");
+                    None
+                }
+            };
+        (mapping, connection)
+    };
+
+    // Without MIDI input there's no need to keep the sender alive here
+    #[cfg(not(feature = "midi"))]
+    drop(command_sender);
+
+    // The receiver is moved into the GUI's update closure when the window is activated
+    let notification_receiver = RefCell::new(Some(notification_receiver));
 
     let app = Application::builder()
         .application_id("org.example.freeverb-rs")
@@ -44,20 +140,77 @@ fn run_main<Module: AudioModule>() {
             .vexpand(false)
             .build();
 
+        let mut updaters = Vec::new();
+
         for id in 0..Module::parameter_count() {
-            let parameter = Module::parameter(id);
-            let widget = match parameter.value_type() {
-                ValueType::Float => {
-                    gtk_parameter_slider::make_slider(parameter, id, command_sender.clone())
-                }
-                ValueType::Bool => {
-                    gtk_parameter_toggle::make_toggle(parameter, id, command_sender.clone())
+            let Some(parameter) = Module::parameter(id) else {
+                eprintln!("Missing parameter for id {id}");
+                updaters.push(None);
+                continue;
+            };
+            let (widget, updater) = match parameter.value_type() {
+                ValueType::Float | ValueType::Int { .. } => {
+                    gtk_parameter_slider::make_slider(parameter, id, store.clone())
                 }
+                ValueType::Bool => gtk_parameter_toggle::make_toggle(parameter, id, store.clone()),
+                ValueType::Choice(option_count) => gtk_parameter_dropdown::make_dropdown(
+                    parameter,
+                    id,
+                    option_count,
+                    store.clone(),
+                ),
             };
             widgets.append(&widget);
+            updaters.push(Some(updater));
         }
 
-        window.set_child(Some(&widgets));
+        let (meters_widget, meters) = gtk_meters::make_meters();
+        widgets.append(&meters_widget);
+
+        let parameter_widgets = Rc::new(ParameterWidgets::new(store.clone(), updaters));
+        let history = Rc::new(RefCell::new(StateHistory::with_defaults::<Module>(
+            MAX_UNDO_STEPS,
+        )));
+
+        let preset_bar = gtk_presets::make_preset_bar::<Module>(&window, parameter_widgets.clone());
+        let history_bar =
+            gtk_history::make_history_bar(app, &window, parameter_widgets.clone(), history.clone());
+
+        let (audio_status_widget, audio_status) =
+            gtk_audio_status::make_audio_status(audio_error.as_deref());
+
+        // Reflect changes that were made on the audio thread, e.g. by automation, record changes
+        // in the history, and show the processor's notifications and the engine's status
+        let mut notification_receiver = notification_receiver.borrow_mut().take();
+        let audio_engine = audio_engine.clone();
+        glib::timeout_add_local(GUI_UPDATE_INTERVAL, move || {
+            parameter_widgets.pull_audio_changes();
+            gtk_history::record_changes(&mut history.borrow_mut(), &parameter_widgets);
+            if let Some(receiver) = &mut notification_receiver {
+                while let Some(notification) = receiver.try_recv() {
+                    meters.update(notification);
+                }
+            }
+            if let Some(engine) = &audio_engine {
+                audio_status.update(engine);
+            }
+            glib::ControlFlow::Continue
+        });
+
+        let content = gtk::Box::builder()
+            .orientation(Orientation::Vertical)
+            .spacing(4)
+            .build();
+        content.append(&preset_bar);
+        content.append(&history_bar);
+        #[cfg(feature = "midi")]
+        content.append(&gtk_midi_learn::make_midi_learn_bar::<Module>(
+            midi_mapping.clone(),
+        ));
+        content.append(&widgets);
+        content.append(&audio_status_widget);
+
+        window.set_child(Some(&content));
 
         window.present();
     });
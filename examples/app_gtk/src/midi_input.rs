@@ -0,0 +1,108 @@
+use {
+    audio_module::{Command, MidiMapping, MidiMessage, ParameterProvider},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Returns the path of the file that learned MIDI mappings are saved to.
+pub fn mappings_path() -> PathBuf {
+    gtk::glib::user_config_dir()
+        .join("freeverb-rs")
+        .join("midi_mappings.txt")
+}
+
+/// Loads MIDI mappings from a file, falling back to mappings that only contain the hold parameter
+/// if the file doesn't exist or can't be parsed.
+///
+/// Along with the mappings, the path that learned mappings should be saved to is returned. A file
+/// that couldn't be parsed isn't overwritten, so that the user's mappings aren't lost.
+pub fn load_mappings<Module: ParameterProvider>(
+    path: &Path,
+    hold_parameter: Option<&str>,
+) -> (MidiMapping, Option<PathBuf>) {
+    let default_mapping = || {
+        let mut mapping = MidiMapping::new();
+        mapping.set_hold_parameter(hold_parameter);
+        mapping
+    };
+
+    match fs::read_to_string(path) {
+        Ok(text) => match MidiMapping::from_text::<Module>(&text) {
+            Ok(mapping) => (mapping, Some(path.to_path_buf())),
+            Err(error) => {
+                eprintln!(
+                    "Error while loading '{}': {error}, learned MIDI mappings won't be saved \
+                     until the file has been fixed or removed",
+                    path.display()
+                );
+                (default_mapping(), None)
+            }
+        },
+        Err(_) => (default_mapping(), Some(path.to_path_buf())),
+    }
+}
+
+fn save_mappings(mapping: &MidiMapping, path: &Path) {
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, mapping.to_text()));
+    if let Err(error) = result {
+        eprintln!("Error while saving '{}': {error}", path.display());
+    }
+}
+
+/// Connects to the first available MIDI input port, sending the commands produced by the mapping
+/// to the audio thread.
+///
+/// The mappings are saved to `mappings_path` whenever a new mapping has been learned, if a path is
+/// provided.
+pub fn start_midi<Module: ParameterProvider>(
+    mapping: Arc<Mutex<MidiMapping>>,
+    mappings_path: Option<PathBuf>,
+    command_sender: crossbeam_channel::Sender<Command>,
+) -> Result<midir::MidiInputConnection<()>, String> {
+    let input = midir::MidiInput::new("freeverb-rs").map_err(|error| error.to_string())?;
+
+    let port = input
+        .ports()
+        .into_iter()
+        .next()
+        .ok_or("no MIDI input ports are available")?;
+    let port_name = input.port_name(&port).unwrap_or_default();
+
+    let connection = input
+        .connect(
+            &port,
+            "freeverb-rs input",
+            move |_timestamp, bytes, _| {
+                let Some(message) = MidiMessage::parse(bytes) else {
+                    return;
+                };
+
+                let mut mapping = mapping.lock().unwrap();
+                let was_learning = mapping.learning().is_some();
+                let command = mapping.handle::<Module>(message);
+                if was_learning
+                    && mapping.learning().is_none()
+                    && let Some(path) = &mappings_path
+                {
+                    save_mappings(&mapping, path);
+                }
+
+                if let Some(command) = command
+                    && command_sender.try_send(command).is_err()
+                {
+                    eprintln!("MIDI command queue is full");
+                }
+            },
+            (),
+        )
+        .map_err(|error| error.to_string())?;
+
+    println!("Receiving MIDI from '{port_name}'");
+    Ok(connection)
+}
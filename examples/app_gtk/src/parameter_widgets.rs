@@ -0,0 +1,47 @@
+use {audio_module::ParameterStore, std::sync::Arc};
+
+/// Updates a parameter's widget to show a user value.
+pub type WidgetUpdater = Box<dyn Fn(f32)>;
+
+/// The parameter store along with the widgets that display its values.
+pub struct ParameterWidgets {
+    store: Arc<ParameterStore>,
+    updaters: Vec<Option<WidgetUpdater>>,
+}
+
+impl ParameterWidgets {
+    pub fn new(store: Arc<ParameterStore>, updaters: Vec<Option<WidgetUpdater>>) -> Self {
+        Self { store, updaters }
+    }
+
+    pub fn store(&self) -> &Arc<ParameterStore> {
+        &self.store
+    }
+
+    /// Sets a parameter's value, updating its widget to match.
+    pub fn set_value(&self, id: usize, value: f32) {
+        if let Err(error) = self.store.set(id, value) {
+            eprintln!("Error while setting parameter: {error}");
+        }
+        self.update_widget(id, value);
+    }
+
+    /// Sets the values of all parameters, indexed by parameter id.
+    pub fn set_values(&self, values: &[f32]) {
+        for (id, value) in values.iter().enumerate() {
+            self.set_value(id, *value);
+        }
+    }
+
+    /// Updates the widgets of parameters that were changed on the audio thread.
+    pub fn pull_audio_changes(&self) {
+        self.store
+            .pull_gui_changes(|id, value| self.update_widget(id, value));
+    }
+
+    fn update_widget(&self, id: usize, value: f32) {
+        if let Some(Some(updater)) = self.updaters.get(id) {
+            updater(value);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use {
+    audio_module::{AudioModule, AudioProcessor, Command, CommandHandler, PresetProvider},
+    clap::Parser,
+    freeverb_module::FreeverbModule,
+    std::{
+        path::{Path, PathBuf},
+        process,
+    },
+};
+
+mod parameters;
+
+const CHANNELS: usize = 2;
+const FRAMES_PER_BUFFER: usize = 512;
+
+/// Applies Freeverb to a WAV file
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// The WAV file to process, in mono or stereo
+    #[arg(required_unless_present_any = ["list_parameters", "list_presets"])]
+    input: Option<PathBuf>,
+
+    /// The stereo WAV file to write
+    #[arg(required_unless_present_any = ["list_parameters", "list_presets"])]
+    output: Option<PathBuf>,
+
+    /// The name of a factory preset to start from, e.g. `Cathedral`
+    #[arg(short, long)]
+    factory_preset: Option<String>,
+
+    /// A preset file containing `parameter = value` lines, with other parameters at their defaults
+    #[arg(short, long, conflicts_with = "factory_preset")]
+    preset: Option<PathBuf>,
+
+    /// Sets a parameter's value, e.g. `--set room_size=0.8`, overriding the presets
+    #[arg(short, long, value_name = "PARAMETER=VALUE")]
+    set: Vec<String>,
+
+    /// The length of the reverb tail to append after the end of the input, in seconds
+    ///
+    /// Defaults to the tail length reported by the processor.
+    #[arg(short, long)]
+    tail: Option<f32>,
+
+    /// Lists the available parameters and exits
+    #[arg(long)]
+    list_parameters: bool,
+
+    /// Lists the factory presets and exits
+    #[arg(long)]
+    list_presets: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    if args.list_parameters || args.list_presets {
+        if args.list_parameters {
+            parameters::print_parameters::<FreeverbModule>();
+        }
+        if args.list_presets {
+            parameters::print_factory_presets::<FreeverbModule>();
+        }
+        return;
+    }
+
+    let (Some(input), Some(output)) = (&args.input, &args.output) else {
+        unreachable!("input and output are required");
+    };
+
+    if let Err(error) = run::<FreeverbModule>(input, output, &args) {
+        eprintln!("Error: {error}");
+        process::exit(1);
+    }
+}
+
+fn run<Module: AudioModule + PresetProvider>(
+    input: &Path,
+    output: &Path,
+    args: &Args,
+) -> Result<(), String> {
+    let mut reader = hound::WavReader::open(input)
+        .map_err(|error| format!("failed to open '{}': {error}", input.display()))?;
+    let spec = reader.spec();
+
+    let input_channels = spec.channels as usize;
+    if input_channels != 1 && input_channels != 2 {
+        return Err(format!(
+            "unsupported channel count ({input_channels}), only mono and stereo input is supported"
+        ));
+    }
+
+    let samples = read_samples(&mut reader)?;
+
+    let mut values = parameters::default_values::<Module>();
+    if let Some(name) = &args.factory_preset {
+        parameters::apply_factory_preset::<Module>(name, &mut values)?;
+    }
+    if let Some(preset) = &args.preset {
+        parameters::apply_preset_file::<Module>(preset, &mut values)?;
+    }
+    for assignment in args.set.iter() {
+        parameters::apply_assignment::<Module>(assignment, &mut values)?;
+    }
+
+    let sample_rate = spec.sample_rate as usize;
+    let mut processor = Module::create_processor(sample_rate);
+    for (id, value) in values.into_iter().enumerate() {
+        processor
+            .handle_command(Command::SetParameter(id, value))
+            .map_err(|error| format!("failed to set parameter: {error}"))?;
+    }
+
+    let input_frames = samples.len() / input_channels;
+    let tail_frames = match args.tail {
+        Some(tail) => (tail.max(0.0) * sample_rate as f32) as usize,
+        None => processor
+            .tail_samples()
+            .ok_or("the processor's tail is infinite with these settings, please specify --tail")?,
+    };
+    // Any latency is compensated for by dropping frames from the start of the output
+    let latency_frames = processor.latency_samples();
+
+    let mut writer = hound::WavWriter::create(
+        output,
+        hound::WavSpec {
+            channels: CHANNELS as u16,
+            sample_rate: spec.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        },
+    )
+    .map_err(|error| format!("failed to create '{}': {error}", output.display()))?;
+
+    let mut input_buffer = [0.0f32; FRAMES_PER_BUFFER * CHANNELS];
+    let mut output_buffer = [0.0f32; FRAMES_PER_BUFFER * CHANNELS];
+
+    let total_frames = input_frames + tail_frames + latency_frames;
+    for start in (0..total_frames).step_by(FRAMES_PER_BUFFER) {
+        let frames = FRAMES_PER_BUFFER.min(total_frames - start);
+        let input = &mut input_buffer[..frames * CHANNELS];
+        let output = &mut output_buffer[..frames * CHANNELS];
+
+        // Mono input is copied to both channels, and the tail is processed with silent input
+        for (i, frame) in input.chunks_exact_mut(CHANNELS).enumerate() {
+            let source = start + i;
+            if source < input_frames {
+                let left = samples[source * input_channels];
+                let right = samples[source * input_channels + input_channels - 1];
+                frame.copy_from_slice(&[left, right]);
+            } else {
+                frame.fill(0.0);
+            }
+        }
+
+        processor.process(input, output, CHANNELS as u32);
+
+        let skipped_frames = latency_frames.saturating_sub(start).min(frames);
+        for sample in output[skipped_frames * CHANNELS..].iter() {
+            writer
+                .write_sample(*sample)
+                .map_err(|error| format!("failed to write output: {error}"))?;
+        }
+    }
+
+    writer
+        .finalize()
+        .map_err(|error| format!("failed to finalize output: {error}"))
+}
+
+fn read_samples<R: std::io::Read>(reader: &mut hound::WavReader<R>) -> Result<Vec<f32>, String> {
+    let spec = reader.spec();
+
+    let samples: Result<Vec<f32>, _> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect(),
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u32 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect()
+        }
+    };
+
+    samples.map_err(|error| format!("failed to read input: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, clap::CommandFactory};
+
+    #[test]
+    fn verify_args() {
+        Args::command().debug_assert();
+    }
+}
@@ -0,0 +1,141 @@
+use {
+    audio_module::{ParameterProvider, Preset, PresetProvider},
+    std::{fs, path::Path},
+};
+
+/// Returns the default user values for all of the module's parameters.
+pub fn default_values<Module: ParameterProvider>() -> Vec<f32> {
+    (0..Module::parameter_count())
+        .map(|id| Module::parameter(id).map_or(0.0, |parameter| parameter.default_user_value()))
+        .collect()
+}
+
+/// Prints the parameters' ids along with their default values.
+pub fn print_parameters<Module: ParameterProvider>() {
+    for parameter in (0..Module::parameter_count()).filter_map(Module::parameter) {
+        let string_converter = parameter.make_string_converter();
+        println!(
+            "{} (default: {})",
+            parameter.id(),
+            string_converter.to_string(parameter.default_user_value())
+        );
+    }
+}
+
+/// Prints the names of the module's factory presets.
+pub fn print_factory_presets<Module: PresetProvider>() {
+    for preset in Module::factory_presets() {
+        println!("{}", preset.name);
+    }
+}
+
+/// Applies the factory preset with the given name, ignoring case.
+pub fn apply_factory_preset<Module: PresetProvider>(
+    name: &str,
+    values: &mut [f32],
+) -> Result<(), String> {
+    let preset =
+        Module::factory_preset(name).ok_or_else(|| format!("unknown factory preset '{name}'"))?;
+
+    apply_preset::<Module>(&preset, values);
+    Ok(())
+}
+
+/// Applies the values in a preset file, see [Preset::from_text] for the format.
+pub fn apply_preset_file<Module: ParameterProvider>(
+    path: &Path,
+    values: &mut [f32],
+) -> Result<(), String> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read preset '{}': {error}", path.display()))?;
+    let preset = Preset::from_text::<Module>(&text)
+        .map_err(|error| format!("{}: {error}", path.display()))?;
+
+    apply_preset::<Module>(&preset, values);
+    Ok(())
+}
+
+// Parameters that aren't in the preset are reset to their defaults, see [Preset]
+fn apply_preset<Module: ParameterProvider>(preset: &Preset, values: &mut [f32]) {
+    values.copy_from_slice(&preset.user_values::<Module>());
+}
+
+/// Applies a `parameter=value` assignment, see [audio_module::parse_assignment].
+pub fn apply_assignment<Module: ParameterProvider>(
+    assignment: &str,
+    values: &mut [f32],
+) -> Result<(), String> {
+    let (index, value) =
+        audio_module::parse_assignment::<Module>(assignment).map_err(|error| error.to_string())?;
+    values[index] = value;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, freeverb_module::FreeverbModule, std::env};
+
+    fn index(id: &str) -> usize {
+        FreeverbModule::parameter_index(id).unwrap()
+    }
+
+    #[test]
+    fn assignments() {
+        let mut values = default_values::<FreeverbModule>();
+
+        apply_assignment::<FreeverbModule>("room_size=0.8", &mut values).unwrap();
+        apply_assignment::<FreeverbModule>(" freeze = on ", &mut values).unwrap();
+        assert_eq!(values[index("room_size")], 0.8);
+        assert_eq!(values[index("freeze")], 1.0);
+
+        let defaults = default_values::<FreeverbModule>();
+        let mut values = defaults.clone();
+        assert!(apply_assignment::<FreeverbModule>("room_size", &mut values).is_err());
+        assert!(apply_assignment::<FreeverbModule>("size=0.5", &mut values).is_err());
+        assert!(apply_assignment::<FreeverbModule>("room_size=big", &mut values).is_err());
+        // Values outside of a parameter's range are rejected rather than passed to the processor
+        assert!(apply_assignment::<FreeverbModule>("wet=-6", &mut values).is_err());
+        assert!(apply_assignment::<FreeverbModule>("room_size=35", &mut values).is_err());
+        assert_eq!(values, defaults);
+
+        // Integer values are rounded
+        apply_assignment::<FreeverbModule>("shimmer_pitch=6.6", &mut values).unwrap();
+        assert_eq!(values[index("shimmer_pitch")], 7.0);
+    }
+
+    #[test]
+    fn preset_files() {
+        let folder = env::temp_dir().join(format!("freeverb-cli-test-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+
+        let valid = folder.join("valid.preset");
+        fs::write(&valid, "name = Test\nroom_size = 25 %\n").unwrap();
+        let mut values = default_values::<FreeverbModule>();
+        apply_assignment::<FreeverbModule>("freeze=on", &mut values).unwrap();
+        apply_preset_file::<FreeverbModule>(&valid, &mut values).unwrap();
+        assert_eq!(values[index("room_size")], 0.25);
+        // Parameters that aren't in the preset are reset to their defaults
+        assert_eq!(values[index("freeze")], 0.0);
+
+        let unknown = folder.join("unknown.preset");
+        fs::write(&unknown, "size = 0.5\n").unwrap();
+        let error = apply_preset_file::<FreeverbModule>(&unknown, &mut values).unwrap_err();
+        assert!(error.contains("unknown parameter 'size'"), "{error}");
+
+        let invalid = folder.join("invalid.preset");
+        fs::write(&invalid, "room_size = big\n").unwrap();
+        let error = apply_preset_file::<FreeverbModule>(&invalid, &mut values).unwrap_err();
+        assert!(error.contains("invalid value for 'room_size'"), "{error}");
+
+        let out_of_range = folder.join("out_of_range.preset");
+        fs::write(&out_of_range, "room_size = 25 %\nwet = -6\n").unwrap();
+        let error = apply_preset_file::<FreeverbModule>(&out_of_range, &mut values).unwrap_err();
+        assert!(error.contains("line 2: invalid value for 'wet'"), "{error}");
+
+        let missing = folder.join("missing.preset");
+        assert!(apply_preset_file::<FreeverbModule>(&missing, &mut values).is_err());
+        assert_eq!(values[index("room_size")], 0.25);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use {
+    audio_module::{AudioModule, AudioProcessor, ParameterStore, PresetProvider},
+    clap::Parser,
+    cpal_host::{AudioEngine, HostConfig, StreamError},
+    freeverb_module::FreeverbModule,
+    osc_control::OscServer,
+    std::{process, sync::Arc, time::Duration},
+};
+
+// The number of commands that can be queued for the audio thread
+const COMMAND_CAPACITY: usize = 1024;
+
+// How often the engine's counters are checked for failed commands
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs Freeverb on audio devices without a GUI
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// The audio host to use, e.g. `ALSA` or `JACK`, defaults to the system's default host
+    #[arg(long)]
+    host: Option<String>,
+
+    /// The name of the input device, defaults to the host's default input device
+    #[arg(short, long)]
+    input_device: Option<String>,
+
+    /// The name of the output device, defaults to the host's default output device
+    #[arg(short, long)]
+    output_device: Option<String>,
+
+    /// The sample rate in Hz
+    #[arg(short = 'r', long, default_value_t = 44100)]
+    sample_rate: u32,
+
+    /// The number of frames per audio callback, defaults to the host's buffer size
+    #[arg(short, long)]
+    buffer_size: Option<u32>,
+
+    /// The number of channels to open on each device
+    #[arg(short, long, default_value_t = 2)]
+    channels: u16,
+
+    /// The number of input frames to buffer ahead of the output, increase this if underruns occur
+    #[arg(short, long, default_value_t = 256)]
+    latency: u32,
+
+    /// Only opens an output device, with the reverb being fed with silence
+    #[arg(long)]
+    output_only: bool,
+
+    /// The name of a factory preset to start from, e.g. `Cathedral`
+    #[arg(short, long)]
+    factory_preset: Option<String>,
+
+    /// Sets a parameter's value, e.g. `--set room_size=0.8`, overriding the factory preset
+    #[arg(short, long, value_name = "PARAMETER=VALUE")]
+    set: Vec<String>,
+
+    /// Receives OSC messages on the given UDP port, e.g. `/freeverb/room_size 0.8`
+    #[arg(long)]
+    osc_port: Option<u16>,
+
+    /// The prefix of the OSC addresses
+    #[arg(long, default_value = "/freeverb")]
+    osc_prefix: String,
+
+    /// Lists the available audio hosts and exits
+    #[arg(long)]
+    list_hosts: bool,
+
+    /// Lists the selected host's devices and exits
+    #[arg(long)]
+    list_devices: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let result = if args.list_hosts {
+        for name in cpal_host::host_names() {
+            println!("{name}");
+        }
+        Ok(())
+    } else if args.list_devices {
+        list_devices(args.host.as_deref())
+    } else {
+        run::<FreeverbModule>(&args)
+    };
+
+    if let Err(error) = result {
+        eprintln!("Error: {error}");
+        process::exit(1);
+    }
+}
+
+fn list_devices(host: Option<&str>) -> Result<(), String> {
+    let devices = cpal_host::list_devices(host).map_err(|error| error.to_string())?;
+    for device in devices {
+        let directions = match (device.is_input, device.is_output) {
+            (true, true) => "input/output",
+            (true, false) => "input",
+            (false, true) => "output",
+            (false, false) => "unknown",
+        };
+        let default = if device.is_default { ", default" } else { "" };
+        println!("{} ({directions}{default})", device.name);
+    }
+    Ok(())
+}
+
+fn run<Module: AudioModule + PresetProvider>(args: &Args) -> Result<(), String> {
+    let store = Arc::new(ParameterStore::new(&initial_values::<Module>(args)?));
+
+    let config = HostConfig {
+        host: args.host.clone(),
+        input_device: args.input_device.clone(),
+        output_device: args.output_device.clone(),
+        sample_rate: args.sample_rate,
+        buffer_size: args.buffer_size,
+        channels: args.channels,
+        duplex: !args.output_only,
+        latency_frames: args.latency,
+    };
+
+    let mut processor = Module::create_processor(config.sample_rate as usize);
+    // The processor picks up the initial values from the store when it first runs
+    processor.set_parameter_store(store.clone());
+
+    let (command_sender, command_receiver) = crossbeam_channel::bounded(COMMAND_CAPACITY);
+
+    if let Some(port) = args.osc_port {
+        OscServer::start::<Module>(port, &args.osc_prefix, store, command_sender)
+            .map_err(|error| format!("failed to start OSC on port {port}: {error}"))?;
+        println!("Receiving OSC on port {port}");
+    }
+
+    let engine = AudioEngine::start(&config, processor, command_receiver)
+        .map_err(|error| error.to_string())?;
+
+    match engine.input_device_name() {
+        Some(input) => println!("Processing '{input}' -> '{}'", engine.output_device_name()),
+        None => println!("Playing to '{}'", engine.output_device_name()),
+    }
+
+    let stats = engine.stats();
+    let mut reported_failed_commands = 0;
+    loop {
+        match engine.wait_for_error_timeout(STATUS_INTERVAL) {
+            Some(StreamError::DeviceNotAvailable) => {
+                return Err("the audio device is no longer available".to_string());
+            }
+            Some(error) => eprintln!("Audio stream error: {error}"),
+            None => {}
+        }
+
+        // Failed commands are counted by the audio thread, and reported here
+        let failed_commands = stats.failed_commands();
+        if failed_commands > reported_failed_commands {
+            eprintln!(
+                "{} command(s) couldn't be handled by the processor",
+                failed_commands - reported_failed_commands
+            );
+            reported_failed_commands = failed_commands;
+        }
+    }
+}
+
+// Returns the values from the factory preset with any assignments applied
+//
+// Parameters that aren't in the factory preset are at their defaults, as in the other examples
+fn initial_values<Module: PresetProvider>(args: &Args) -> Result<Vec<f32>, String> {
+    let mut values = match &args.factory_preset {
+        Some(name) => Module::factory_preset(name)
+            .ok_or_else(|| format!("unknown factory preset '{name}'"))?
+            .user_values::<Module>(),
+        None => ParameterStore::with_defaults::<Module>().snapshot(),
+    };
+
+    for assignment in args.set.iter() {
+        let (index, value) = audio_module::parse_assignment::<Module>(assignment)
+            .map_err(|error| error.to_string())?;
+        values[index] = value;
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, audio_module::ParameterProvider, clap::CommandFactory};
+
+    #[test]
+    fn verify_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn initial_values_from_args() {
+        let args = Args::parse_from([
+            "freeverb-headless",
+            "--factory-preset",
+            "cathedral",
+            "--set",
+            "room_size=25 %",
+        ]);
+        let values = initial_values::<FreeverbModule>(&args).unwrap();
+        let room_size = FreeverbModule::parameter_index("room_size").unwrap();
+        assert_eq!(values[room_size], 0.25);
+
+        let args = Args::parse_from(["freeverb-headless", "--set", "size=1"]);
+        assert!(initial_values::<FreeverbModule>(&args).is_err());
+    }
+}
@@ -0,0 +1,36 @@
+//! Writes the plugin's LV2 bundle, copying in the plugin library if it has been built.
+
+use {
+    freeverb_lv2::Freeverb,
+    std::{
+        env::{
+            self,
+            consts::{DLL_PREFIX, DLL_SUFFIX},
+        },
+        fs,
+        path::PathBuf,
+    },
+};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let folder = PathBuf::from(
+        env::args()
+            .nth(1)
+            .unwrap_or_else(|| "freeverb.lv2".to_string()),
+    );
+    let binary = format!("{DLL_PREFIX}freeverb_lv2{DLL_SUFFIX}");
+
+    lv2_plugin::write_bundle::<Freeverb>(&folder, &binary)?;
+    println!("Wrote the plugin description to {}", folder.display());
+
+    // The library is built to the same folder as this tool
+    let library = env::current_exe()?.with_file_name(&binary);
+    if library.exists() {
+        fs::copy(&library, folder.join(&binary))?;
+        println!("Copied {}", library.display());
+    } else {
+        println!("{binary} wasn't found, build the library and copy it into the bundle");
+    }
+
+    Ok(())
+}
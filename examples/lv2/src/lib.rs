@@ -0,0 +1,17 @@
+use {
+    freeverb_module::FreeverbModule,
+    lv2_plugin::{Lv2Definition, export_lv2_plugin},
+    std::ffi::CStr,
+};
+
+pub struct Freeverb;
+
+impl Lv2Definition for Freeverb {
+    type Module = FreeverbModule;
+
+    const URI: &'static CStr = c"urn:freeverb-rs:freeverb";
+    const NAME: &'static str = "Freeverb";
+    const CLASS: Option<&'static str> = Some("ReverbPlugin");
+}
+
+export_lv2_plugin!(Freeverb);
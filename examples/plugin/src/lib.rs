@@ -0,0 +1,34 @@
+use {
+    clap_plugin::{PluginDefinition, export_clap_plugin, features},
+    freeverb_module::FreeverbModule,
+    std::ffi::CStr,
+    vst3_plugin::{TUID, Vst3Definition, export_vst3_plugin, uid},
+};
+
+struct Freeverb;
+
+impl PluginDefinition for Freeverb {
+    type Module = FreeverbModule;
+
+    const ID: &'static str = "rs.freeverb";
+    const NAME: &'static str = "Freeverb";
+    const VENDOR: &'static str = "freeverb-rs";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const DESCRIPTION: &'static str = "A Rust implementation of the Freeverb algorithm";
+    const FEATURES: &'static [&'static CStr] =
+        &[features::AUDIO_EFFECT, features::REVERB, features::STEREO];
+}
+
+export_clap_plugin!(Freeverb);
+
+impl Vst3Definition for Freeverb {
+    type Module = FreeverbModule;
+
+    const CLASS_ID: TUID = uid(0x8E5C2A47, 0x1D3B4F96, 0xA0E7C951, 0x36B8F2D4);
+    const NAME: &'static str = "Freeverb";
+    const VENDOR: &'static str = "freeverb-rs";
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const SUBCATEGORIES: &'static str = "Fx|Reverb";
+}
+
+export_vst3_plugin!(Freeverb);